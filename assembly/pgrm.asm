@@ -9,6 +9,7 @@
 ; will contain the index of the element that contains the “value of interest”.
 ; If the “value of interest” is not in the array, then at the end of execution,
 ; R6 will contain –1.
+; Each element of the array is a 4-byte word.
 
 ; Equivalent C-like pseudo-code:
 ; r6 = -1 // Initially, the value has not yet been.
@@ -24,12 +25,12 @@
             mov r5, #20      ; # Elements in array.
             mov r4, #0x1234  ; Start address
             mov r3, #'a'     ; Value of interest.
-            str r3, [r4, #4] ; Store value of interest at array[2].
+            str r3, [r4, #8] ; Store value of interest at array[2].
 
             mvn r6, #0       ; r6 = -1
             mov r7, #0       ; Initialize loop r7 = 0.
             b   TestForDone  ; Test for done at end of loop!
-DoFor       ldr r8, [r4, r7] ; Get element array[r7]
+DoFor       ldr r8, [r4, r7, lsl #2] ; Get element array[r7]
             cmp r8, r3       ; Element == value of interest.
             bne IncR7        ; No  - Continue loop.
             mov r6, r7       ; Yes - Save index.
@@ -37,4 +38,4 @@ DoFor       ldr r8, [r4, r7] ; Get element array[r7]
 IncR7       add r7, r7, #1   ; r7++
 TestForDone cmp r7, r5       ; r7 < r5
             blt DoFor        ; Yes - Do loop body again.
-            DoneFor          ; Continue.
+DoneFor     svc #0           ; Halt.
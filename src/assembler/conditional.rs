@@ -0,0 +1,55 @@
+use super::lexer::Span;
+use std::str::FromStr;
+
+/// A directive of conditional assembly, which is handled before lexing.
+#[derive(Clone, Copy, EnumString, Eq, Debug, PartialEq)]
+pub enum Conditional {
+    /// Assemble what follows if the expression is not zero.
+    #[strum(serialize = ".if", serialize = ".IF")]
+    If,
+    /// Assemble what follows if the symbol is defined.
+    #[strum(serialize = ".ifdef", serialize = ".IFDEF")]
+    Ifdef,
+    /// Assemble what follows if the symbol is not defined.
+    #[strum(serialize = ".ifndef", serialize = ".IFNDEF")]
+    Ifndef,
+    /// Assemble what follows if what precedes it in the block is not assembled.
+    #[strum(serialize = ".else", serialize = ".ELSE")]
+    Else,
+    /// End the block.
+    #[strum(serialize = ".endif", serialize = ".ENDIF")]
+    Endif,
+}
+
+impl Conditional {
+    /// Get the directive starting the line split into the given lexemes, if any.
+    pub fn find(spans: &[Span]) -> Option<Conditional> {
+        Conditional::from_str(&spans.first()?.text).ok()
+    }
+    /// Check whether the directive opens a block.
+    pub fn opens_block(self) -> bool {
+        matches!(
+            self,
+            Conditional::If | Conditional::Ifdef | Conditional::Ifndef
+        )
+    }
+}
+
+/// A block of lines assembled on a condition, from `.if`, `.ifdef` or `.ifndef` up to `.endif`.
+pub struct Block {
+    /// Whether the lines of the current branch of the block are assembled.
+    pub is_assembled: bool,
+    /// Whether the block reached its `.else`, which it has at most one of.
+    pub has_else: bool,
+    /// The number of source files and of macro expansions being read when the block was opened,
+    /// which must end it.
+    pub depth: (usize, usize),
+    /// The name of the source file the block is opened in.
+    pub file: String,
+    /// The line the block is opened on, starting at 1.
+    pub line: usize,
+    /// The source code of the line the block is opened on.
+    pub source: String,
+    /// The location of the directive opening the block.
+    pub span: Span,
+}
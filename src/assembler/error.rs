@@ -0,0 +1,196 @@
+use std::fmt;
+use std::io;
+
+/// A diagnostic pointing at the token of a source line that could not be assembled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssemblyError {
+    /// The name of the source file.
+    pub file: String,
+    /// The line of the offending token, starting at 1.
+    pub line: usize,
+    /// The column of the offending token, starting at 1.
+    pub column: usize,
+    /// The offending token as written in the source.
+    pub token: String,
+    /// A human-readable explanation of the error.
+    pub reason: String,
+    /// The source line containing the offending token, after the expansion of macros.
+    pub source: String,
+    /// The invocations of the macros the offending line was expanded from, innermost first.
+    pub invocations: Vec<Invocation>,
+}
+
+/// The invocation of a macro, pointing at its name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Invocation {
+    /// The name of the source file.
+    pub file: String,
+    /// The line of the invocation, starting at 1.
+    pub line: usize,
+    /// The column of the name of the macro, starting at 1.
+    pub column: usize,
+    /// The name of the macro.
+    pub name: String,
+    /// The source line holding the invocation.
+    pub source: String,
+}
+
+/// Render the location of the token along with its source line, underlining the token with
+/// carets.
+fn snippet(
+    f: &mut fmt::Formatter,
+    file: &str,
+    line: usize,
+    column: usize,
+    token: &str,
+    source: &str,
+) -> fmt::Result {
+    let number = line.to_string();
+    let gutter = " ".repeat(number.len());
+    // Preserve tabs so the carets line up with the source line.
+    let indent: String = source
+        .chars()
+        .take(column - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let carets = "^".repeat(std::cmp::max(token.chars().count(), 1));
+    writeln!(f, "{}--> {}:{}:{}", gutter, file, line, column)?;
+    writeln!(f, "{} |", gutter)?;
+    writeln!(f, "{} | {}", number, source)?;
+    write!(f, "{} | {}{}", gutter, indent, carets)
+}
+
+impl fmt::Display for AssemblyError {
+    /// Render the error in the style of rustc, underlining the offending token with carets, then
+    /// every invocation of a macro the token was expanded from.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "error: {}", self.reason)?;
+        snippet(
+            f,
+            &self.file,
+            self.line,
+            self.column,
+            &self.token,
+            &self.source,
+        )?;
+        for invocation in &self.invocations {
+            writeln!(
+                f,
+                "\nnote: in this expansion of macro `{}`",
+                invocation.name
+            )?;
+            snippet(
+                f,
+                &invocation.file,
+                invocation.line,
+                invocation.column,
+                &invocation.name,
+                &invocation.source,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// An error raised while assembling a program.
+#[derive(Debug)]
+pub enum Error {
+    /// The source could not be read.
+    Io(io::Error),
+    /// The source contains expressions that could not be assembled.
+    Assembly(Vec<AssemblyError>),
+    /// The sections of the program could not be placed as described by the layout.
+    Layout(String),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "error: {}", err),
+            Error::Layout(reason) => write!(f, "error: {}", reason),
+            Error::Assembly(errors) => {
+                for err in errors {
+                    writeln!(f, "{}\n", err)?;
+                }
+                match errors.len() {
+                    1 => write!(f, "error: aborting due to previous error"),
+                    n => write!(f, "error: aborting due to {} previous errors", n),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let err = AssemblyError {
+            file: String::from("pgrm.asm"),
+            line: 3,
+            column: 13,
+            token: String::from("#0x1FFFF"),
+            reason: String::from("immediate 0x1FFFF does not fit in 16 bits for form Four"),
+            source: String::from("add r1, r2, #0x1FFFF"),
+            invocations: Vec::new(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "error: immediate 0x1FFFF does not fit in 16 bits for form Four\n \
+             --> pgrm.asm:3:13\n  \
+             |\n\
+             3 | add r1, r2, #0x1FFFF\n  \
+             |             ^^^^^^^^"
+        );
+    }
+
+    #[test]
+    fn test_display_preserves_tabs() {
+        let err = AssemblyError {
+            file: String::from("pgrm.asm"),
+            line: 1,
+            column: 2,
+            token: String::from("foo"),
+            reason: String::from("expected an opcode after a label, found a label"),
+            source: String::from("\tfoo"),
+            invocations: Vec::new(),
+        };
+        assert!(err.to_string().ends_with("1 | \tfoo\n  | \t^^^"));
+    }
+
+    #[test]
+    fn test_display_invocations() {
+        let err = AssemblyError {
+            file: String::from("pgrm.asm"),
+            line: 2,
+            column: 13,
+            token: String::from("#0x1FFFF"),
+            reason: String::from("immediate 0x1FFFF does not fit in 16 bits for form Four"),
+            source: String::from("add r1, r1, #0x1FFFF"),
+            invocations: vec![Invocation {
+                file: String::from("pgrm.asm"),
+                line: 10,
+                column: 5,
+                name: String::from("inc"),
+                source: String::from("    inc r1, #0x1FFFF"),
+            }],
+        };
+        assert!(err.to_string().ends_with(
+            "note: in this expansion of macro `inc`\n  \
+             --> pgrm.asm:10:5\n   \
+             |\n\
+             10 |     inc r1, #0x1FFFF\n   \
+             |     ^^^"
+        ));
+    }
+
+}
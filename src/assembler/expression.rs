@@ -0,0 +1,280 @@
+/// A constant expression, as found in immediates and in the operands of directives.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Number(i64),
+    Symbol(String),
+    Unary(char, Box<Expression>),
+    Binary(&'static str, Box<Expression>, Box<Expression>),
+}
+
+/// The binary operators by increasing precedence, as in C. Comparisons and logical operators
+/// evaluate to 1 when they hold, and to 0 otherwise.
+const BINARY_OPERATORS: [&[&str]; 10] = [
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<=", ">=", "<", ">"],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+/// The unary operators: negation, bitwise not, logical not and identity.
+const UNARY_OPERATORS: [char; 4] = ['-', '~', '!', '+'];
+
+/// Check whether the character may start the name of a symbol.
+pub fn is_symbol_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '.'
+}
+
+/// Check whether the character may continue the name of a symbol.
+pub fn is_symbol_char(c: char) -> bool {
+    is_symbol_start(c) || c.is_ascii_digit()
+}
+
+/// Check whether the text is the name of a symbol, such as `ARRAY_LEN` or `loop.end`.
+pub fn is_symbol(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) => is_symbol_start(c) && chars.all(is_symbol_char),
+        None => false,
+    }
+}
+
+/// A recursive descent parser over the characters of an expression.
+struct Parser<'a> {
+    text: &'a str,
+    /// The byte offset of the next character to parse.
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Get the remaining text, skipping whitespace.
+    fn rest(&mut self) -> &'a str {
+        let rest = &self.text[self.index..];
+        self.index += rest.len() - rest.trim_start().len();
+        &self.text[self.index..]
+    }
+    /// Consume the given operator if the remaining text starts with it, rather than with a longer
+    /// operator such as `&&` for `&`.
+    fn eat(&mut self, operator: &str) -> bool {
+        let rest = self.rest();
+        let found = rest.starts_with(operator)
+            && !BINARY_OPERATORS
+                .iter()
+                .flat_map(|level| level.iter())
+                .any(|longer| {
+                    longer.len() > operator.len()
+                        && longer.starts_with(operator)
+                        && rest.starts_with(longer)
+                });
+        if found {
+            self.index += operator.len();
+        }
+        found
+    }
+    fn binary(&mut self, level: usize) -> Result<Expression, String> {
+        if level == BINARY_OPERATORS.len() {
+            return self.unary();
+        }
+        let mut lhs = self.binary(level + 1)?;
+        'outer: loop {
+            for operator in BINARY_OPERATORS[level] {
+                if self.eat(operator) {
+                    let rhs = self.binary(level + 1)?;
+                    lhs = Expression::Binary(operator, Box::new(lhs), Box::new(rhs));
+                    continue 'outer;
+                }
+            }
+            return Ok(lhs);
+        }
+    }
+    fn unary(&mut self) -> Result<Expression, String> {
+        for operator in UNARY_OPERATORS.iter() {
+            if self.eat(&operator.to_string()) {
+                return Ok(Expression::Unary(*operator, Box::new(self.unary()?)));
+            }
+        }
+        self.primary()
+    }
+    fn primary(&mut self) -> Result<Expression, String> {
+        let rest = self.rest();
+        let c = match rest.chars().next() {
+            Some(c) => c,
+            None => return Err(String::from("expected an operand")),
+        };
+        if self.eat("(") {
+            let expr = self.binary(0)?;
+            if !self.eat(")") {
+                return Err(String::from("expected `)`"));
+            }
+            return Ok(expr);
+        }
+        // A character such as `'a'` stands for its ASCII code.
+        if c == '\'' {
+            let mut chars = rest.chars().skip(1);
+            return match (chars.next(), chars.next()) {
+                (Some(c), Some('\'')) if c.is_ascii() => {
+                    self.index += 3;
+                    Ok(Expression::Number(c as i64))
+                }
+                _ => Err(String::from("expected a character such as `'a'`")),
+            };
+        }
+        let len = rest
+            .find(|c: char| !is_symbol_char(c))
+            .unwrap_or(rest.len());
+        let word = &rest[..len];
+        self.index += len;
+        if is_symbol(word) {
+            return Ok(Expression::Symbol(word.to_string()));
+        }
+        let number = match word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+            Some(digits) => u32::from_str_radix(digits, 16),
+            None => word.parse::<u32>(),
+        };
+        match number {
+            Ok(number) => Ok(Expression::Number(number as i64)),
+            Err(_) if word.is_empty() => Err(format!("unexpected `{}`", c)),
+            Err(_) => Err(format!("invalid number `{}`", word)),
+        }
+    }
+}
+
+impl Expression {
+    /// Parse an expression made of numbers, characters, symbols, parentheses and the operators
+    /// of C: unary `-`, `~`, `!` and `+`, then by decreasing precedence `*`, `/` and `%`, `+` and
+    /// `-`, `<<` and `>>`, comparisons, `==` and `!=`, `&`, `^`, `|`, `&&` and finally `||`.
+    pub fn parse(text: &str) -> Result<Expression, String> {
+        let mut parser = Parser { text, index: 0 };
+        let expr = parser.binary(0)?;
+        match parser.rest().chars().next() {
+            Some(c) => Err(format!("unexpected `{}`", c)),
+            None => Ok(expr),
+        }
+    }
+    /// Evaluate the expression, getting the value of every symbol from the given function.
+    /// Arithmetic is carried out on 64-bit signed integers and fails rather than overflowing.
+    pub fn evaluate<F>(&self, symbol: &mut F) -> Result<i64, String>
+    where
+        F: FnMut(&str) -> Result<i64, String>,
+    {
+        let overflow = || String::from("arithmetic overflow");
+        match self {
+            Expression::Number(value) => Ok(*value),
+            Expression::Symbol(name) => symbol(name),
+            Expression::Unary(operator, operand) => {
+                let value = operand.evaluate(symbol)?;
+                match operator {
+                    '-' => value.checked_neg().ok_or_else(overflow),
+                    '~' => Ok(!value),
+                    '!' => Ok((value == 0) as i64),
+                    _ => Ok(value),
+                }
+            }
+            Expression::Binary(operator, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.evaluate(symbol)?, rhs.evaluate(symbol)?);
+                let shift = || match rhs {
+                    0..=63 => Ok(rhs as u32),
+                    _ => Err(format!("shift amount {} is out of range", rhs)),
+                };
+                let value = match *operator {
+                    "||" => Some((lhs != 0 || rhs != 0) as i64),
+                    "&&" => Some((lhs != 0 && rhs != 0) as i64),
+                    "==" => Some((lhs == rhs) as i64),
+                    "!=" => Some((lhs != rhs) as i64),
+                    "<=" => Some((lhs <= rhs) as i64),
+                    ">=" => Some((lhs >= rhs) as i64),
+                    "<" => Some((lhs < rhs) as i64),
+                    ">" => Some((lhs > rhs) as i64),
+                    "|" => Some(lhs | rhs),
+                    "^" => Some(lhs ^ rhs),
+                    "&" => Some(lhs & rhs),
+                    "<<" => lhs.checked_mul(1 << shift()?),
+                    ">>" => Some(lhs >> shift()?),
+                    "+" => lhs.checked_add(rhs),
+                    "-" => lhs.checked_sub(rhs),
+                    "*" => lhs.checked_mul(rhs),
+                    _ if rhs == 0 => return Err(String::from("division by zero")),
+                    "/" => lhs.checked_div(rhs),
+                    _ => lhs.checked_rem(rhs),
+                };
+                value.ok_or_else(overflow)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// Evaluate the expression, in which `BASE` is 0x1000 and `LEN` is 20.
+    fn eval(text: &str) -> Result<i64, String> {
+        Expression::parse(text)?.evaluate(&mut |name: &str| match name {
+            "BASE" => Ok(0x1000),
+            "LEN" => Ok(20),
+            _ => Err(format!("undefined symbol `{}`", name)),
+        })
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(7));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(9));
+        assert_eq!(eval("1 << 4 | 1 & 3"), Ok(17));
+        assert_eq!(eval("-LEN*4"), Ok(-80));
+        assert_eq!(eval("~0 & 0xFF"), Ok(0xFF));
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert_eq!(eval("LEN == 20 && BASE != 0"), Ok(1));
+        assert_eq!(eval("LEN < 20 || !(BASE >= 0x1000)"), Ok(0));
+        assert_eq!(eval("1 << 2 <= 4 & 3"), Ok(1));
+        assert_eq!(eval("2 | 1 > 0"), Ok(3));
+    }
+
+    #[test]
+    fn test_symbols_and_characters() {
+        assert_eq!(eval("(BASE + 0x10) & 0xFFFF"), Ok(0x1010));
+        assert_eq!(eval("'a' + 1"), Ok(98));
+        assert_eq!(eval("FOO"), Err(String::from("undefined symbol `FOO`")));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(eval("1 +"), Err(String::from("expected an operand")));
+        assert_eq!(eval("(1"), Err(String::from("expected `)`")));
+        assert_eq!(eval("1 2"), Err(String::from("unexpected `2`")));
+        assert_eq!(
+            eval("0x1FFFFFFFF"),
+            Err(String::from("invalid number `0x1FFFFFFFF`"))
+        );
+        assert_eq!(
+            eval("1 / (LEN - 20)"),
+            Err(String::from("division by zero"))
+        );
+        assert_eq!(
+            eval("1 << 64"),
+            Err(String::from("shift amount 64 is out of range"))
+        );
+        assert_eq!(
+            eval("0x7FFFFFFF << 40"),
+            Err(String::from("arithmetic overflow"))
+        );
+    }
+
+    #[test]
+    fn test_is_symbol() {
+        assert!(is_symbol("ARRAY_LEN"));
+        assert!(is_symbol(".loop"));
+        assert!(!is_symbol("4LEN"));
+        assert!(!is_symbol("LEN*4"));
+    }
+
+}
@@ -1,54 +1,340 @@
 mod token;
 
-use super::super::util::{ConditionCode, Literal, Opcode, Register};
+use super::super::util::{ConditionCode, Literal, Opcode, Register, Shift};
+use super::expression::{is_symbol, Expression};
+use num_traits::FromPrimitive;
 use std::str::FromStr;
 pub use token::*;
 
-/// Convert the source code into meaningful lexemes.
-pub fn lexer(mut buf: String) -> Vec<Token> {
-    // Pad separators with whitespace.
-    buf = buf.replace(",", " , ");
-    buf = buf.replace("[", " [ ");
-    buf = buf.replace("]", " ] ");
-    // Pad comments with whitespace.
-    buf = buf.replace(";", " ; ");
+/// The delimiter of a string literal.
+const QUOTE: char = '"';
+
+/// Check whether the character ends an expression, outside of parentheses and characters. The
+/// writeback `!` only ever follows `]` or a register, so it stands for logical not or `!=`.
+fn ends_expression(c: char) -> bool {
+    [
+        Separator::Comma,
+        Separator::CloseBrace,
+        Separator::CloseCurly,
+    ]
+    .iter()
+    .any(|separator| separator.to_string() == c.to_string())
+        || Comment::from_str(&c.to_string()).is_ok()
+}
+
+/// Split a line of source code into lexemes, dropping whitespace and comments. A string literal
+/// forms a single lexeme, quotes included, and so does an expression such as `#(BASE + 4) * 2`,
+/// which is either an immediate or an operand of a directive.
+pub fn split(buf: &str) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut lexeme: Option<Span> = None;
+    // Whether the current lexeme is a string literal, and whether its last character escapes the
+    // next one.
+    let (mut in_string, mut escaped) = (false, false);
+    // Whether the current lexeme is an expression, the depth of parentheses within it and
+    // whether it is in the middle of a character such as `','`.
+    let (mut in_expression, mut depth, mut in_char) = (false, 0, false);
+    // Whether the line holds a directive, whose operands are expressions.
+    let mut is_directive = false;
+    for (column, c) in (1..).zip(buf.chars()) {
+        if in_expression {
+            if in_char || depth > 0 || !ends_expression(c) {
+                match c {
+                    '\'' => in_char = !in_char,
+                    '(' if !in_char => depth += 1,
+                    ')' if !in_char => depth -= 1,
+                    _ => (),
+                }
+                lexeme.as_mut().unwrap().text.push(c);
+                continue;
+            }
+            let mut span = lexeme.take().unwrap();
+            span.text.truncate(span.text.trim_end().len());
+            spans.push(span);
+            in_expression = false;
+        }
+        if in_string {
+            lexeme.as_mut().unwrap().text.push(c);
+            in_string = escaped || c != QUOTE;
+            escaped = !escaped && c == '\\';
+            continue;
+        }
+        if c == QUOTE {
+            if let Some(span) = lexeme.take() {
+                spans.push(span);
+            }
+            lexeme = Some(Span {
+                column,
+                text: c.to_string(),
+            });
+            in_string = true;
+            continue;
+        }
+        let text = c.to_string();
+        let is_comment = Comment::from_str(&text).is_ok();
+        let is_separator = Separator::from_str(&text).is_ok();
+        // An immediate starts with its prefix, whereas operands of a directive are written
+        // without it and follow either the directive or a comma, as does the value loaded by
+        // `LDR Rd, =value`.
+        let is_operand = spans.last().is_some_and(|span| {
+            is_directive && (span.text == "," || Directive::from_str(&span.text).is_ok())
+                || span.text == Separator::Equals.to_string()
+        });
+        if lexeme.is_none() && (c == '#' || is_operand && !ends_expression(c) && !c.is_whitespace())
+        {
+            lexeme = Some(Span { column, text });
+            in_expression = true;
+            in_char = c == '\'';
+            depth = (c == '(') as i32;
+            continue;
+        }
+        if !(c.is_whitespace() || is_comment || is_separator) {
+            lexeme
+                .get_or_insert(Span {
+                    column,
+                    text: String::new(),
+                })
+                .text
+                .push(c);
+            continue;
+        }
+        // Whitespace, separators and comments end the current lexeme.
+        if let Some(span) = lexeme.take() {
+            is_directive |= Directive::from_str(&span.text).is_ok();
+            spans.push(span);
+        }
+        if is_comment {
+            // Comments indicate the end of an expression.
+            break;
+        }
+        if is_separator {
+            spans.push(Span { column, text });
+        }
+    }
+    if let Some(mut span) = lexeme {
+        span.text.truncate(span.text.trim_end().len());
+        spans.push(span);
+    }
+    spans
+}
+
+/// Resolve the escape sequences of the string literal held by the span, quotes included.
+fn text(span: &Span) -> Result<Token, Diagnostic> {
+    let error = |reason: String| Diagnostic {
+        span: span.clone(),
+        reason,
+    };
+    let mut chars = span.text.chars().skip(1);
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some(QUOTE) => break,
+            Some('\\') => match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some('r') => text.push('\r'),
+                Some('0') => text.push('\0'),
+                Some(c @ '\\') | Some(c @ QUOTE) => text.push(c),
+                Some(c) => return Err(error(format!("unknown escape sequence `\\{}`", c))),
+                None => return Err(error(String::from("unterminated string"))),
+            },
+            Some(c) => text.push(c),
+            None => return Err(error(String::from("unterminated string"))),
+        }
+    }
+    Ok(Token::Text(text))
+}
+
+/// Split a mnemonic such as `ADDEQ` into its opcode and its condition code suffix.
+fn split_cond_code(token: &str) -> Option<(Opcode, ConditionCode)> {
+    let len = token.len();
+    if len <= 2 || !token.is_char_boundary(len - 2) {
+        return None;
+    }
+    let opcode = Opcode::from_str(&token[..len - 2]).ok()?;
+    let cond_code = ConditionCode::from_suffix(&token[len - 2..])?;
+    Some((opcode, cond_code))
+}
+
+/// Parse a register of a register list.
+fn list_register(span: &Span) -> Result<Register, Diagnostic> {
+    Register::from_str(&span.text).map_err(|_| Diagnostic {
+        reason: format!("expected a register, found `{}`", span.text),
+        span: span.clone(),
+    })
+}
+
+/// Parse the register list opened by the given curly brace, such as `{r4-r7, lr}`, consuming the
+/// lexemes up to and including its closing curly brace.
+fn register_list<I: Iterator<Item = Span>>(
+    buf: &str,
+    open: Span,
+    spans: &mut I,
+) -> Result<(Token, Span), Diagnostic> {
+    let mut lexemes = Vec::new();
+    let mut close = None;
+    for span in spans {
+        if span.text == Separator::CloseCurly.to_string() {
+            close = Some(span);
+            break;
+        }
+        lexemes.push(span);
+    }
+    let close = close.ok_or_else(|| Diagnostic {
+        reason: String::from("expected register list to end with `}`"),
+        span: open.clone(),
+    })?;
+    // The list spans the source from its opening to its closing curly brace.
+    let text: String = buf
+        .chars()
+        .skip(open.column - 1)
+        .take(close.column - open.column + 1)
+        .collect();
+    let span = Span {
+        column: open.column,
+        text,
+    };
+    if lexemes.is_empty() {
+        return Err(Diagnostic {
+            reason: String::from("empty register list"),
+            span,
+        });
+    }
+    // The list holds registers and ranges of registers separated by commas.
+    let mut registers = Vec::new();
+    let comma = Separator::Comma.to_string();
+    let dash = Separator::Dash.to_string();
+    // The index just past the comma ending the current element.
+    let mut index = 0;
+    for element in lexemes.split(|span| span.text == comma) {
+        index += element.len() + 1;
+        match element {
+            [register] => registers.push(list_register(register)?),
+            [first, separator, last] if separator.text == dash => {
+                let (first, last) = (list_register(first)?, list_register(last)?);
+                if first.clone() as usize > last.clone() as usize {
+                    return Err(Diagnostic {
+                        reason: format!("register range ends below {:?}", first),
+                        span: element[2].clone(),
+                    });
+                }
+                for register in first as usize..=last as usize {
+                    registers.extend(Register::from_usize(register));
+                }
+            }
+            _ => {
+                return Err(Diagnostic {
+                    reason: String::from("expected a register or a range of registers"),
+                    // Point at the comma or the curly brace ending an empty element.
+                    span: element
+                        .first()
+                        .or_else(|| lexemes.get(index - 1))
+                        .cloned()
+                        .unwrap_or(close),
+                });
+            }
+        }
+    }
+    Ok((Token::RegisterList(registers), span))
+}
+
+/// Convert an immediate, or an operand of a directive written without the prefix of an
+/// immediate, into a literal if it is a number or a character, into a label if it is the name of
+/// a symbol, and into an expression otherwise.
+fn operand(span: &Span) -> Result<Token, Diagnostic> {
+    let expr = span.text.trim_start_matches('#');
+    if let Ok(mut immed) = Literal::from_str(&format!("#{}", expr)) {
+        // Ensure immediate value has valid prefix.
+        if immed.is_valid() {
+            return Ok(Token::Literal(immed));
+        }
+    }
+    let is_immediate = span.text.starts_with('#');
+    if !is_immediate && is_symbol(expr) {
+        return Ok(Token::Label(Label::Name(expr.to_string())));
+    }
+    match Expression::parse(expr) {
+        Ok(_) => Ok(Token::Literal(Literal::Expression(expr.to_string()))),
+        Err(reason) => Err(Diagnostic {
+            reason: match is_immediate {
+                true => format!("invalid immediate `{}`: {}", span.text, reason),
+                false => format!("invalid expression `{}`: {}", span.text, reason),
+            },
+            span: span.clone(),
+        }),
+    }
+}
+
+/// Convert the source code into meaningful lexemes, each paired with its location in the source.
+pub fn lexer(buf: &str) -> Result<Vec<(Token, Span)>, Diagnostic> {
     let mut tokens = Vec::new();
-    // Iterate over string split by whitespace.
-    for token in buf.split_whitespace() {
-        if let Ok(opcode) = Opcode::from_str(&token) {
-            tokens.push(Token::Opcode(opcode));
+    let mut spans = split(buf).into_iter();
+    while let Some(span) = spans.next() {
+        if span.text == Separator::OpenCurly.to_string() {
+            tokens.push(register_list(buf, span, &mut spans)?);
             continue;
         }
-        if let Ok(cond_code) = ConditionCode::from_str(&token) {
-            tokens.push(Token::Opcode(Opcode::B));
-            tokens.push(Token::ConditionCode(cond_code));
+        let token = &span.text;
+        if token.starts_with(QUOTE) {
+            tokens.push((text(&span)?, span));
             continue;
         }
-        if let Ok(register) = Register::from_str(&token) {
-            tokens.push(Token::Register(register));
+        // Operands of a directive, like the value loaded by `LDR Rd, =value`, may be written
+        // without the prefix of an immediate, and name symbols rather than opcodes or registers.
+        let is_operand = tokens
+            .iter()
+            .any(|(token, _)| matches!(token, Token::Directive(_)))
+            || matches!(
+                tokens.last(),
+                Some((Token::Separator(Separator::Equals), _))
+            );
+        if let Ok(directive) = Directive::from_str(token) {
+            tokens.push((Token::Directive(directive), span));
             continue;
         }
-        if let Ok(separator) = Separator::from_str(&token) {
-            tokens.push(Token::Separator(separator));
+        if let Ok(separator) = Separator::from_str(token) {
+            tokens.push((Token::Separator(separator), span));
             continue;
         }
-        if let Ok(mut immed) = Literal::from_str(&token) {
-            // Ensure immediate value has valid prefix.
-            if immed.is_valid() {
-                tokens.push(Token::Literal(immed));
+        if is_operand {
+            tokens.push((operand(&span)?, span));
+            continue;
+        }
+        // A shift following a comma applies to the last operand rather than being an opcode.
+        if let Some((Token::Separator(Separator::Comma), _)) = tokens.last() {
+            if let Ok(shift) = Shift::from_str(token) {
+                tokens.push((Token::Shift(shift), span));
                 continue;
             }
         }
-        if let Ok(_) = Comment::from_str(&token) {
-            // Comments indicate the end of an expression.
-            break;
+        if let Ok(opcode) = Opcode::from_str(token) {
+            tokens.push((Token::Opcode(opcode), span));
+            continue;
+        }
+        if let Ok(cond_code) = ConditionCode::from_str(token) {
+            tokens.push((Token::Opcode(Opcode::B), span.clone()));
+            tokens.push((Token::ConditionCode(cond_code), span));
+            continue;
         }
-        if let Ok(label) = Label::from_str(&token) {
-            tokens.push(Token::Label(label));
+        if let Some((opcode, cond_code)) = split_cond_code(token) {
+            tokens.push((Token::Opcode(opcode), span.clone()));
+            tokens.push((Token::ConditionCode(cond_code), span));
+            continue;
+        }
+        if let Ok(register) = Register::from_str(token) {
+            tokens.push((Token::Register(register), span));
+            continue;
+        }
+        if token.starts_with('#') {
+            tokens.push((operand(&span)?, span));
+            continue;
+        }
+        if let Ok(label) = Label::from_str(token) {
+            tokens.push((Token::Label(label), span));
             continue;
         }
     }
-    return tokens;
+    Ok(tokens)
 }
 
 #[cfg(test)]
@@ -57,9 +343,18 @@ mod tests {
     use super::super::super::util::{Opcode::*, Register::*};
     use super::*;
 
+    /// Convert the source code into tokens, discarding their locations.
+    fn lex(buf: &str) -> Vec<Token> {
+        lexer(buf)
+            .unwrap()
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect()
+    }
+
     #[test]
     fn test_opcode_token() {
-        let mut tokens = lexer(String::from("ADD"));
+        let mut tokens = lex("ADD");
         if let Some(token) = tokens.pop() {
             assert_eq!(token, Token::Opcode(ADD))
         }
@@ -67,7 +362,7 @@ mod tests {
 
     #[test]
     fn test_register_token() {
-        let mut tokens = lexer(String::from("PC"));
+        let mut tokens = lex("PC");
         if let Some(token) = tokens.pop() {
             assert_eq!(token, Token::Register(PC))
         }
@@ -75,7 +370,7 @@ mod tests {
 
     #[test]
     fn test_separator_token() {
-        let mut tokens = lexer(String::from(","));
+        let mut tokens = lex(",");
         if let Some(token) = tokens.pop() {
             assert_eq!(token, Token::Separator(Separator::Comma))
         }
@@ -83,7 +378,7 @@ mod tests {
 
     #[test]
     fn test_literal_token() {
-        let mut tokens = lexer(String::from("#0"));
+        let mut tokens = lex("#0");
         if let Some(token) = tokens.pop() {
             // Lexer automatically strips pound sign.
             assert_eq!(token, Token::Literal(Literal::Immediate(String::from("0"))))
@@ -92,7 +387,7 @@ mod tests {
 
     #[test]
     fn test_comment() {
-        let mut tokens = lexer(String::from(";"));
+        let mut tokens = lex(";");
         // Lexer drops comments.
         if let Some(_) = tokens.pop() {
             assert!(false);
@@ -103,7 +398,7 @@ mod tests {
 
     #[test]
     fn test_label_token() {
-        let mut tokens = lexer(String::from("foo"));
+        let mut tokens = lex("foo");
         if let Some(token) = tokens.pop() {
             assert_eq!(token, Token::Label(Label::Name(String::from("foo"))))
         }
@@ -111,7 +406,7 @@ mod tests {
 
     #[test]
     fn test_expr_after_comment() {
-        let mut tokens = lexer(String::from("; ADD R0, R0, R0"));
+        let mut tokens = lex("; ADD R0, R0, R0");
         if let Some(_) = tokens.pop() {
             assert!(false);
         } else {
@@ -126,7 +421,209 @@ mod tests {
             Token::Separator(Separator::OpenBrace),
             Token::Separator(Separator::CloseBrace),
         ];
-        assert_eq!(lexer(String::from(",[]")), tokens);
+        assert_eq!(lex(",[]"), tokens);
+    }
+
+    #[test]
+    fn test_spans() {
+        let spans: Vec<Span> = lexer("foo add r1,r2, #1 ; bar")
+            .unwrap()
+            .into_iter()
+            .map(|(_, span)| span)
+            .collect();
+        let columns: Vec<usize> = spans.iter().map(|span| span.column).collect();
+        assert_eq!(columns, vec![1, 5, 9, 11, 12, 14, 16]);
+        assert_eq!(spans[6].text, "#1");
+    }
+
+    #[test]
+    fn test_branch_with_link() {
+        // As on ARM, BLE branches if less or equal rather than branching with link if equal.
+        let tokens = lex("bl ble blle bx");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Opcode(BL),
+                Token::Opcode(B),
+                Token::ConditionCode(ConditionCode::LE),
+                Token::Opcode(BL),
+                Token::ConditionCode(ConditionCode::LE),
+                Token::Opcode(BX),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shift() {
+        let tokens = lex("lsl r0, r1, LSL #2");
+        assert_eq!(tokens[0], Token::Opcode(LSL));
+        assert_eq!(tokens[5], Token::Shift(Shift::LSL));
+    }
+
+    #[test]
+    fn test_negative_immediate() {
+        let lexemes = lexer("ldr r0, [r1, #-4]!").unwrap();
+        assert_eq!(
+            lexemes[6].0,
+            Token::Literal(Literal::Immediate(String::from("-4")))
+        );
+        assert_eq!(lexemes[6].1.text, "#-4");
+        assert_eq!(lexemes[8].0, Token::Separator(Separator::Writeback));
+    }
+
+    #[test]
+    fn test_cond_code_shares_span() {
+        let lexemes = lexer("  bne foo").unwrap();
+        assert_eq!(lexemes[0].1, lexemes[1].1);
+        assert_eq!(lexemes[1].1.column, 3);
+    }
+
+    #[test]
+    fn test_cond_code_suffix() {
+        let tokens = lex("addeq movsne LDRGT");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Opcode(ADD),
+                Token::ConditionCode(ConditionCode::EQ),
+                Token::Opcode(MOVS),
+                Token::ConditionCode(ConditionCode::NE),
+                Token::Opcode(LDR),
+                Token::ConditionCode(ConditionCode::GT),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_register_list() {
+        let lexemes = lexer("push {r4-r6, lr} ; save").unwrap();
+        assert_eq!(lexemes.len(), 2);
+        assert_eq!(lexemes[1].0, Token::RegisterList(vec![R4, R5, R6, LR]));
+        assert_eq!(lexemes[1].1.column, 6);
+        assert_eq!(lexemes[1].1.text, "{r4-r6, lr}");
+    }
+
+    #[test]
+    fn test_register_list_with_writeback() {
+        let tokens = lex("ldmia r0!,{r1}");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Opcode(LDMIA),
+                Token::Register(R0),
+                Token::Separator(Separator::Writeback),
+                Token::Separator(Separator::Comma),
+                Token::RegisterList(vec![R1]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_register_list() {
+        let err = lexer("pop {r7-r4}").unwrap_err();
+        assert_eq!(err.span.text, "r4");
+        assert_eq!(err.reason, "register range ends below R7");
+        let err = lexer("pop {r4, foo}").unwrap_err();
+        assert_eq!(err.span.column, 10);
+        assert_eq!(err.reason, "expected a register, found `foo`");
+        let err = lexer("pop {r4,, r5}").unwrap_err();
+        assert_eq!(err.span.column, 9);
+        let err = lexer("pop {r4,}").unwrap_err();
+        assert_eq!(err.span.text, "}");
+        let err = lexer("pop {r4").unwrap_err();
+        assert_eq!(err.span.column, 5);
+        assert_eq!(err.reason, "expected register list to end with `}`");
+        assert_eq!(lexer("pop {}").unwrap_err().reason, "empty register list");
+    }
+
+    #[test]
+    fn test_invalid_immediate() {
+        let err = lexer("mov r0, #0x1FFFFFFFF").unwrap_err();
+        assert_eq!(err.span.column, 9);
+        assert_eq!(err.span.text, "#0x1FFFFFFFF");
+    }
+
+    #[test]
+    fn test_directive_operands() {
+        let immed = |immed: &str| Token::Literal(Literal::Immediate(String::from(immed)));
+        assert_eq!(
+            lex("table .word 0x10, -4, #2, end"),
+            vec![
+                Token::Label(Label::Name(String::from("table"))),
+                Token::Directive(Directive::Word),
+                immed("0x10"),
+                Token::Separator(Separator::Comma),
+                immed("-4"),
+                Token::Separator(Separator::Comma),
+                immed("2"),
+                Token::Separator(Separator::Comma),
+                Token::Label(Label::Name(String::from("end"))),
+            ]
+        );
+        assert_eq!(lex(".BYTE -1")[1], immed("-1"));
+    }
+
+    #[test]
+    fn test_expression() {
+        let lexemes = lexer("ldr r0, [r1, #(BASE + 0x10) & 0xFF]! ; load").unwrap();
+        assert_eq!(
+            lexemes[6].0,
+            Token::Literal(Literal::Expression(String::from("(BASE + 0x10) & 0xFF")))
+        );
+        assert_eq!(lexemes[6].1.column, 14);
+        assert_eq!(lexemes[8].0, Token::Separator(Separator::Writeback));
+        let expr = |expr: &str| Token::Literal(Literal::Expression(String::from(expr)));
+        assert_eq!(
+            lex(".equ b, ',' + 1, LEN * (2 + 1)"),
+            vec![
+                Token::Directive(Directive::Equ),
+                Token::Label(Label::Name(String::from("b"))),
+                Token::Separator(Separator::Comma),
+                expr("',' + 1"),
+                Token::Separator(Separator::Comma),
+                expr("LEN * (2 + 1)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_value() {
+        let lexemes = lexer("ldr r0,=BASE + 4 ; load").unwrap();
+        assert_eq!(lexemes[3].0, Token::Separator(Separator::Equals));
+        assert_eq!(
+            lexemes[4].0,
+            Token::Literal(Literal::Expression(String::from("BASE + 4")))
+        );
+        assert_eq!(lexemes[4].1.column, 9);
+    }
+
+    #[test]
+    fn test_invalid_expression() {
+        let err = lexer("mov r0, #LEN *").unwrap_err();
+        assert_eq!(
+            err.reason,
+            "invalid immediate `#LEN *`: expected an operand"
+        );
+        let err = lexer(".word (1 + 2").unwrap_err();
+        assert_eq!(err.reason, "invalid expression `(1 + 2`: expected `)`");
+    }
+
+    #[test]
+    fn test_string() {
+        let lexemes = lexer(".asciz \"a, b; \\\"c\\\"\\n\" ; comment").unwrap();
+        assert_eq!(lexemes.len(), 2);
+        assert_eq!(lexemes[1].0, Token::Text(String::from("a, b; \"c\"\n")));
+        assert_eq!(lexemes[1].1.column, 8);
+        assert_eq!(lexemes[1].1.text, "\"a, b; \\\"c\\\"\\n\"");
+    }
+
+    #[test]
+    fn test_invalid_string() {
+        let err = lexer(".asciz \"abc").unwrap_err();
+        assert_eq!(err.reason, "unterminated string");
+        assert_eq!(err.span.text, "\"abc");
+        let err = lexer(".asciz \"\\q\"").unwrap_err();
+        assert_eq!(err.reason, "unknown escape sequence `\\q`");
     }
 
 }
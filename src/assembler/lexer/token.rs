@@ -1,4 +1,4 @@
-use super::super::super::util::{ConditionCode, Literal, Opcode, Register};
+use super::super::super::util::{ConditionCode, Literal, Opcode, Register, Shift};
 
 #[derive(Clone, EnumString, Eq, Debug, PartialEq, ToString)]
 pub enum Separator {
@@ -8,6 +8,17 @@ pub enum Separator {
     OpenBrace,
     #[strum(serialize = "]")]
     CloseBrace,
+    #[strum(serialize = "{")]
+    OpenCurly,
+    #[strum(serialize = "}")]
+    CloseCurly,
+    #[strum(serialize = "-")]
+    Dash,
+    #[strum(serialize = "!")]
+    Writeback,
+    /// The prefix of the value loaded by `LDR Rd, =value`.
+    #[strum(serialize = "=")]
+    Equals,
 }
 
 #[derive(Clone, EnumString, Eq, Debug, PartialEq, ToString)]
@@ -16,6 +27,75 @@ pub enum Comment {
     Comment,
 }
 
+/// An assembler directive, which emits data into the program rather than an instruction.
+#[derive(Clone, Copy, Display, EnumString, Eq, Debug, PartialEq)]
+pub enum Directive {
+    /// Emit each operand as a word.
+    #[strum(serialize = ".word", serialize = ".WORD")]
+    Word,
+    /// Emit each operand as a byte.
+    #[strum(serialize = ".byte", serialize = ".BYTE")]
+    Byte,
+    /// Emit the given number of zero bytes.
+    #[strum(serialize = ".space", serialize = ".SPACE")]
+    Space,
+    /// Emit each string operand followed by a null byte.
+    #[strum(serialize = ".asciz", serialize = ".ASCIZ")]
+    Asciz,
+    /// Emit zero bytes up to the next multiple of two to the power of the operand.
+    #[strum(serialize = ".align", serialize = ".ALIGN")]
+    Align,
+    /// Assemble what follows into the section holding instructions.
+    #[strum(serialize = ".text", serialize = ".TEXT")]
+    Text,
+    /// Assemble what follows into the section holding initialized data.
+    #[strum(serialize = ".data", serialize = ".DATA")]
+    Data,
+    /// Assemble what follows into the section holding zero-initialized data.
+    #[strum(serialize = ".bss", serialize = ".BSS")]
+    Bss,
+    /// Assemble what follows into the section with the given name.
+    #[strum(serialize = ".section", serialize = ".SECTION")]
+    Section,
+    /// Define a symbol standing for the value of an expression.
+    #[strum(serialize = ".equ", serialize = ".EQU")]
+    Equ,
+    /// Define a symbol standing for the value of an expression, which may be redefined.
+    #[strum(serialize = ".set", serialize = ".SET")]
+    Set,
+    /// Place the values awaiting a literal pool into a pool right here.
+    #[strum(serialize = ".ltorg", serialize = ".LTORG")]
+    Ltorg,
+    /// Make each label visible to the other files of the program.
+    #[strum(serialize = ".global", serialize = ".GLOBAL")]
+    Global,
+    /// Use each label made visible by another file of the program.
+    #[strum(serialize = ".extern", serialize = ".EXTERN")]
+    Extern,
+}
+
+impl Directive {
+    /// Check whether the directive switches the section being assembled into.
+    pub fn is_section(self) -> bool {
+        matches!(
+            self,
+            Directive::Text | Directive::Data | Directive::Bss | Directive::Section
+        )
+    }
+    /// Check whether the directive takes no operands.
+    pub fn takes_no_operands(self) -> bool {
+        self != Directive::Section && (self.is_section() || self == Directive::Ltorg)
+    }
+    /// Check whether the directive sets which labels are visible across files.
+    pub fn is_visibility(self) -> bool {
+        matches!(self, Directive::Global | Directive::Extern)
+    }
+    /// Check whether the directive defines a symbol rather than emitting anything.
+    pub fn is_definition(self) -> bool {
+        matches!(self, Directive::Equ | Directive::Set)
+    }
+}
+
 #[derive(Clone, EnumString, Eq, Debug, Hash, PartialEq, ToString)]
 pub enum Label {
     #[strum(default = "true")]
@@ -27,7 +107,48 @@ pub enum Token {
     Opcode(Opcode),
     ConditionCode(ConditionCode),
     Register(Register),
+    /// The registers of a register list such as `{r4-r7, lr}`, in the order they were written.
+    RegisterList(Vec<Register>),
     Literal(Literal),
+    /// The shift applied to the last operand, such as `LSL` in `ADD R0, R1, R2, LSL #2`.
+    Shift(Shift),
     Separator(Separator),
     Label(Label),
+    Directive(Directive),
+    /// A string literal such as `"hello\n"`, with its escape sequences resolved.
+    Text(String),
+}
+
+impl Token {
+    /// Describe the kind of token for use in diagnostics.
+    pub fn describe(&self) -> String {
+        match self {
+            Token::Opcode(_) => String::from("an opcode"),
+            Token::ConditionCode(_) => String::from("a condition code"),
+            Token::Register(_) => String::from("a register"),
+            Token::RegisterList(_) => String::from("a register list"),
+            Token::Literal(_) => String::from("an immediate"),
+            Token::Shift(_) => String::from("a shift"),
+            Token::Separator(separator) => format!("`{}`", separator.to_string()),
+            Token::Label(_) => String::from("a label"),
+            Token::Directive(_) => String::from("a directive"),
+            Token::Text(_) => String::from("a string"),
+        }
+    }
+}
+
+/// The location of a lexeme within a line of source code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    /// The column the lexeme starts at, starting at 1.
+    pub column: usize,
+    /// The lexeme as written in the source.
+    pub text: String,
+}
+
+/// A lexeme that could not be assembled, along with the reason why.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub reason: String,
 }
@@ -0,0 +1,364 @@
+use super::conditional::Conditional;
+use super::expression::{is_symbol, is_symbol_char, is_symbol_start};
+use super::lexer::{lexer, Comment, Diagnostic, Label, Span, Token};
+use super::{ADR, INCLUDE};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// The directive starting the definition of a macro.
+const MACRO: [&str; 2] = [".macro", ".MACRO"];
+
+/// The directive ending the definition of a macro.
+const ENDM: [&str; 2] = [".endm", ".ENDM"];
+
+/// The prefix of a parameter within the body of a macro, as in `\reg`.
+const PARAMETER_PREFIX: char = '\\';
+
+/// A directive delimiting the definition of a macro, which is handled before lexing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Delimiter {
+    /// `.macro name param, ...` starts the definition.
+    Macro,
+    /// `.endm` ends the definition.
+    Endm,
+}
+
+impl Delimiter {
+    /// Get the delimiter starting the line split into the given lexemes, if any.
+    pub fn find(spans: &[Span]) -> Option<Delimiter> {
+        let text = spans.first()?.text.as_str();
+        if MACRO.contains(&text) {
+            Some(Delimiter::Macro)
+        } else if ENDM.contains(&text) {
+            Some(Delimiter::Endm)
+        } else {
+            None
+        }
+    }
+}
+
+/// A macro standing for the lines between `.macro` and `.endm`.
+pub struct Macro {
+    pub name: String,
+    /// The names of the parameters, which are written `\name` in the body.
+    params: Vec<Span>,
+    /// Every line of the body, along with its line number in the source.
+    pub body: Vec<(usize, String)>,
+    /// The name of the source file the macro is defined in.
+    pub file: String,
+    /// The line the macro is defined on, starting at 1.
+    pub line: usize,
+    /// The source code of the line the macro is defined on.
+    pub source: String,
+    /// The location of the name of the macro in its definition.
+    pub span: Span,
+}
+
+/// Check whether the word is a directive handled before lexing, which is lexed as a label.
+fn is_preprocessed(word: &str) -> bool {
+    [&MACRO, &ENDM, &INCLUDE]
+        .iter()
+        .any(|names| names.contains(&word))
+        || Conditional::from_str(word).is_ok()
+}
+
+/// Describe the lexeme, unless it is a symbol which would be lexed as a label, and could thus
+/// name a macro.
+fn describe(span: &Span) -> Option<String> {
+    if span.text == ADR {
+        return Some(String::from("an opcode"));
+    }
+    if is_preprocessed(&span.text) {
+        return Some(String::from("a directive"));
+    }
+    match lexer(&span.text).as_deref() {
+        Ok([(Token::Label(_), _)]) if is_symbol(&span.text) => None,
+        Ok([(token, _), ..]) => Some(token.describe()),
+        _ => Some(format!("`{}`", span.text)),
+    }
+}
+
+/// Get the label declared by the line, if any.
+fn declaration(source: &str) -> Option<String> {
+    match lexer(source).ok()?.first()? {
+        (Token::Label(Label::Name(name)), _) if name != ADR && !is_preprocessed(name) => {
+            Some(name.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Rename every given symbol of the line, leaving strings and characters untouched.
+fn rename(source: &str, names: &HashMap<String, String>) -> String {
+    let mut renamed = String::new();
+    let mut symbol = String::new();
+    // The quote of the string or character being read, and whether its last character escapes
+    // the next one.
+    let (mut quote, mut escaped) = (None, false);
+    // Whether the last character may continue a symbol, such as the `0` of `0x10`.
+    let mut in_word = false;
+    for c in source.chars() {
+        if let Some(delimiter) = quote {
+            renamed.push(c);
+            quote = if escaped || c != delimiter {
+                Some(delimiter)
+            } else {
+                None
+            };
+            escaped = !escaped && c == '\\';
+            continue;
+        }
+        if !symbol.is_empty() && is_symbol_char(c) {
+            symbol.push(c);
+            continue;
+        }
+        renamed.push_str(names.get(&symbol).unwrap_or(&symbol));
+        symbol.clear();
+        if !in_word && is_symbol_start(c) {
+            symbol.push(c);
+        } else {
+            if c == '"' || c == '\'' {
+                quote = Some(c);
+            }
+            renamed.push(c);
+        }
+        in_word = is_symbol_char(c);
+    }
+    renamed.push_str(names.get(&symbol).unwrap_or(&symbol));
+    renamed
+}
+
+/// Split the arguments of an invocation at the commas outside of parentheses, brackets, braces,
+/// strings and characters, up to a comment.
+pub fn arguments(text: &str) -> Vec<String> {
+    let mut args = vec![String::new()];
+    let (mut quote, mut escaped, mut depth) = (None, false, 0);
+    for c in text.chars() {
+        let arg = args.last_mut().unwrap();
+        if let Some(delimiter) = quote {
+            arg.push(c);
+            quote = if escaped || c != delimiter {
+                Some(delimiter)
+            } else {
+                None
+            };
+            escaped = !escaped && c == '\\';
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(String::new());
+                continue;
+            }
+            _ if Comment::from_str(&c.to_string()).is_ok() => break,
+            _ => (),
+        }
+        arg.push(c);
+    }
+    match args.as_slice() {
+        [arg] if arg.trim().is_empty() => Vec::new(),
+        _ => args.iter().map(|arg| arg.trim().to_string()).collect(),
+    }
+}
+
+/// Find the invocation of a macro in the line split into the given lexemes, which starts with
+/// either the name of the macro or a label followed by it. Get the label, if any, and the name.
+pub fn invocation(
+    spans: &[Span],
+    is_macro: impl Fn(&str) -> bool,
+) -> Option<(Option<&Span>, &Span)> {
+    match spans {
+        [name, ..] if is_macro(&name.text) => Some((None, name)),
+        [label, name, ..] if is_macro(&name.text) && describe(label).is_none() => {
+            Some((Some(label), name))
+        }
+        _ => None,
+    }
+}
+
+impl Macro {
+    /// Start the definition of a macro from the line `.macro name param, ...` split into the
+    /// given lexemes, with an empty body.
+    pub fn new(spans: &[Span], line: usize, source: &str, file: &str) -> Macro {
+        // The name is missing, so point just past the end of the line.
+        let end = Span {
+            column: source.trim_end().chars().count() + 1,
+            text: String::new(),
+        };
+        let span = spans.get(1).unwrap_or(&end).clone();
+        Macro {
+            name: span.text.clone(),
+            params: spans
+                .iter()
+                .skip(2)
+                .filter(|param| param.text != ",")
+                .cloned()
+                .collect(),
+            body: Vec::new(),
+            file: file.to_string(),
+            line,
+            source: source.to_string(),
+            span,
+        }
+    }
+    /// Ensure the macro is named after a symbol lexed as a label, and that its parameters are
+    /// distinct symbols.
+    pub fn check(&self) -> Result<(), Diagnostic> {
+        let found = match self.name.as_str() {
+            "" => Some(String::from("the end of the line")),
+            _ => describe(&self.span),
+        };
+        if let Some(found) = found {
+            return Err(Diagnostic {
+                span: self.span.clone(),
+                reason: format!("expected the name of a macro, found {}", found),
+            });
+        }
+        for (index, param) in self.params.iter().enumerate() {
+            if !is_symbol(&param.text) {
+                return Err(Diagnostic {
+                    span: param.clone(),
+                    reason: format!("expected the name of a parameter, found `{}`", param.text),
+                });
+            }
+            if self.params[..index]
+                .iter()
+                .any(|other| other.text == param.text)
+            {
+                return Err(Diagnostic {
+                    span: param.clone(),
+                    reason: format!("parameter `{}` is already defined", param.text),
+                });
+            }
+        }
+        Ok(())
+    }
+    /// Check that the given number of arguments matches the parameters of the macro.
+    pub fn check_arguments(&self, n_args: usize) -> Result<(), String> {
+        match self.params.len() {
+            n_params if n_params == n_args => Ok(()),
+            1 => Err(format!(
+                "macro `{}` takes 1 argument, found {}",
+                self.name, n_args
+            )),
+            n_params => Err(format!(
+                "macro `{}` takes {} arguments, found {}",
+                self.name, n_params, n_args
+            )),
+        }
+    }
+    /// Replace every parameter of the line with the matching argument.
+    fn substitute(&self, source: &str, args: &[String]) -> String {
+        let mut substituted = String::new();
+        let mut rest = source;
+        while let Some(index) = rest.find(PARAMETER_PREFIX) {
+            substituted.push_str(&rest[..index]);
+            let after = &rest[index + 1..];
+            let len = after.find(|c| !is_symbol_char(c)).unwrap_or(after.len());
+            match self
+                .params
+                .iter()
+                .position(|param| param.text == after[..len])
+            {
+                Some(param) => {
+                    substituted.push_str(&args[param]);
+                    rest = &after[len..];
+                }
+                None => {
+                    substituted.push(PARAMETER_PREFIX);
+                    rest = after;
+                }
+            }
+        }
+        substituted.push_str(rest);
+        substituted
+    }
+    /// Expand the macro with the given arguments into the lines of its body, along with their
+    /// line numbers. Labels declared in the body are suffixed with the number of the expansion,
+    /// so that every expansion declares labels of its own.
+    pub fn expand(
+        &self,
+        args: &[String],
+        n: usize,
+        is_macro: impl Fn(&str) -> bool,
+    ) -> Vec<(usize, String)> {
+        let lines: Vec<(usize, String)> = self
+            .body
+            .iter()
+            .map(|(line, source)| (*line, self.substitute(source, args)))
+            .collect();
+        let locals: HashMap<String, String> = lines
+            .iter()
+            .filter_map(|(_, source)| declaration(source))
+            .filter(|name| !is_macro(name))
+            .map(|name| {
+                let local = format!("{}..{}", name, n);
+                (name, local)
+            })
+            .collect();
+        lines
+            .into_iter()
+            .map(|(line, source)| (line, rename(&source, &locals)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_arguments() {
+        assert_eq!(
+            arguments(" r0, [r1, #4], ',', \"a, b\" ; c, d"),
+            vec!["r0", "[r1, #4]", "','", "\"a, b\""]
+        );
+        assert!(arguments("  ; comment").is_empty());
+    }
+
+    #[test]
+    fn test_rename() {
+        let names: HashMap<String, String> = vec![(String::from("loop"), String::from("loop..1"))]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            rename("loop b loop.end, 0xloop \"loop\" #loop+1", &names),
+            "loop..1 b loop.end, 0xloop \"loop\" #loop..1+1"
+        );
+    }
+
+    #[test]
+    fn test_expand() {
+        let spans = vec![
+            Span {
+                column: 1,
+                text: String::from(".macro"),
+            },
+            Span {
+                column: 8,
+                text: String::from("count"),
+            },
+            Span {
+                column: 14,
+                text: String::from("reg"),
+            },
+        ];
+        let mut definition = Macro::new(&spans, 1, ".macro count reg", "pgrm.asm");
+        definition.body = vec![
+            (2, String::from("loop subs \\reg, \\reg, #1")),
+            (3, String::from("    bne loop ; \\n")),
+        ];
+        assert_eq!(
+            definition.expand(&[String::from("r2")], 7, |_| false),
+            vec![
+                (2, String::from("loop..7 subs r2, r2, #1")),
+                (3, String::from("    bne loop..7 ; \\n"))
+            ]
+        );
+    }
+
+}
@@ -1,157 +1,2377 @@
+mod conditional;
+mod error;
+mod expression;
 mod lexer;
+mod macros;
 mod parser;
 
+use super::assembler::conditional::{Block, Conditional};
+pub use super::assembler::error::*;
+use super::assembler::expression::is_symbol;
 pub use super::assembler::lexer::Label;
-use super::assembler::lexer::{lexer, Token, Token::*};
-use super::util::{EncoderDecoder, Form, Instruction, Literal::*};
+use super::assembler::lexer::{
+    lexer, split, Diagnostic, Directive, Separator, Span, Token, Token::*,
+};
+use super::assembler::macros::{arguments, invocation, Delimiter, Macro};
+use super::util::image::{self, N_BYTES_IN_INSTRUCTION};
+use super::util::{
+    EncoderDecoder, Form, Layout, Literal, Literal::*, Opcode, Program, Register, Segment,
+    TEXT_SECTION,
+};
+use super::vm::N_BYTES_IN_MAIN_MEMORY;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// The name of the section holding zero-initialized data, which cannot hold anything else.
+const BSS_SECTION: &str = ".bss";
+
+/// The directive assembling the lines of another source file in its place.
+const INCLUDE: [&str; 2] = [".include", ".INCLUDE"];
+
+/// The symbol execution starts at when the layout does not name one.
+const DEFAULT_ENTRY: &str = "_start";
+
+/// The number of bits encoding the signed offset from a load to its literal pool entry.
+const N_BITS_IN_LITERAL_OFFSET: usize = 16;
+
+/// The mnemonic of the pseudo-instruction loading an address relative to the program counter.
+const ADR: &str = "adr";
+
+/// The number of macro invocations an expansion may be nested in, which stops the expansion of
+/// recursive macros.
+const MAX_MACRO_DEPTH: usize = 100;
+
+/// The number of bits encoding the index of the instruction a branch targets.
+const N_BITS_IN_BRANCH_TARGET: usize = 20;
+
+/// A location in the program being assembled.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Location {
+    /// The index of the section, in order of first appearance in the source.
+    section: usize,
+    /// The byte offset from the start of the section.
+    offset: usize,
+}
 
 struct LabelRegistry {
-    // The index of the current instruction being assembled which is used as a pointer for variable
-    // references.
-    instr_ptr: Instruction,
-    /// Defines the label as a location of an instruction in a program.
-    declaration: HashMap<Label, Instruction>,
-    /// Label is interpreted by the assembler as a refernce to a target address.
-    reference: HashMap<Instruction, Label>,
+    // The location of the current expression being assembled, which is used as a pointer for
+    // variable declarations.
+    location: Location,
+    /// The index of the source file of the current expression, which sees its own labels and the
+    /// global labels it declares external.
+    module: usize,
+    /// Defines the label of a source file as the location of an expression in a program.
+    declaration: HashMap<(usize, Label), Location>,
+    /// The source file declaring every label made global by `.global`.
+    globals: HashMap<Label, usize>,
+    /// The labels every source file declares external by `.extern`.
+    externs: HashSet<(usize, Label)>,
+    /// The base address of every section, once they have been placed in main memory.
+    bases: Vec<usize>,
 }
 
 impl LabelRegistry {
     pub fn new() -> LabelRegistry {
         LabelRegistry {
-            instr_ptr: 0,
+            location: Location {
+                section: 0,
+                offset: 0,
+            },
+            module: 0,
             declaration: HashMap::new(),
-            reference: HashMap::new(),
+            globals: HashMap::new(),
+            externs: HashSet::new(),
+            bases: Vec::new(),
+        }
+    }
+    /// Declare the label as the current location, unless the current source file already
+    /// declares it.
+    pub fn register_variable_declaration(
+        &mut self,
+        label: Label,
+        span: &Span,
+    ) -> Result<(), Diagnostic> {
+        if self.is_declared(&label) {
+            return Err(Diagnostic {
+                span: span.clone(),
+                reason: format!("label `{}` is already defined", span.text),
+            });
+        }
+        self.declaration.insert((self.module, label), self.location);
+        Ok(())
+    }
+    /// Check whether the current source file declares the label.
+    pub fn is_declared(&self, label: &Label) -> bool {
+        self.declaration.contains_key(&(self.module, label.clone()))
+    }
+    /// Get the address of the expression declaring the label in the current source file, or in
+    /// the source file making it global if the current one declares it external. Addresses are
+    /// only known once sections have been placed in main memory.
+    pub fn get_address(&self, label: &Label) -> Option<usize> {
+        let mut key = (self.module, label.clone());
+        if !self.declaration.contains_key(&key) && self.externs.contains(&key) {
+            key.0 = *self.globals.get(label)?;
         }
+        self.get_location_address(&key)
     }
-    pub fn register_variable_reference(&mut self, label: Label) {
-        self.reference.insert(self.instr_ptr, label);
+    /// Get the address of the label as seen from outside of the program: the global label, or
+    /// the label of the first source file otherwise.
+    pub fn get_global_address(&self, label: &Label) -> Option<usize> {
+        let module = self.globals.get(label).copied().unwrap_or(0);
+        self.get_location_address(&(module, label.clone()))
     }
-    pub fn register_variable_declaration(&mut self, label: Label) {
-        self.declaration.insert(label, self.instr_ptr);
+    fn get_location_address(&self, key: &(usize, Label)) -> Option<usize> {
+        let location = self.declaration.get(key)?;
+        Some(self.bases.get(location.section)? + location.offset)
     }
-    pub fn get_reference(&mut self) -> &Instruction {
-        self.declaration
-            .get(self.reference.get(&(&self.instr_ptr)).unwrap())
-            .unwrap()
+    /// Get the address of the expression declaring the label referenced by the given span.
+    pub fn get_reference(&self, label: &Label, span: &Span) -> Result<usize, Diagnostic> {
+        self.get_address(label).ok_or_else(|| Diagnostic {
+            span: span.clone(),
+            reason: format!("undefined label `{}`", span.text),
+        })
     }
-    pub fn incr_instr_ptr(&mut self) {
-        self.instr_ptr += 1;
+    pub fn advance(&mut self, n_bytes: usize) {
+        self.location.offset += n_bytes;
     }
-    pub fn reset_instr_ptr(&mut self) {
-        self.instr_ptr = 0;
+    pub fn seek(&mut self, location: Location) {
+        self.location = location;
     }
 }
 
+/// A section of the program being assembled.
+struct Section {
+    name: String,
+    /// The number of bytes assembled into the section so far.
+    size: usize,
+    /// The power of two the base address of the section must be a multiple of, which is the
+    /// largest alignment requested by `.align` in the section.
+    alignment: u32,
+    /// The index in the program of every `LDR Rd, =value` in the section whose value awaits a
+    /// literal pool.
+    literals: Vec<usize>,
+}
+
+/// A pseudo-instruction, which assembles into whichever instruction suits the value of its
+/// operand.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Pseudo {
+    /// `LDR Rd, =value` loads the value into the register, with `MOV` or `MVN`.
+    Load,
+    /// `LDR Rd, =value` loads the value into the register from a literal pool, with `LDR`
+    /// relative to the program counter, when `MOV` and `MVN` cannot load it.
+    LoadLiteral,
+    /// `ADR Rd, label` loads the address into the register relative to the program counter, with
+    /// `ADD` or `SUB`.
+    Address,
+}
+
+/// A symbol defined by `.equ` or `.set`.
+struct Constant {
+    /// The index of the definition among every definition of a symbol in the program.
+    order: usize,
+    /// The operand the symbol stands for, which is evaluated wherever the symbol is used.
+    value: Token,
+    /// Whether the definition was made by `.set`, which allows the symbol to be redefined.
+    is_set: bool,
+}
+
 #[derive(Clone, Debug)]
 struct Expression {
     tokens: Vec<Token>,
-    form: Form,
+    /// The location of every token in the source.
+    spans: Vec<Span>,
+    /// The form of the instruction held by the expression, or `None` if it holds a directive.
+    form: Option<Form>,
+    /// The pseudo-instruction the instruction was written as, if any.
+    pseudo: Option<Pseudo>,
+    /// The location of the literal pool entry holding the value loaded by the instruction.
+    literal: Option<Location>,
+    /// The location of the expression in the program.
+    location: Location,
+    /// The number of symbols defined by `.equ` and `.set` before the expression, which tells
+    /// which definition of a redefined symbol is in effect.
+    definitions: usize,
+    /// The name of the source file the expression was declared in.
+    file: String,
+    /// The line the expression was declared on, starting at 1.
+    line: usize,
+    /// The source code of the expression.
+    source: String,
+    /// The invocations of the macros the expression was expanded from, outermost first.
+    invocations: Vec<Invocation>,
+    /// The index of the source file the expression belongs to, which sets the labels and
+    /// constants it sees.
+    module: usize,
+}
+
+/// Get the directive held by the tokens along with its operands, if any.
+fn directive(tokens: &[Token]) -> Option<(Directive, &[Token])> {
+    let index = tokens
+        .iter()
+        .position(|token| matches!(token, Directive(_)))?;
+    match tokens[index] {
+        Directive(directive) => Some((directive, &tokens[index + 1..])),
+        _ => None,
+    }
+}
+
+/// Get the name of the section switched to by the directive, if it is a section directive.
+fn section_name(directive: Directive, operands: &[Token]) -> Option<String> {
+    match (directive, operands.first()) {
+        (Directive::Section, Some(Label(Label::Name(name)))) => Some(name.clone()),
+        (Directive::Section, Some(Directive(name))) => section_name(*name, &[]),
+        (Directive::Text, _) => Some(String::from(TEXT_SECTION)),
+        (Directive::Data, _) => Some(String::from(".data")),
+        (Directive::Bss, _) => Some(String::from(BSS_SECTION)),
+        _ => None,
+    }
+}
+
+/// Convert the value of an expression into an immediate, ensuring it fits in a word.
+fn to_literal(value: i64) -> Result<Literal, String> {
+    if value < i32::MIN as i64 || value > u32::MAX as i64 {
+        return Err(format!("value {} does not fit in 32 bits", value));
+    }
+    Ok(Immediate(value.to_string()))
+}
+
+/// Rewrite the pseudo-instruction held by the lexemes, if any, into the instruction it assembles
+/// into when its operand is small and positive: `LDR Rd, =value` into `MOV Rd, #value` and
+/// `ADR Rd, label` into `ADD Rd, PC, #label`.
+fn expand(lexemes: &mut Vec<(Token, Span)>) -> Option<Pseudo> {
+    // Skip the label leading the expression, unless it is the mnemonic of `ADR`.
+    let start = match lexemes.get(1) {
+        Some((Label(_), _)) | Some((Opcode(_), _)) => 1,
+        _ => 0,
+    };
+    let (pseudo, opcode) = match &lexemes[start..] {
+        [(Label(Label::Name(name)), _), (Register(_), _), (Separator(Separator::Comma), _), _]
+            if name.eq_ignore_ascii_case(ADR) =>
+        {
+            (Pseudo::Address, Opcode::ADD)
+        }
+        [(Opcode(Opcode::LDR), _), (Register(_), _), (Separator(Separator::Comma), _), (Separator(Separator::Equals), _), _]
+        | [(Opcode(Opcode::LDR), _), (ConditionCode(_), _), (Register(_), _), (Separator(Separator::Comma), _), (Separator(Separator::Equals), _), _] => {
+            (Pseudo::Load, Opcode::MOV)
+        }
+        _ => return None,
+    };
+    let span = lexemes[start].1.clone();
+    lexemes[start].0 = Opcode(opcode);
+    let (operand, operand_span) = lexemes.pop().unwrap();
+    match pseudo {
+        Pseudo::Address => lexemes.extend(vec![
+            (Register(Register::PC), span.clone()),
+            (Separator(Separator::Comma), span),
+        ]),
+        // Drop the `=` prefixing the value.
+        _ => {
+            lexemes.pop();
+        }
+    }
+    // The instruction to assemble into depends on the value of the operand, which is only
+    // checked once it is known.
+    let operand = match operand {
+        Label(_) | Literal(_) => Literal(Expression(
+            operand_span.text.trim_start_matches('#').to_string(),
+        )),
+        operand => operand,
+    };
+    lexemes.push((operand, operand_span));
+    Some(pseudo)
+}
+
+/// Report a diagnostic against the given line of the source file, expanded from the given
+/// invocations of macros, outermost first.
+fn error(
+    file: &str,
+    line: usize,
+    source: &str,
+    diagnostic: Diagnostic,
+    invocations: &[Invocation],
+) -> AssemblyError {
+    AssemblyError {
+        file: file.to_string(),
+        line,
+        column: diagnostic.span.column,
+        token: diagnostic.span.text,
+        reason: diagnostic.reason,
+        source: source.to_string(),
+        invocations: invocations.iter().rev().cloned().collect(),
+    }
+}
+
+/// Get the canonical form of the path of a source file, or the path itself if the file does not
+/// exist.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Get the number of bytes needed to align the location to the given power of two.
+fn padding(location: usize, power: u32) -> usize {
+    let alignment = 1 << power;
+    (alignment - location % alignment) % alignment
 }
 
 pub struct Assembler {
     registry: LabelRegistry,
     program: Vec<Expression>,
+    layout: Layout,
+    /// Every section of the program, in order of first appearance in the source.
+    sections: Vec<Section>,
+    /// Every definition of the symbols defined by `.equ` and `.set` in each source file, in
+    /// order.
+    constants: HashMap<(usize, String), Vec<Constant>>,
+    /// The number of definitions made so far.
+    n_definitions: usize,
+    /// Every macro defined so far, by name.
+    macros: HashMap<String, Macro>,
+    /// The macro being defined, whose body is recorded up to `.endm`.
+    definition: Option<Macro>,
+    /// The invocations of the macros being expanded, outermost first.
+    expansion: Vec<Invocation>,
+    /// The number of expansions of macros so far.
+    n_expansions: usize,
+    /// The name of every source file of the program, in order.
+    modules: Vec<String>,
+    /// The directories searched for the files included by `.include`, after the directory of
+    /// the file including them.
+    include_paths: Vec<PathBuf>,
+    /// The source files being read, outermost first, which must not include themselves.
+    files: Vec<PathBuf>,
+    /// The symbols defined for every source file before it is read, along with their values.
+    symbols: Vec<(String, String)>,
+    /// The blocks of conditional assembly the current line is in, outermost first.
+    blocks: Vec<Block>,
+}
+
+impl Default for Assembler {
+    fn default() -> Assembler {
+        Assembler::with_layout(Layout::default())
+    }
 }
 
 impl Assembler {
+    /// Instantiate an assembler placing every section right after the one preceding it, starting
+    /// with `.text` at address zero.
     pub fn new() -> Assembler {
+        Assembler::default()
+    }
+    /// Instantiate an assembler placing sections and setting the entry point of the program as
+    /// described by the layout.
+    pub fn with_layout(layout: Layout) -> Assembler {
         Assembler {
             registry: LabelRegistry::new(),
             program: Vec::new(),
+            layout,
+            sections: vec![Section {
+                name: String::from(TEXT_SECTION),
+                size: 0,
+                alignment: 2,
+                literals: Vec::new(),
+            }],
+            constants: HashMap::new(),
+            n_definitions: 0,
+            macros: HashMap::new(),
+            definition: None,
+            expansion: Vec::new(),
+            n_expansions: 0,
+            modules: Vec::new(),
+            include_paths: Vec::new(),
+            files: Vec::new(),
+            symbols: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+    /// Search the directory for the files included by `.include`, after the directories added
+    /// before it.
+    pub fn add_include_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.include_paths.push(path.into());
+    }
+    /// Define the symbol as standing for the value of the expression in every source file, as
+    /// `.equ` would.
+    pub fn add_symbol(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if !is_symbol(name) {
+            return Err(format!("invalid symbol name `{}`", name));
+        }
+        if self.symbols.iter().any(|(symbol, _)| symbol == name) {
+            return Err(format!("symbol `{}` is already defined", name));
+        }
+        expression::Expression::parse(value)
+            .map_err(|reason| format!("invalid expression `{}`: {}", value, reason))?;
+        self.symbols.push((name.to_string(), value.to_string()));
+        Ok(())
+    }
+    /// Assemble the source file found at the given path.
+    pub fn read_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Program, Error> {
+        self.read_files(&[path])
+    }
+    /// Assemble the source files found at the given paths into a single program.
+    pub fn read_files<P: AsRef<Path>>(&mut self, paths: &[P]) -> Result<Program, Error> {
+        let mut sources = Vec::new();
+        for path in paths {
+            let name = path.as_ref().display().to_string();
+            let file = File::open(path)
+                .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", name, err)))?;
+            sources.push((BufReader::new(file), name));
         }
+        self.read_all(
+            sources
+                .iter_mut()
+                .map(|(reader, name)| (reader, name.as_str()))
+                .collect(),
+        )
     }
-    pub fn read_file(&mut self) -> Vec<Instruction> {
-        let mut program = Vec::new();
-        let file = File::open("assembly/pgrm.asm").unwrap();
-        for buf in BufReader::new(file).lines() {
-            let expression = buf.unwrap();
-            // Convert the expression into meaningful lexemes.
-            let mut tokens = lexer(expression);
-            // Ensure expression is syntactically correct.
-            if let Ok(form) = parser::run(&mut tokens) {
-                self.optimized_tokens(&mut tokens, form);
+    /// Assemble the source read from the standard input.
+    pub fn read_stdin(&mut self) -> Result<Program, Error> {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        self.read_str(&source, "<stdin>")
+    }
+    /// Assemble the source held in memory, referring to it by the given name in diagnostics.
+    pub fn read_str(&mut self, source: &str, file: &str) -> Result<Program, Error> {
+        self.read(source.as_bytes(), file)
+    }
+    /// Assemble the source provided by the given reader, referring to it by the given name in
+    /// diagnostics.
+    pub fn read<R: BufRead>(&mut self, reader: R, file: &str) -> Result<Program, Error> {
+        self.read_all(vec![(reader, file)])
+    }
+    /// Assemble the sources provided by the given readers into a single program, referring to
+    /// each by the given name in diagnostics. Every source file starts in `.text`, and only sees
+    /// the labels, constants and macros it declares, along with the labels another source file
+    /// makes global by `.global` and it declares external by `.extern`. Data declared by
+    /// directives is laid out in little-endian byte order, and every section is padded with zero
+    /// bytes to a whole number of words.
+    pub fn read_all<R: BufRead>(&mut self, sources: Vec<(R, &str)>) -> Result<Program, Error> {
+        let mut errors = Vec::new();
+        for (module, (reader, file)) in sources.into_iter().enumerate() {
+            self.registry.module = module;
+            self.modules.push(file.to_string());
+            self.macros.clear();
+            self.switch_section(String::from(TEXT_SECTION));
+            for (name, value) in self.symbols.clone() {
+                let span = Span {
+                    column: 1,
+                    text: name.clone(),
+                };
+                let value = Literal(Expression(value));
+                // The source file has yet to define anything the symbol could clash with.
+                self.define(Directive::Equ, &Label(Label::Name(name)), &span, &value)
+                    .unwrap();
+                self.n_definitions += 1;
+            }
+            self.read_source(reader, file, &mut errors)?;
+        }
+        // Values still awaiting a literal pool are placed at the end of their section.
+        for section in 0..self.sections.len() {
+            if !self.sections[section].literals.is_empty() {
+                self.switch_section(self.sections[section].name.clone());
+                self.flush_literals();
             }
         }
-        self.registry.reset_instr_ptr();
-        // Convert the program to bytecode.
+        self.sections[self.registry.location.section].size = self.registry.location.offset;
+        // Assembly errors may throw the size of sections off, so they take precedence.
+        self.registry.bases = match self.place_sections() {
+            Ok(bases) => bases,
+            Err(_) if !errors.is_empty() => return Err(Error::Assembly(errors)),
+            Err(err) => return Err(err),
+        };
+        // Convert the program to bytecode and data.
+        // The bytes assembled into every section.
+        let mut contents: Vec<Vec<u8>> = vec![Vec::new(); self.sections.len()];
         for expr in self.program.clone() {
-            program.push(self.get_bytecode(expr));
-            self.registry.incr_instr_ptr();
+            self.registry.seek(expr.location);
+            self.registry.module = expr.module;
+            let encoding = match expr.form {
+                Some(_) => self
+                    .get_bytecode(&expr)
+                    .map(|instr| instr.to_le_bytes().to_vec()),
+                None => self.get_data(&expr),
+            };
+            match encoding {
+                Ok(encoding) => {
+                    let bytes = &mut contents[expr.location.section];
+                    bytes.resize(expr.location.offset, 0);
+                    bytes.extend(encoding);
+                }
+                Err(diagnostic) => errors.push(error(
+                    &expr.file,
+                    expr.line,
+                    &expr.source,
+                    diagnostic,
+                    &expr.invocations,
+                )),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(Error::Assembly(errors));
+        }
+        let mut segments = Vec::new();
+        for ((section, mut bytes), base) in
+            self.sections.iter().zip(contents).zip(&self.registry.bases)
+        {
+            bytes.resize(section.size + padding(section.size, 2), 0);
+            segments.push(Segment {
+                name: section.name.clone(),
+                address: *base as u32,
+                words: image::from_bytes(&bytes)?,
+            });
         }
-        program
+        Ok(Program {
+            segments,
+            entry: self.get_entry()? as u32,
+            stack_top: self.layout.stack_top,
+        })
+    }
+    /// Place every section at its base address in the layout, or right after the section
+    /// preceding it otherwise, ensuring sections neither overlap nor run past the end of main
+    /// memory, and that every instruction of `.text` can be branched to.
+    fn place_sections(&self) -> Result<Vec<usize>, Error> {
+        let mut bases = Vec::new();
+        let mut end = 0;
+        for section in &self.sections {
+            let base = match self.layout.bases.get(&section.name) {
+                Some(base) if padding(*base as usize, section.alignment) != 0 => {
+                    return Err(Error::Layout(format!(
+                        "base address {:#010X} of section `{}` is not aligned to {} bytes",
+                        base,
+                        section.name,
+                        1 << section.alignment
+                    )));
+                }
+                Some(base) => *base as usize,
+                None => end + padding(end, section.alignment),
+            };
+            end = base + section.size + padding(section.size, 2);
+            if end as u64 > N_BYTES_IN_MAIN_MEMORY {
+                return Err(Error::Layout(format!(
+                    "section `{}` does not fit in main memory",
+                    section.name
+                )));
+            }
+            // Branches encode the index of the instruction they target, which limits how far
+            // `.text` may be placed.
+            let n_branch_targets = 1 << N_BITS_IN_BRANCH_TARGET;
+            if section.name == TEXT_SECTION && end / N_BYTES_IN_INSTRUCTION > n_branch_targets {
+                return Err(Error::Layout(format!(
+                    "section `{}` at {:#010X} runs past {:#010X}, the end of the range of a branch",
+                    section.name,
+                    base,
+                    n_branch_targets * N_BYTES_IN_INSTRUCTION
+                )));
+            }
+            bases.push(base);
+        }
+        for (i, section) in self.sections.iter().enumerate() {
+            for (j, other) in self.sections.iter().enumerate().take(i) {
+                let overlaps =
+                    bases[i] < bases[j] + other.size && bases[j] < bases[i] + section.size;
+                if overlaps {
+                    return Err(Error::Layout(format!(
+                        "section `{}` at {:#010X} overlaps section `{}` at {:#010X}",
+                        section.name, bases[i], other.name, bases[j]
+                    )));
+                }
+            }
+        }
+        Ok(bases)
+    }
+    /// Get the address execution starts at: the entry symbol of the layout, or `_start` if the
+    /// program defines it, or the start of `.text` otherwise.
+    fn get_entry(&self) -> Result<usize, Error> {
+        let entry = match self.layout.entry {
+            Some(ref symbol) => self
+                .registry
+                .get_global_address(&Label::Name(symbol.clone()))
+                .ok_or_else(|| Error::Layout(format!("undefined entry symbol `{}`", symbol)))?,
+            None => match self
+                .registry
+                .get_global_address(&Label::Name(String::from(DEFAULT_ENTRY)))
+            {
+                Some(address) => address,
+                None => self.registry.bases[0],
+            },
+        };
+        if padding(entry, 2) != 0 {
+            return Err(Error::Layout(format!(
+                "entry point {:#010X} is not word aligned",
+                entry
+            )));
+        }
+        Ok(entry)
+    }
+    /// Assemble what follows into the section with the given name, creating it on first use.
+    fn switch_section(&mut self, name: String) {
+        let location = self.registry.location;
+        self.sections[location.section].size = location.offset;
+        let section = match self
+            .sections
+            .iter()
+            .position(|section| section.name == name)
+        {
+            Some(section) => section,
+            None => {
+                self.sections.push(Section {
+                    name,
+                    size: 0,
+                    alignment: 2,
+                    literals: Vec::new(),
+                });
+                self.sections.len() - 1
+            }
+        };
+        self.registry.seek(Location {
+            section,
+            offset: self.sections[section].size,
+        });
+    }
+    /// Assemble every line of the source file, which must not be one of the files being read,
+    /// and end the definition of any macro it starts.
+    fn read_source<R: BufRead>(
+        &mut self,
+        reader: R,
+        file: &str,
+        errors: &mut Vec<AssemblyError>,
+    ) -> io::Result<()> {
+        self.files.push(canonical(Path::new(file)));
+        for (line, buf) in (1..).zip(reader.lines()) {
+            self.read_line(&buf?, line, file, errors);
+        }
+        self.end_blocks(errors);
+        self.files.pop();
+        if let Some(definition) = self.definition.take() {
+            let diagnostic = Diagnostic {
+                reason: format!("macro `{}` is not terminated by `.endm`", definition.name),
+                span: definition.span,
+            };
+            errors.push(error(
+                &definition.file,
+                definition.line,
+                &definition.source,
+                diagnostic,
+                &[],
+            ));
+        }
+        Ok(())
+    }
+    /// Assemble a line of the source file, unless it belongs to the definition of a macro, and
+    /// report any error.
+    fn read_line(
+        &mut self,
+        source: &str,
+        line: usize,
+        file: &str,
+        errors: &mut Vec<AssemblyError>,
+    ) {
+        if let Err(diagnostic) = self.preprocess(source, line, file, errors) {
+            errors.push(error(file, line, source, diagnostic, &self.expansion));
+        }
+    }
+    /// Record the line into the body of the macro being defined, start or end the definition of
+    /// a macro, assemble the lines of the macro the line invokes or of the file it includes.
+    /// Assemble any other line.
+    fn preprocess(
+        &mut self,
+        source: &str,
+        line: usize,
+        file: &str,
+        errors: &mut Vec<AssemblyError>,
+    ) -> Result<(), Diagnostic> {
+        let spans = split(source);
+        // Conditional assembly applies to the expansions of a macro rather than its definition.
+        if self.definition.is_none() {
+            if let Some(conditional) = Conditional::find(&spans) {
+                return self.read_conditional(conditional, &spans, line, source, file);
+            }
+            if !self.blocks.iter().all(|block| block.is_assembled) {
+                return Ok(());
+            }
+        }
+        match (Delimiter::find(&spans), self.definition.as_mut()) {
+            (Some(Delimiter::Macro), Some(_)) => {
+                return Err(Diagnostic {
+                    span: spans[0].clone(),
+                    reason: String::from("macros cannot be defined within a macro"),
+                })
+            }
+            (Some(Delimiter::Endm), Some(_)) => {
+                let definition = self.definition.take().unwrap();
+                if self.check_definition(&definition).is_ok() {
+                    self.macros.insert(definition.name.clone(), definition);
+                }
+                return Ok(());
+            }
+            (None, Some(definition)) => {
+                definition.body.push((line, source.to_string()));
+                return Ok(());
+            }
+            (Some(Delimiter::Macro), None) => {
+                // The body of an invalid definition is skipped all the same, up to `.endm`.
+                let definition = Macro::new(&spans, line, source, file);
+                let result = self.check_definition(&definition);
+                self.definition = Some(definition);
+                return result;
+            }
+            (Some(Delimiter::Endm), None) => {
+                return Err(Diagnostic {
+                    span: spans[0].clone(),
+                    reason: String::from("`.endm` without a matching `.macro`"),
+                })
+            }
+            (None, None) => (),
+        }
+        if spans
+            .first()
+            .is_some_and(|span| INCLUDE.contains(&span.text.as_str()))
+        {
+            return self.include(source, file, errors);
+        }
+        let (label, span) = match invocation(&spans, |name| self.macros.contains_key(name)) {
+            Some(invocation) => invocation,
+            None => return self.read_expression(source, line, file),
+        };
+        // A label leading an invocation labels the first line of the expansion.
+        if let Some(label) = label {
+            self.declare_label(&Label::Name(label.text.clone()), label)?;
+        }
+        let definition = &self.macros[&span.text];
+        let rest: String = source
+            .chars()
+            .skip(span.column - 1 + span.text.chars().count())
+            .collect();
+        let args = arguments(&rest);
+        definition
+            .check_arguments(args.len())
+            .map_err(|reason| Diagnostic {
+                span: span.clone(),
+                reason,
+            })?;
+        if self.expansion.len() == MAX_MACRO_DEPTH {
+            return Err(Diagnostic {
+                span: span.clone(),
+                reason: format!(
+                    "macro `{}` is nested in more than {} invocations",
+                    span.text, MAX_MACRO_DEPTH
+                ),
+            });
+        }
+        self.n_expansions += 1;
+        let lines = definition.expand(&args, self.n_expansions, |name| {
+            self.macros.contains_key(name)
+        });
+        let body = definition.file.clone();
+        self.expansion.push(Invocation {
+            file: file.to_string(),
+            line,
+            column: span.column,
+            name: span.text.clone(),
+            source: source.to_string(),
+        });
+        for (line, source) in lines {
+            self.read_line(&source, line, &body, errors);
+        }
+        self.end_blocks(errors);
+        self.expansion.pop();
+        Ok(())
+    }
+    /// Open a block of conditional assembly, or switch to its `.else` branch or end it. The
+    /// condition of a block within lines that are not assembled is not checked.
+    fn read_conditional(
+        &mut self,
+        conditional: Conditional,
+        spans: &[Span],
+        line: usize,
+        source: &str,
+        file: &str,
+    ) -> Result<(), Diagnostic> {
+        let depth = (self.files.len(), self.expansion.len());
+        if conditional.opens_block() {
+            let condition = if self.blocks.iter().all(|block| block.is_assembled) {
+                self.check_condition(conditional, &spans[0], source)
+            } else {
+                Ok(false)
+            };
+            // A block whose condition cannot be checked is still ended by its `.endif`.
+            self.blocks.push(Block {
+                is_assembled: *condition.as_ref().unwrap_or(&false),
+                has_else: false,
+                depth,
+                file: file.to_string(),
+                line,
+                source: source.to_string(),
+                span: spans[0].clone(),
+            });
+            return condition.map(|_| ());
+        }
+        let block = match self.blocks.last_mut() {
+            Some(block) if block.depth == depth => block,
+            _ => {
+                return Err(Diagnostic {
+                    span: spans[0].clone(),
+                    reason: format!("`{}` without a matching `.if`", spans[0].text),
+                })
+            }
+        };
+        match conditional {
+            Conditional::Else if block.has_else => Err(Diagnostic {
+                span: spans[0].clone(),
+                reason: String::from("`.else` after another `.else` in the same block"),
+            }),
+            Conditional::Else => {
+                block.has_else = true;
+                block.is_assembled = !block.is_assembled;
+                Ok(())
+            }
+            _ => {
+                self.blocks.pop();
+                Ok(())
+            }
+        }
+    }
+    /// Check the condition of the line opening a block: whether the expression following `.if`
+    /// is not zero, or whether the symbol following `.ifdef` is defined, or not for `.ifndef`.
+    /// Only constants defined before the line and labels declared before it are known.
+    fn check_condition(
+        &self,
+        conditional: Conditional,
+        directive: &Span,
+        source: &str,
+    ) -> Result<bool, Diagnostic> {
+        let rest: String = source
+            .chars()
+            .skip(directive.column - 1 + directive.text.chars().count())
+            .collect();
+        let span = Span {
+            column: source.chars().count() - rest.trim_start().chars().count() + 1,
+            text: arguments(&rest).join(","),
+        };
+        let expected = match conditional {
+            Conditional::If => "an expression",
+            _ => "the name of a symbol",
+        };
+        let found = match span.text.as_str() {
+            "" => Some(String::from("the end of the expression")),
+            name if conditional != Conditional::If && !is_symbol(name) => {
+                Some(format!("`{}`", name))
+            }
+            _ => None,
+        };
+        if let Some(found) = found {
+            return Err(Diagnostic {
+                span,
+                reason: format!("expected {}, found {}", expected, found),
+            });
+        }
+        if conditional != Conditional::If {
+            let is_defined = self
+                .constants
+                .contains_key(&(self.registry.module, span.text.clone()))
+                || self.registry.is_declared(&Label::Name(span.text.clone()));
+            return Ok(is_defined == (conditional == Conditional::Ifdef));
+        }
+        let value = expression::Expression::parse(&span.text).and_then(|expr| {
+            expr.evaluate(&mut |name: &str| {
+                self.get_symbol(name, self.n_definitions, &mut Vec::new())
+            })
+        });
+        match value {
+            Ok(value) => Ok(value != 0),
+            Err(reason) => Err(Diagnostic {
+                reason: format!("invalid condition `{}`: {}", span.text, reason),
+                span,
+            }),
+        }
+    }
+    /// Report every block of conditional assembly the source file or the expansion of a macro
+    /// being read leaves open.
+    fn end_blocks(&mut self, errors: &mut Vec<AssemblyError>) {
+        let depth = (self.files.len(), self.expansion.len());
+        while self.blocks.last().is_some_and(|block| block.depth == depth) {
+            let block = self.blocks.pop().unwrap();
+            let diagnostic = Diagnostic {
+                reason: format!("`{}` is not terminated by `.endif`", block.span.text),
+                span: block.span,
+            };
+            errors.push(error(
+                &block.file,
+                block.line,
+                &block.source,
+                diagnostic,
+                &self.expansion,
+            ));
+        }
+    }
+    /// Assemble the lines of the file included by the line `.include "path"`, which is searched
+    /// in the directory of the including file, then in the include paths.
+    fn include(
+        &mut self,
+        source: &str,
+        file: &str,
+        errors: &mut Vec<AssemblyError>,
+    ) -> Result<(), Diagnostic> {
+        let lexemes = lexer(source)?;
+        let (path, span) = match lexemes.get(1) {
+            Some((Text(path), span)) => (path, span),
+            lexeme => {
+                let (span, found) = match lexeme {
+                    Some((token, span)) => (span.clone(), token.describe()),
+                    None => (
+                        Span {
+                            column: source.trim_end().chars().count() + 1,
+                            text: String::new(),
+                        },
+                        String::from("the end of the expression"),
+                    ),
+                };
+                return Err(Diagnostic {
+                    span,
+                    reason: format!("expected the path of a file, found {}", found),
+                });
+            }
+        };
+        if let Some((token, span)) = lexemes.get(2) {
+            return Err(Diagnostic {
+                span: span.clone(),
+                reason: format!(
+                    "expected the end of the expression, found {}",
+                    token.describe()
+                ),
+            });
+        }
+        let found = Path::new(file)
+            .parent()
+            .into_iter()
+            .chain(self.include_paths.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(path))
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| Diagnostic {
+                span: span.clone(),
+                reason: format!("file `{}` not found in the include paths", path),
+            })?;
+        if self.files.contains(&canonical(&found)) {
+            return Err(Diagnostic {
+                span: span.clone(),
+                reason: format!("file `{}` includes itself", path),
+            });
+        }
+        let name = found.display().to_string();
+        File::open(&found)
+            .and_then(|included| self.read_source(BufReader::new(included), &name, errors))
+            .map_err(|err| Diagnostic {
+                span: span.clone(),
+                reason: format!("cannot read `{}`: {}", path, err),
+            })
+    }
+    /// Ensure the definition of the macro is valid, and that no other macro has its name.
+    fn check_definition(&self, definition: &Macro) -> Result<(), Diagnostic> {
+        definition.check()?;
+        if self.macros.contains_key(&definition.name) {
+            return Err(Diagnostic {
+                span: definition.span.clone(),
+                reason: format!("macro `{}` is already defined", definition.name),
+            });
+        }
+        Ok(())
+    }
+    /// Convert a line of source code into an expression.
+    fn read_expression(&mut self, source: &str, line: usize, file: &str) -> Result<(), Diagnostic> {
+        // Convert the expression into meaningful lexemes.
+        let mut lexemes = lexer(source)?;
+        // Skip lines that only hold whitespace or comments.
+        if lexemes.is_empty() {
+            return Ok(());
+        }
+        let pseudo = expand(&mut lexemes);
+        let (mut tokens, spans): (Vec<Token>, Vec<Span>) = lexemes.iter().cloned().unzip();
+        // Ensure expression is syntactically correct.
+        let form = parser::run(&mut tokens).map_err(|err| Diagnostic {
+            span: match spans.get(err.index) {
+                Some(span) => span.clone(),
+                // The expression ended prematurely, so point just past its end.
+                None => Span {
+                    column: source.trim_end().chars().count() + 1,
+                    text: String::new(),
+                },
+            },
+            reason: err.reason,
+        })?;
+        let tokens: Vec<Token> = lexemes.iter().map(|(token, _)| token.clone()).collect();
+        // The span of the opcode or the directive of the expression.
+        let span = lexemes
+            .iter()
+            .find(|(token, _)| matches!(token, Opcode(_) | Directive(_)))
+            .map(|(_, span)| span.clone());
+        if let Some((directive, operands)) = directive(&tokens) {
+            if let Some(name) = section_name(directive, operands) {
+                self.switch_section(name);
+                // A label leading a section directive labels the start of what follows.
+                if let Some((Label(label), span)) = lexemes.first() {
+                    self.declare_label(label, span)?;
+                }
+                return Ok(());
+            }
+            if directive.is_definition() {
+                let index = tokens
+                    .iter()
+                    .position(|token| *token == Directive(directive));
+                let (name, span) = &lexemes[index.unwrap() + 1];
+                self.define(directive, name, span, &tokens[index.unwrap() + 3])?;
+            }
+            if directive.is_visibility() {
+                let operands = lexemes
+                    .iter()
+                    .skip_while(|(token, _)| !matches!(token, Directive(_)));
+                for (token, span) in operands {
+                    if let Label(label) = token {
+                        self.declare_visibility(directive, label, span)?;
+                    }
+                }
+            }
+        }
+        // Instructions must be word aligned, which data preceding them may break.
+        let offset = self.registry.location.offset;
+        if form.is_some() && padding(offset, 2) != 0 {
+            return Err(Diagnostic {
+                span: span.unwrap(),
+                reason: format!(
+                    "instruction at unaligned offset {:#X}, insert `.align 2` before it",
+                    offset
+                ),
+            });
+        }
+        // The `.bss` section only reserves zero-initialized memory.
+        let emits_data = matches!(
+            directive(&tokens),
+            Some((Directive::Word, _))
+                | Some((Directive::Byte, _))
+                | Some((Directive::Asciz, _))
+                | Some((Directive::Ltorg, _))
+        );
+        let section = &self.sections[self.registry.location.section];
+        if section.name == BSS_SECTION && (form.is_some() || emits_data) {
+            return Err(Diagnostic {
+                span: span.unwrap(),
+                reason: format!(
+                    "only `.space` and `.align` may be used in `{}`",
+                    BSS_SECTION
+                ),
+            });
+        }
+        self.optimized_tokens(lexemes, form, pseudo, line, source, file)
+    }
+    /// Place the values awaiting a literal pool in the current section into a pool at the current
+    /// location, as words.
+    fn flush_literals(&mut self) {
+        let section = self.registry.location.section;
+        let loads = std::mem::take(&mut self.sections[section].literals);
+        if loads.is_empty() {
+            return;
+        }
+        self.registry
+            .advance(padding(self.registry.location.offset, 2));
+        for index in loads {
+            let load = &self.program[index];
+            let operand = load.tokens.len() - 1;
+            // Any error in the value is reported against the load.
+            let entry = Expression {
+                tokens: vec![Directive(Directive::Word), load.tokens[operand].clone()],
+                spans: vec![load.spans[operand].clone(); 2],
+                form: None,
+                pseudo: None,
+                literal: None,
+                location: self.registry.location,
+                definitions: load.definitions,
+                file: load.file.clone(),
+                line: load.line,
+                source: load.source.clone(),
+                invocations: load.invocations.clone(),
+                module: load.module,
+            };
+            self.program[index].literal = Some(self.registry.location);
+            self.program.push(entry);
+            self.registry.advance(N_BYTES_IN_INSTRUCTION);
+        }
+    }
+    /// Declare the label as the location of the current expression.
+    fn declare_label(&mut self, label: &Label, span: &Span) -> Result<(), Diagnostic> {
+        let Label::Name(name) = label;
+        if self
+            .constants
+            .contains_key(&(self.registry.module, name.clone()))
+        {
+            return Err(Diagnostic {
+                span: span.clone(),
+                reason: format!("symbol `{}` is already defined", name),
+            });
+        }
+        self.registry
+            .register_variable_declaration(label.clone(), span)
+    }
+    /// Make the label of the current source file visible to the other source files, or use the
+    /// label another source file makes visible. A label is global in a single source file.
+    fn declare_visibility(
+        &mut self,
+        directive: Directive,
+        label: &Label,
+        span: &Span,
+    ) -> Result<(), Diagnostic> {
+        let module = self.registry.module;
+        if directive == Directive::Extern {
+            self.registry.externs.insert((module, label.clone()));
+            return Ok(());
+        }
+        if let Some(other) = self.registry.globals.get(label) {
+            if *other != module {
+                return Err(Diagnostic {
+                    span: span.clone(),
+                    reason: format!(
+                        "label `{}` is already global in `{}`",
+                        span.text, self.modules[*other]
+                    ),
+                });
+            }
+        }
+        self.registry.globals.insert(label.clone(), module);
+        Ok(())
+    }
+    /// Define the symbol named by the token as standing for the value. Only symbols defined by
+    /// `.set` may be redefined, and only by `.set`.
+    fn define(
+        &mut self,
+        directive: Directive,
+        name: &Token,
+        span: &Span,
+        value: &Token,
+    ) -> Result<(), Diagnostic> {
+        let name = match name {
+            Label(Label::Name(name)) => name,
+            _ => return Ok(()),
+        };
+        let is_set = directive == Directive::Set;
+        let key = (self.registry.module, name.clone());
+        let is_defined = match self.constants.get(&key) {
+            Some(definitions) => !(is_set && definitions[0].is_set),
+            None => self.registry.is_declared(&Label::Name(name.clone())),
+        };
+        if is_defined {
+            return Err(Diagnostic {
+                span: span.clone(),
+                reason: format!("symbol `{}` is already defined", name),
+            });
+        }
+        self.constants.entry(key).or_default().push(Constant {
+            order: self.n_definitions,
+            value: value.clone(),
+            is_set,
+        });
+        Ok(())
+    }
+    /// Get the value of the symbol as seen by an expression preceded by the given number of
+    /// definitions: either the value of the constant in effect, or of its first definition if it
+    /// is used before being defined, or the address of the label. Labels have no address until
+    /// sections are placed in main memory. The definitions being evaluated are tracked to detect
+    /// circular definitions.
+    fn get_symbol(
+        &self,
+        name: &str,
+        before: usize,
+        visiting: &mut Vec<usize>,
+    ) -> Result<i64, String> {
+        if let Some(definitions) = self
+            .constants
+            .get(&(self.registry.module, name.to_string()))
+        {
+            let definition = definitions
+                .iter()
+                .rev()
+                .find(|definition| definition.order < before)
+                .unwrap_or(&definitions[0]);
+            if visiting.contains(&definition.order) {
+                return Err(format!("symbol `{}` is defined in terms of itself", name));
+            }
+            visiting.push(definition.order);
+            let value = self.get_value(&definition.value, definition.order, visiting);
+            visiting.pop();
+            return value;
+        }
+        if self.registry.bases.is_empty() {
+            return Err(format!(
+                "symbol `{}` must be a constant defined before this line",
+                name
+            ));
+        }
+        match self.registry.get_address(&Label::Name(name.to_string())) {
+            Some(address) => Ok(address as i64),
+            None => Err(format!("undefined symbol `{}`", name)),
+        }
+    }
+    /// Get the value of the operand, which is an immediate, an expression or a symbol.
+    fn get_value(
+        &self,
+        operand: &Token,
+        before: usize,
+        visiting: &mut Vec<usize>,
+    ) -> Result<i64, String> {
+        match operand {
+            Literal(Expression(expr)) => expression::Expression::parse(expr)?
+                .evaluate(&mut |name: &str| self.get_symbol(name, before, visiting)),
+            Literal(immed) if immed.is_negative() => {
+                Ok(-(immed.clone().get_value().wrapping_neg() as i64))
+            }
+            Literal(immed) => Ok(immed.clone().get_value() as i64),
+            Label(Label::Name(name)) => self.get_symbol(name, before, visiting),
+            _ => Ok(0),
+        }
+    }
+    /// Evaluate the operand of the expression found at the given index.
+    fn evaluate(&self, expr: &Expression, index: usize) -> Result<Literal, Diagnostic> {
+        // The value of a definition must not depend on the definition itself.
+        let mut visiting = match directive(&expr.tokens) {
+            Some((directive, _)) if directive.is_definition() => vec![expr.definitions],
+            _ => Vec::new(),
+        };
+        self.get_value(&expr.tokens[index], expr.definitions, &mut visiting)
+            .and_then(to_literal)
+            .map_err(|reason| Diagnostic {
+                span: expr.spans[index].clone(),
+                reason,
+            })
     }
     /// Remove tokens that are irrelevant to byte code encoding and register all labels in label
-    /// registry if it is not a Form Six expression.
-    fn optimized_tokens(&mut self, tokens: &mut Vec<Token>, form: Option<Form>) {
-        let mut optimized_tokens = Vec::new();
-        for token in tokens {
+    /// registry: a label leading the expression declares it, a label operand of an instruction
+    /// refers to it, and any other label is the name of a symbol or of a section.
+    fn optimized_tokens(
+        &mut self,
+        lexemes: Vec<(Token, Span)>,
+        form: Option<Form>,
+        pseudo: Option<Pseudo>,
+        line: usize,
+        source: &str,
+        file: &str,
+    ) -> Result<(), Diagnostic> {
+        let mut expr = Expression {
+            tokens: Vec::new(),
+            spans: Vec::new(),
+            form,
+            pseudo,
+            literal: None,
+            location: self.registry.location,
+            definitions: self.n_definitions,
+            file: file.to_string(),
+            line,
+            source: source.to_string(),
+            invocations: self.expansion.clone(),
+            module: self.registry.module,
+        };
+        for (index, (token, span)) in lexemes.into_iter().enumerate() {
             match token {
-                Label(label) => {
-                    match form {
-                        Some(Form::Six) => self.registry.register_variable_reference(label.clone()),
-                        _ => self.registry.register_variable_declaration(label.clone()),
-                    }
-                    optimized_tokens.push(token.clone());
+                Label(ref label) if index == 0 => self.declare_label(label, &span)?,
+                Separator(_) => continue,
+                _ => (),
+            }
+            expr.tokens.push(token);
+            expr.spans.push(span);
+        }
+        // The directive held by the expression, along with the index of its first operand.
+        let held = directive(&expr.tokens)
+            .map(|(held, operands)| (held, expr.tokens.len() - operands.len()));
+        // The operand of `.space` and `.align` must be known before sections are placed.
+        if let Some((held @ (Directive::Space | Directive::Align), index)) = held {
+            let immed = self.evaluate(&expr, index)?;
+            parser::check_operand(held, &immed).map_err(|reason| Diagnostic {
+                span: expr.spans[index].clone(),
+                reason,
+            })?;
+            expr.tokens[index] = Literal(immed);
+        }
+        // Only expressions holding an instruction or a directive occupy a location in the
+        // program.
+        let n_bytes = match (form, held) {
+            (Some(_), _) => N_BYTES_IN_INSTRUCTION,
+            (None, Some((held, index))) => self.get_data_size(held, &expr.tokens[index..]),
+            (None, None) => return Ok(()),
+        };
+        if let Some((Directive::Align, [Literal(immed)])) = directive(&expr.tokens) {
+            let section = &mut self.sections[self.registry.location.section];
+            section.alignment = section.alignment.max(immed.clone().get_value());
+        }
+        if let Some((held, _)) = held {
+            self.n_definitions += held.is_definition() as usize;
+        }
+        // A value unknown until sections are placed, or that neither `MOV` nor `MVN` can load,
+        // awaits a literal pool.
+        if expr.pseudo == Some(Pseudo::Load) {
+            let value = self.evaluate(&expr, expr.tokens.len() - 1);
+            if !matches!(value.map(Literal::get_value), Ok(value) if value >> 16 == 0 || !value >> 16 == 0)
+            {
+                expr.pseudo = Some(Pseudo::LoadLiteral);
+                let section = &mut self.sections[self.registry.location.section];
+                section.literals.push(self.program.len());
+            }
+        }
+        self.program.push(expr);
+        self.registry.advance(n_bytes);
+        if let Some((Directive::Ltorg, _)) = held {
+            self.flush_literals();
+        }
+        Ok(())
+    }
+    /// Get the number of bytes of data emitted by the directive.
+    fn get_data_size(&self, directive: Directive, operands: &[Token]) -> usize {
+        // The single operand of `.space` and `.align`, which is evaluated as the source is read.
+        let value = || match operands.first() {
+            Some(Literal(immed)) => immed.clone().get_value(),
+            _ => 0,
+        };
+        match directive {
+            Directive::Word => operands.len() * N_BYTES_IN_INSTRUCTION,
+            Directive::Byte => operands.len(),
+            Directive::Space => value() as usize,
+            Directive::Align => padding(self.registry.location.offset, value()),
+            Directive::Asciz => operands
+                .iter()
+                .map(|operand| match operand {
+                    Text(text) => text.len() + 1,
+                    _ => 0,
+                })
+                .sum(),
+            // Section directives are handled as the source is read, definitions and visibility
+            // directives emit nothing and literal pools are placed as separate expressions.
+            Directive::Text
+            | Directive::Data
+            | Directive::Bss
+            | Directive::Section
+            | Directive::Equ
+            | Directive::Set
+            | Directive::Ltorg
+            | Directive::Global
+            | Directive::Extern => 0,
+        }
+    }
+    /// Get the data emitted by the directive held by the expression.
+    fn get_data(&self, expr: &Expression) -> Result<Vec<u8>, Diagnostic> {
+        let mut data = Vec::new();
+        let (directive, operands) = match directive(&expr.tokens) {
+            Some(directive) => directive,
+            None => return Ok(data),
+        };
+        let first = expr.tokens.len() - operands.len();
+        for (index, operand) in (first..).zip(operands) {
+            match (directive, operand) {
+                (Directive::Word, _) => {
+                    let immed = self.evaluate(expr, index)?;
+                    data.extend_from_slice(&immed.get_value().to_le_bytes())
+                }
+                (Directive::Byte, _) => {
+                    let immed = self.evaluate(expr, index)?;
+                    parser::check_operand(directive, &immed).map_err(|reason| Diagnostic {
+                        span: expr.spans[index].clone(),
+                        reason,
+                    })?;
+                    data.push(immed.get_value() as u8)
+                }
+                // The value of a definition is evaluated to report any error where it is made.
+                (Directive::Equ, _) | (Directive::Set, _) if index == expr.tokens.len() - 1 => {
+                    self.evaluate(expr, index)?;
+                }
+                // Labels are visible across source files once every source file is read.
+                (Directive::Global, Label(label)) if !self.registry.is_declared(label) => {
+                    return Err(Diagnostic {
+                        span: expr.spans[index].clone(),
+                        reason: format!("undefined label `{}`", expr.spans[index].text),
+                    })
+                }
+                (Directive::Extern, Label(label)) if !self.registry.globals.contains_key(label) => {
+                    return Err(Diagnostic {
+                        span: expr.spans[index].clone(),
+                        reason: format!(
+                            "label `{}` is not global in any source file",
+                            expr.spans[index].text
+                        ),
+                    })
                 }
-                ConditionCode(_) | Opcode(_) | Register(_) | Literal(_) => {
-                    optimized_tokens.push(token.clone())
+                (Directive::Asciz, Text(text)) => {
+                    data.extend_from_slice(text.as_bytes());
+                    data.push(0);
                 }
                 _ => (),
             }
         }
-        optimized_tokens.reverse();
-        if let Some(form) = form {
-            self.program.push(Expression {
-                tokens: optimized_tokens,
-                form: form,
-            });
+        // The remaining directives only emit zero bytes.
+        data.resize(self.get_data_size(directive, operands), 0);
+        Ok(data)
+    }
+    /// Evaluate the immediate of the instruction found at the given index, ensuring it fits in the
+    /// operand of the form of the instruction. Immediates offsetting an address are signed.
+    fn evaluate_immediate(&self, expr: &Expression, index: usize) -> Result<Literal, Diagnostic> {
+        let immed = self.evaluate(expr, index)?;
+        let is_offset = expr.tokens.iter().any(|token| match token {
+            Opcode(opcode) => opcode.is_load_or_store(),
+            _ => false,
+        });
+        parser::check_width(&[expr.form.unwrap()], is_offset, &immed).map_err(|reason| {
+            Diagnostic {
+                span: expr.spans[index].clone(),
+                reason,
+            }
+        })?;
+        Ok(immed)
+    }
+    /// Rewrite the pseudo-instruction held by the expression into the instruction suiting the
+    /// value of its operand.
+    fn resolve_pseudo(&self, expr: &Expression, pseudo: Pseudo) -> Result<Expression, Diagnostic> {
+        let mut expr = expr.clone();
+        let index = expr.tokens.len() - 1;
+        let error = |reason: String| Diagnostic {
+            span: expr.spans[index].clone(),
+            reason,
+        };
+        let address = self.registry.bases[expr.location.section] + expr.location.offset;
+        // The value of a literal is evaluated where it is placed in the pool.
+        let value = match pseudo {
+            Pseudo::LoadLiteral => 0,
+            _ => self.evaluate(&expr, index)?.get_value(),
+        };
+        let (opcode, immed) = match pseudo {
+            Pseudo::Load if value >> 16 == 0 => (Opcode::MOV, value),
+            Pseudo::Load => (Opcode::MVN, !value),
+            Pseudo::LoadLiteral => {
+                let literal = expr.literal.unwrap();
+                let offset = self.registry.bases[literal.section] + literal.offset - address;
+                if offset >> (N_BITS_IN_LITERAL_OFFSET - 1) != 0 {
+                    return Err(error(format!(
+                        "literal pool at {:#010X} is out of the range of `ldr` at {:#010X}, \
+                         insert `.ltorg` closer to it",
+                        address + offset,
+                        address
+                    )));
+                }
+                (Opcode::LDR, offset as u32)
+            }
+            Pseudo::Address => {
+                let offset = value as i64 - address as i64;
+                match offset {
+                    0..=0xFFF => (Opcode::ADD, offset as u32),
+                    -0xFFF..=-1 => (Opcode::SUB, -offset as u32),
+                    _ => {
+                        return Err(error(format!(
+                            "address {:#010X} is out of the range of `{}` at {:#010X}",
+                            value, ADR, address
+                        )))
+                    }
+                }
+            }
+        };
+        for token in expr.tokens.iter_mut() {
+            match token {
+                Opcode(_) => *token = Opcode(opcode.clone()),
+                Literal(_) => *token = Literal(Immediate(immed.to_string())),
+                _ => (),
+            }
         }
-        self.registry.incr_instr_ptr();
+        Ok(expr)
     }
     /// Get the bytecode encoding of the expression.
-    fn get_bytecode(&mut self, expr: Expression) -> u32 {
+    fn get_bytecode(&mut self, expr: &Expression) -> Result<u32, Diagnostic> {
         #[derive(Clone)]
         enum RegisterType {
             Dr = 0,
             Rx,
             Ry,
+            Rz,
         }
+        let expr = &match expr.pseudo {
+            Some(pseudo) => self.resolve_pseudo(expr, pseudo)?,
+            None => expr.clone(),
+        };
+        let form = expr.form.unwrap();
         let mut next_encoded_register = RegisterType::Dr;
         let mut encoder = EncoderDecoder::new(None);
-        for token in &expr.tokens {
+        for (index, token) in expr.tokens.iter().enumerate() {
             match token {
-                Label(_) => match expr.form {
-                    Form::Six => {
-                        let immed = self.registry.get_reference();
+                Label(label) => match form {
+                    // The label leading the expression is a declaration.
+                    Form::Six if index > 0 => {
+                        let span = &expr.spans[index];
+                        let location = self.registry.get_reference(label, span)?;
+                        if padding(location, 2) != 0 {
+                            return Err(Diagnostic {
+                                span: span.clone(),
+                                reason: format!("label `{}` is not word aligned", span.text),
+                            });
+                        }
+                        if (location / N_BYTES_IN_INSTRUCTION) >> N_BITS_IN_BRANCH_TARGET != 0 {
+                            return Err(Diagnostic {
+                                span: span.clone(),
+                                reason: format!(
+                                    "label `{}` at {:#010X} is out of the range of a branch",
+                                    span.text, location
+                                ),
+                            });
+                        }
+                        // Branch targets are encoded as the index of the target instruction.
+                        let immed = location / N_BYTES_IN_INSTRUCTION;
                         encoder.set_immed20(Immediate(immed.to_string()));
                     }
                     _ => (),
                 },
-                Opcode(opcode) => encoder.set_opcode(expr.form, opcode.clone()),
+                Opcode(opcode) => encoder.set_opcode(form, opcode.clone()),
                 ConditionCode(cond_code) => encoder.set_cc(cond_code.clone()),
                 Register(register) => match next_encoded_register.clone() {
+                    // Forms without a condition code encode their registers in its place.
+                    RegisterType::Dr if !form.has_cc() => {
+                        encoder.set_unconditional_dr(register.clone());
+                        next_encoded_register = RegisterType::Rx;
+                    }
                     RegisterType::Dr => {
                         encoder.set_dr(register.clone());
                         next_encoded_register = RegisterType::Rx;
                     }
+                    RegisterType::Rx if !form.has_cc() => {
+                        encoder.set_unconditional_rx(register.clone());
+                        next_encoded_register = RegisterType::Ry;
+                    }
                     RegisterType::Rx => {
                         encoder.set_rx(register.clone());
                         next_encoded_register = RegisterType::Ry;
                     }
-                    RegisterType::Ry => encoder.set_ry(register.clone()),
-                },
-                Literal(immed) => match expr.form {
-                    Form::Four => encoder.set_immed16(immed.clone()),
-                    Form::Five => encoder.set_immed20(immed.clone()),
-                    _ => (),
+                    RegisterType::Ry => {
+                        encoder.set_ry(register.clone());
+                        next_encoded_register = RegisterType::Rz;
+                    }
+                    RegisterType::Rz => {
+                        encoder.set_rz(register.clone());
+                        // A register following a shift holds the shift amount.
+                        if let Form::Eleven | Form::Twelve = form {
+                            encoder.set_shift_by_register();
+                        }
+                    }
                 },
+                Shift(shift) => {
+                    encoder.set_shift(*shift);
+                    next_encoded_register = RegisterType::Rz;
+                }
+                RegisterList(registers) => encoder.set_register_list(registers),
+                Literal(immed) => {
+                    let immed = match immed.is_expression() {
+                        true => self.evaluate_immediate(expr, index)?,
+                        false => immed.clone(),
+                    };
+                    match form {
+                        Form::Sixteen | Form::Fourteen | Form::Fifteen => {
+                            encoder.set_immed12(immed)
+                        }
+                        Form::Four | Form::Seventeen => encoder.set_immed16(immed),
+                        Form::Five | Form::Seven => encoder.set_immed20(immed),
+                        Form::Eleven | Form::Twelve => encoder.set_immed5(immed),
+                        _ => (),
+                    }
+                }
                 _ => (),
             }
         }
-        return encoder.get_instr();
+        Ok(encoder.get_instr())
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::fs;
+
+    /// Assemble the source, expecting it to contain a single error.
+    fn read_err(source: &str) -> AssemblyError {
+        read_all_err(&[(source, "pgrm.asm")])
+    }
+
+    /// Assemble the source files, expecting them to contain a single error.
+    fn read_all_err(sources: &[(&str, &str)]) -> AssemblyError {
+        let sources = sources
+            .iter()
+            .map(|(source, file)| (source.as_bytes(), *file))
+            .collect();
+        match Assembler::new().read_all(sources) {
+            Err(Error::Assembly(mut errors)) => {
+                assert_eq!(errors.len(), 1);
+                errors.remove(0)
+            }
+            _ => panic!("expected an assembly error"),
+        }
+    }
+
+    /// Write the source files into a new directory named after the test, returning its path.
+    fn write_sources(test: &str, sources: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("arm-vm-{}-{}", test, std::process::id()));
+        for (source, file) in sources {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_read_str() {
+        let program = Assembler::new().read_str("mov r1, #0x2\nadd r1, r1, #0x3", "pgrm.asm");
+        assert_eq!(program.unwrap().text(), vec![0x23100002, 0x21110003]);
+    }
+
+    #[test]
+    fn test_read_str_with_svc() {
+        let program = Assembler::new().read_str("mov r1, #0x2\nsvc #0\nswi #0x7", "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![0x23100002, 0x90000000, 0x90000007]
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_flag_setting_opcodes() {
+        let source = "adds r1, r2, r3\nsubs r1, r2, #0x1\nmovs r1, #0x0\nMVNS r1, r2";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![0x11012300, 0x62120001, 0x63100000, 0x17012000]
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_conditional_opcodes() {
+        let source = "addeq r1, r2, r3\nMOVNE r1, #0x2\nldrgt r1, [r2, #0x1]\nsubsle r1, r2, #0x1";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![0x01112300, 0x93210002, 0xB8D12001, 0xC9E12001]
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_immediate_limits() {
+        let source = "add r1, r2, #0xFFFF\nmov r1, #0xFFFFF\nsvc #0xFFFFF";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![0x2112FFFF, 0x231FFFFF, 0x900FFFFF]
+        );
+        let err = read_err("add r1, r2, #0x10000");
+        assert_eq!(
+            err.reason,
+            "immediate 0x10000 does not fit in 16 bits for form Four"
+        );
+        let err = read_err("mov r1, #0x100000");
+        assert_eq!(
+            err.reason,
+            "immediate 0x100000 does not fit in 20 bits for form Five"
+        );
+        let err = read_err("svc #0x100000");
+        assert_eq!(
+            err.reason,
+            "immediate 0x100000 does not fit in 20 bits for form Seven"
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_conditional_immediate_limits() {
+        let source = "addeq r1, r2, #0xFFF\nmovne r1, #0xFFFF";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(program.unwrap().text(), vec![0x91112FFF, 0x9321FFFF]);
+        let err = read_err("addeq r1, r2, #0x1000");
+        assert_eq!(err.token, "#0x1000");
+        assert_eq!(
+            err.reason,
+            "immediate 0x1000 does not fit in 12 bits for form Sixteen"
+        );
+        let err = read_err("movne r1, #0x10000");
+        assert_eq!(
+            err.reason,
+            "immediate 0x10000 does not fit in 16 bits for form Seventeen"
+        );
+        let err = read_err(".equ BIG, 0x800\naddeq r0, r0, #BIG * 2");
+        assert_eq!(
+            err.reason,
+            "immediate 0x1000 does not fit in 12 bits for form Sixteen"
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_subroutine() {
+        let source =
+            "    BL double\n    bleq double\ndouble add r0, r0, r0\n    bx lr\n    BXNE LR";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![0x81000002, 0x81100002, 0x01000000, 0x820E0000, 0x822E0000]
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_block_transfers() {
+        let source = "push {r4, lr}\npop {r4, pc}\nstmdb sp!, {r0-r2}\nldmia r0, {r1,r2}\n\
+                      LDMIA R0!, {R1}\npusheq {r0}";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![0xA5004010, 0xA4008010, 0xA30D0007, 0xA0000006, 0xA1000002, 0xA5100001]
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_shifts() {
+        let source = "add r0, r1, r2, lsl #2\neors r3, r3, r4, ror r5\nMOV R0, R1, ASR #31\n\
+                      cmp r2, r3, lsr r4\nlsl r0, r1, #3\nasrs r0, r1, r2\nmovne r0, r1, lsl #1";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![
+                0xB1001202, 0xC60334E5, 0xB300105F, 0xD70230A4, 0x29010003, 0x1B001200, 0xB3201001
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_shift_amount_out_of_bounds() {
+        let err = read_err("add r0, r1, r2, lsl #32");
+        assert_eq!(err.token, "#32");
+        assert_eq!(
+            err.reason,
+            "immediate 0x20 does not fit in 5 bits for form Eleven"
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_multiply_divide() {
+        let source = "mla r0, r1, r2, r3\nmls r0, r1, r2, r3\numull r0, r1, r2, r3\n\
+                      smullne r4, r5, r6, r7\numlal r0, r1, r2, r3\nsdiv r0, r1, r2\nudiv r0, r1, r2";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![
+                0xE0001203, 0xE1001203, 0xE2001203, 0xE3245607, 0xE4001203, 0x0D001200, 0x0E001200
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_byte_and_halfword_transfers() {
+        let source = "ldrb r0, [r1]\nstrb r0, [r1, #1]\nldrsh r0, [r1, #2]\nstrh r0, [r1, r2]\n\
+                      ldrsbeq r0, [r1]\nldrhs r0, [r1]";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![0x38001000, 0x3D010001, 0x51010002, 0x4A001200, 0x4C101000, 0x30301000]
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_indexed_addressing() {
+        let source = "ldr r0, [r1, #-4]\nldr r0, [r1, #4]!\nldr r0, [r1], #4\n\
+                      ldr r0, [r1, r2, lsl #2]\nstrb r0, [r1], #-1";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![0x3101FFFC, 0x70001004, 0x78001004, 0xF0001202, 0x7B001FFF]
+        );
+    }
+
+    #[test]
+    fn test_error_negative_offset_out_of_bounds() {
+        let err = read_err("str r0, [r1, #-0x801]!");
+        assert_eq!(err.token, "#-0x801");
+        assert_eq!(
+            err.reason,
+            "immediate -0x801 does not fit in 12 signed bits for form Fourteen"
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_data() {
+        let source = "    b main\nmsg .asciz \"hi\"\n    .byte 0x1, -1\n    .align 2\n\
+                      table .word msg, table, -2\n    .space 4\nmain svc #0";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![
+                0x80000007, 0x01006968, 0x000000FF, 0x00000004, 0x0000000C, 0xFFFFFFFE, 0x00000000,
+                0x90000000
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_str_pads_data_to_word() {
+        let program = Assembler::new().read_str(".byte 'a'\n.byte 'b'", "pgrm.asm");
+        assert_eq!(program.unwrap().text(), vec![0x00006261]);
+    }
+
+    #[test]
+    fn test_error_unaligned_instruction() {
+        let err = read_err(".byte 1\nfoo mov r0, r1");
+        assert_eq!((err.line, err.column), (2, 5));
+        assert_eq!(
+            err.reason,
+            "instruction at unaligned offset 0x1, insert `.align 2` before it"
+        );
+    }
+
+    #[test]
+    fn test_error_unaligned_branch_target() {
+        let err = read_err("b foo\n.byte 1\nfoo .byte 2");
+        assert_eq!((err.line, err.column), (1, 3));
+        assert_eq!(err.reason, "label `foo` is not word aligned");
+    }
+
+    #[test]
+    fn test_error_undefined_label_in_data() {
+        let err = read_err(".word 0, foo");
+        assert_eq!(err.column, 10);
+        assert_eq!(err.reason, "undefined symbol `foo`");
+    }
+
+    #[test]
+    fn test_read_str_with_constants() {
+        let source = ".equ ARRAY_LEN, 20\n.set BASE, 0x1000\nstart mov r0, #ARRAY_LEN*4\n\
+                      add r1, r0, #(BASE + 0x10) & 0xFFF\nldr r2, [r1, #-ARRAY_LEN]\n\
+                      mov r3, #end - start\nend .word -(BASE >> 4), ~0";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![0x23000050, 0x21100010, 0x3121FFEC, 0x23300010, 0xFFFFFF00, 0xFFFFFFFF]
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_redefined_constant() {
+        let source = ".set N, 1\n.byte N\n.set N, N + 1\n.byte N, M\n.equ M, N * 10\n.space M - 17";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(program.unwrap().text(), vec![0x00140201, 0x00000000]);
+    }
+
+    #[test]
+    fn test_error_redefined_constant() {
+        let err = read_err(".equ LEN, 4\n.set LEN, 8");
+        assert_eq!((err.line, err.column), (2, 6));
+        assert_eq!(err.reason, "symbol `LEN` is already defined");
+        let err = read_err("foo .word 0\n.equ foo, 4");
+        assert_eq!(err.reason, "symbol `foo` is already defined");
+    }
+
+    #[test]
+    fn test_error_circular_constant() {
+        match Assembler::new().read_str(".equ A, B + 1\n.equ B, A", "pgrm.asm") {
+            Err(Error::Assembly(errors)) => {
+                assert_eq!((errors[0].line, errors[0].column), (1, 9));
+                assert_eq!(errors[0].reason, "symbol `A` is defined in terms of itself");
+                assert_eq!(errors[1].line, 2);
+            }
+            _ => panic!("expected an assembly error"),
+        }
+    }
+
+    #[test]
+    fn test_error_expression_out_of_bounds() {
+        let err = read_err(".equ BIG, 0x80000\nmov r0, #BIG * 2");
+        assert_eq!((err.line, err.token.as_str()), (2, "#BIG * 2"));
+        assert_eq!(
+            err.reason,
+            "immediate 0x100000 does not fit in 20 bits for form Five"
+        );
+        let err = read_err(".equ BIG, 0x10000\n.word BIG * BIG");
+        assert_eq!(err.reason, "value 4294967296 does not fit in 32 bits");
+    }
+
+    #[test]
+    fn test_error_space_before_label() {
+        let err = read_err(".space end\nend .word 0");
+        assert_eq!(
+            err.reason,
+            "symbol `end` must be a constant defined before this line"
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_macros() {
+        let source = ".macro dec reg\n    subs \\reg, \\reg, #1\n.endm\n\
+                      .macro countdown reg, from\n    mov \\reg, \\from\nloop dec \\reg\n    bne loop\n\
+                      .endm\n_start countdown r0, #3\n    countdown r1, #(2 + 1) ; twice\n    svc #0";
+        let expanded = "_start mov r0, #3\nfirst subs r0, r0, #1\n    bne first\n\
+                        mov r1, #(2 + 1)\nsecond subs r1, r1, #1\n    bne second\n    svc #0";
+        let program = Assembler::new().read_str(source, "pgrm.asm").unwrap();
+        let expected = Assembler::new().read_str(expanded, "pgrm.asm").unwrap();
+        assert_eq!(program.text(), expected.text());
+    }
+
+    #[test]
+    fn test_error_in_macro() {
+        let source = ".macro inc reg, amount\n    add \\reg, \\reg, \\amount\n.endm\n\
+                      .macro bump reg\n    inc \\reg, #0x1FFFF\n.endm\nmov r0, #1\n    bump r0";
+        let err = read_err(source);
+        assert_eq!((err.line, err.column), (2, 17));
+        assert_eq!(err.source, "    add r0, r0, #0x1FFFF");
+        let invocations: Vec<(usize, usize, &str)> = err
+            .invocations
+            .iter()
+            .map(|invocation| (invocation.line, invocation.column, invocation.name.as_str()))
+            .collect();
+        assert_eq!(invocations, vec![(5, 5, "inc"), (8, 5, "bump")]);
+        let err = read_err(".macro jump\n    b nowhere\n.endm\njump");
+        assert_eq!((err.line, err.invocations.len()), (2, 1));
+        assert_eq!(err.reason, "undefined label `nowhere`");
+    }
+
+    #[test]
+    fn test_error_macro_definition() {
+        let err = read_err(".macro mov\n.endm");
+        assert_eq!((err.line, err.column), (1, 8));
+        assert_eq!(err.reason, "expected the name of a macro, found an opcode");
+        let err = read_err(".macro inc reg, reg\n.endm");
+        assert_eq!(err.reason, "parameter `reg` is already defined");
+        let err = read_err(".macro inc\n.endm\n.macro inc\n.endm");
+        assert_eq!(
+            (err.line, err.reason.as_str()),
+            (3, "macro `inc` is already defined")
+        );
+        let err = read_err(".macro inc\n    add r0, r0, #1");
+        assert_eq!((err.line, err.column), (1, 8));
+        assert_eq!(err.reason, "macro `inc` is not terminated by `.endm`");
+        let err = read_err(".macro inc\n.macro dec\n.endm");
+        assert_eq!((err.line, err.column), (2, 1));
+        assert_eq!(err.reason, "macros cannot be defined within a macro");
+        let err = read_err("mov r0, r1\n.endm");
+        assert_eq!(err.reason, "`.endm` without a matching `.macro`");
+    }
+
+    #[test]
+    fn test_error_macro_invocation() {
+        let err = read_err(".macro inc reg\n    add \\reg, \\reg, #1\n.endm\n    inc r0, r1");
+        assert_eq!((err.line, err.column), (4, 5));
+        assert_eq!(err.reason, "macro `inc` takes 1 argument, found 2");
+        let err = read_err(".macro forever\n    forever\n.endm\nforever");
+        assert_eq!((err.line, err.invocations.len()), (2, 100));
+        assert_eq!(
+            err.reason,
+            "macro `forever` is nested in more than 100 invocations"
+        );
+    }
+
+    #[test]
+    fn test_read_all_with_global_labels() {
+        let main =
+            ".extern value\nlocal .word 7\n.global _start\n_start ldr r0, =value\n    svc #0";
+        let data = ".global value\n.equ local, 1\nvalue .word 4\n.word local";
+        let program = Assembler::new()
+            .read_all(vec![
+                (main.as_bytes(), "main.asm"),
+                (data.as_bytes(), "data.asm"),
+            ])
+            .unwrap();
+        assert_eq!(
+            program.text(),
+            vec![0x00000007, 0x33000010, 0x90000000, 0x00000004, 0x00000001, 0x0000000C]
+        );
+        assert_eq!(program.entry, 4);
+    }
+
+    #[test]
+    fn test_error_global_labels() {
+        let err = read_all_err(&[("b value", "main.asm"), ("value .word 4", "data.asm")]);
+        assert_eq!((err.file.as_str(), err.line), ("main.asm", 1));
+        assert_eq!(err.reason, "undefined label `value`");
+        let err = read_all_err(&[(".extern value", "main.asm"), ("value .word 4", "data.asm")]);
+        assert_eq!((err.file.as_str(), err.column), ("main.asm", 9));
+        assert_eq!(err.reason, "label `value` is not global in any source file");
+        let err = read_all_err(&[(".global value", "main.asm")]);
+        assert_eq!(err.reason, "undefined label `value`");
+        let err = read_all_err(&[
+            (".global value\nvalue .word 1", "main.asm"),
+            (".global value\nvalue .word 2", "data.asm"),
+        ]);
+        assert_eq!((err.file.as_str(), err.line), ("data.asm", 1));
+        assert_eq!(err.reason, "label `value` is already global in `main.asm`");
+    }
+
+    #[test]
+    fn test_read_file_with_includes() {
+        let dir = write_sources(
+            "includes",
+            &[
+                (
+                    ".include \"defs.asm\"\nmov r0, #LEN\n.include \"util.asm\"\n    inc r0",
+                    "main.asm",
+                ),
+                (".equ LEN, 4", "defs.asm"),
+                (
+                    ".macro inc reg\n    add \\reg, \\reg, #1\n.endm",
+                    "lib/util.asm",
+                ),
+            ],
+        );
+        let mut assembler = Assembler::new();
+        assembler.add_include_path(dir.join("lib"));
+        let program = assembler.read_file(dir.join("main.asm"));
+        let expected = Assembler::new().read_str("mov r0, #4\nadd r0, r0, #1", "pgrm.asm");
+        fs::remove_dir_all(dir).unwrap();
+        assert_eq!(program.unwrap().text(), expected.unwrap().text());
+    }
+
+    #[test]
+    fn test_error_include() {
+        let dir = write_sources(
+            "include-errors",
+            &[
+                (".include \"b.asm\"", "a.asm"),
+                ("mov r0, r1\n.include \"a.asm\"", "b.asm"),
+                (".include \"missing.asm\"\n.include \"c.asm\"", "main.asm"),
+                ("mov r0, #0x1FFFFF", "c.asm"),
+            ],
+        );
+        let result = Assembler::new().read_file(dir.join("a.asm"));
+        let errors = match result {
+            Err(Error::Assembly(errors)) => errors,
+            _ => panic!("expected an assembly error"),
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].file, dir.join("b.asm").display().to_string());
+        assert_eq!((errors[0].line, errors[0].column), (2, 10));
+        assert_eq!(errors[0].reason, "file `a.asm` includes itself");
+        let result = Assembler::new().read_file(dir.join("main.asm"));
+        fs::remove_dir_all(&dir).unwrap();
+        let errors = match result {
+            Err(Error::Assembly(errors)) => errors,
+            _ => panic!("expected an assembly error"),
+        };
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].reason,
+            "file `missing.asm` not found in the include paths"
+        );
+        assert_eq!(errors[1].file, dir.join("c.asm").display().to_string());
+        assert_eq!(errors[1].line, 1);
+    }
+
+    #[test]
+    fn test_read_str_with_conditionals() {
+        let source =
+            ".equ VARIANT, 2\n.ifdef SOLUTION\n    mov r0, #1\n.else\n    mov r0, #2\n.endif\n\
+                      .if VARIANT == 2 ; nested\n  .ifndef VARIANT\n    mov r1, #1\n  .else\n\
+                      \x20   mov r1, #2\n  .endif\n.else\n  .if UNDEFINED\n  .endif\n.endif";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(program.unwrap().text(), vec![0x23000002, 0x23100002]);
+        let mut assembler = Assembler::new();
+        assembler.add_symbol("SOLUTION", "1").unwrap();
+        assembler.add_symbol("OFFSET", "SOLUTION").unwrap();
+        let source = source.replace(".equ VARIANT, 2", ".equ VARIANT, 1 + OFFSET");
+        let program = assembler.read_str(&source, "pgrm.asm");
+        assert_eq!(program.unwrap().text(), vec![0x23000001, 0x23100002]);
+    }
+
+    #[test]
+    fn test_read_str_with_recursive_macro() {
+        let source =
+            ".macro fill n\n  .if \\n\n    .word \\n\n    fill \\n - 1\n  .endif\n.endm\nfill 3";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(program.unwrap().text(), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_error_conditionals() {
+        let err = read_err("mov r0, r1\n.endif");
+        assert_eq!((err.line, err.column), (2, 1));
+        assert_eq!(err.reason, "`.endif` without a matching `.if`");
+        let err = read_err(".if 1\n    mov r0, r1");
+        assert_eq!((err.line, err.column), (1, 1));
+        assert_eq!(err.reason, "`.if` is not terminated by `.endif`");
+        let err = read_err(".if 1\n.else\n.else\n.endif");
+        assert_eq!(err.line, 3);
+        assert_eq!(
+            err.reason,
+            "`.else` after another `.else` in the same block"
+        );
+        let err = read_err(".if  foo\nfoo mov r0, r1\n.endif");
+        assert_eq!((err.line, err.column, err.token.as_str()), (1, 6, "foo"));
+        assert_eq!(
+            err.reason,
+            "invalid condition `foo`: symbol `foo` must be a constant defined before this line"
+        );
+        let err = read_err(".ifdef 4\n.endif");
+        assert_eq!(err.reason, "expected the name of a symbol, found `4`");
+        let err = read_err(".if\n.endif");
+        assert_eq!((err.line, err.column), (1, 4));
+        assert_eq!(
+            err.reason,
+            "expected an expression, found the end of the expression"
+        );
+        let mut assembler = Assembler::new();
+        assert_eq!(
+            assembler.add_symbol("1X", "1"),
+            Err(String::from("invalid symbol name `1X`"))
+        );
+        assert_eq!(
+            assembler.add_symbol("X", "1 +"),
+            Err(String::from(
+                "invalid expression `1 +`: expected an operand"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_label_addresses() {
+        let source = "_start ldr r0, =msg\n    adr r1, msg\n    ldr r2, =0xFFFFFFFF\n\
+                      ldr r3, =-2\n    adr r4, _start\n    ldreq r5, ='a'\n    mov r6, #msg\n\
+                      svc #0\nmsg .asciz \"hi\"";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![
+                0x33000024, 0x211F001C, 0x27200000, 0x27300001, 0x224F0010, 0x93150061, 0x23600020,
+                0x90000000, 0x00006968, 0x00000020
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_literal_pools() {
+        let source = "ldr r0, =0x12345678\n    ldr r1, =0xDEADBEEF\n    .ltorg\n\
+                      ldrne r2, =0x12345678\n    ldr r3, =0x10\n    svc #0\n.byte 1";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![
+                0x33000008, 0x33100008, 0x12345678, 0xDEADBEEF, 0xE8220010, 0x23300010, 0x90000000,
+                0x00000001, 0x12345678
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_label_address_out_of_range() {
+        let err = read_err("ldr r0, =0x12345678\n.space 0x8000");
+        assert_eq!((err.line, err.column), (1, 10));
+        assert_eq!(
+            err.reason,
+            "literal pool at 0x00008004 is out of the range of `ldr` at 0x00000000, \
+             insert `.ltorg` closer to it"
+        );
+        let err = read_err("adr r0, far\n.space 0x1000\nfar .word 0");
+        assert_eq!(
+            err.reason,
+            "address 0x00001004 is out of the range of `adr` at 0x00000000"
+        );
+    }
+
+    #[test]
+    fn test_error_duplicate_label() {
+        let err = read_err("foo mov r0, r1\nfoo mov r1, r0");
+        assert_eq!((err.line, err.column), (2, 1));
+        assert_eq!(err.reason, "label `foo` is already defined");
+    }
+
+    #[test]
+    fn test_read_str_with_sections() {
+        let source = ".data\nvalue .word 0x7\n.text\n    b main\n.bss\nbuf .space 6\n\
+                      .section .rodata\nptrs .word value, buf\n.text\nmain svc #0";
+        let program = Assembler::new().read_str(source, "pgrm.asm").unwrap();
+        let segments: Vec<(&str, u32, Vec<u32>)> = program
+            .segments
+            .iter()
+            .map(|segment| {
+                (
+                    segment.name.as_str(),
+                    segment.address,
+                    segment.words.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            segments,
+            vec![
+                (".text", 0x0, vec![0x80000001, 0x90000000]),
+                (".data", 0x8, vec![0x7]),
+                (".bss", 0xC, vec![0x0, 0x0]),
+                (".rodata", 0x14, vec![0x8, 0xC]),
+            ]
+        );
+        assert_eq!((program.entry, program.stack_top), (0x0, None));
+    }
+
+    #[test]
+    fn test_read_str_with_layout() {
+        let layout = Layout::parse("section .data 0x8000\nstack 0x10000").unwrap();
+        let source = ".data\nvalue .word 0x7\n.text\n_start b _start\n.word value";
+        let program = Assembler::with_layout(layout).read_str(source, "pgrm.asm");
+        let program = program.unwrap();
+        assert_eq!(program.text(), vec![0x80000000, 0x8000]);
+        assert_eq!(program.segment(".data").unwrap().address, 0x8000);
+        assert_eq!((program.entry, program.stack_top), (0x0, Some(0x10000)));
+    }
+
+    #[test]
+    fn test_read_str_with_entry() {
+        let mut layout = Layout::default();
+        layout.entry = Some(String::from("main"));
+        let source = "    svc #1\nmain svc #0";
+        let program = Assembler::with_layout(layout).read_str(source, "pgrm.asm");
+        assert_eq!(program.unwrap().entry, 0x4);
+        let program = Assembler::new().read_str("svc #1\n_start svc #0", "pgrm.asm");
+        assert_eq!(program.unwrap().entry, 0x4);
+    }
+
+    #[test]
+    fn test_read_str_aligns_sections() {
+        let source = ".byte 1\n.data\n.align 3\nvalue .word 0x7";
+        let program = Assembler::new().read_str(source, "pgrm.asm").unwrap();
+        assert_eq!(program.segment(".data").unwrap().address, 0x8);
+    }
+
+    #[test]
+    fn test_error_undefined_entry() {
+        let mut layout = Layout::default();
+        layout.entry = Some(String::from("main"));
+        match Assembler::with_layout(layout).read_str("svc #0", "pgrm.asm") {
+            Err(Error::Layout(reason)) => assert_eq!(reason, "undefined entry symbol `main`"),
+            _ => panic!("expected a layout error"),
+        }
+    }
+
+    #[test]
+    fn test_error_overlapping_sections() {
+        let layout = Layout::parse("section .data 0x4").unwrap();
+        match Assembler::with_layout(layout).read_str("svc #0\nsvc #0\n.data\n.word 1", "pgrm.asm")
+        {
+            Err(Error::Layout(reason)) => assert_eq!(
+                reason,
+                "section `.data` at 0x00000004 overlaps section `.text` at 0x00000000"
+            ),
+            _ => panic!("expected a layout error"),
+        }
+    }
+
+    #[test]
+    fn test_error_branch_out_of_range() {
+        let layout = Layout::parse("section .data 0x400000").unwrap();
+        let source = "    b foo\n.data\nfoo .word 0";
+        match Assembler::with_layout(layout).read_str(source, "pgrm.asm") {
+            Err(Error::Assembly(errors)) => assert_eq!(
+                errors[0].reason,
+                "label `foo` at 0x00400000 is out of the range of a branch"
+            ),
+            _ => panic!("expected an assembly error"),
+        }
+    }
+
+    #[test]
+    fn test_error_text_out_of_branch_range() {
+        let layout = Layout::parse("section .text 0x3FFFFC").unwrap();
+        let program = Assembler::with_layout(layout.clone()).read_str("foo b foo", "pgrm.asm");
+        assert_eq!(program.unwrap().text(), vec![0x800FFFFF]);
+        match Assembler::with_layout(layout).read_str("foo b foo\n    b foo", "pgrm.asm") {
+            Err(Error::Layout(reason)) => assert_eq!(
+                reason,
+                "section `.text` at 0x003FFFFC runs past 0x00400000, the end of the range of a \
+                 branch"
+            ),
+            _ => panic!("expected a layout error"),
+        }
+    }
+
+    #[test]
+    fn test_error_data_in_bss() {
+        let err = read_err(".bss\nbuf .space 4\n    .word 1");
+        assert_eq!((err.line, err.column), (3, 5));
+        assert_eq!(
+            err.reason,
+            "only `.space` and `.align` may be used in `.bss`"
+        );
+    }
+
+    #[test]
+    fn test_read_str_with_label() {
+        let program = Assembler::new().read_str("foo mov r1, r2\n    b foo", "pgrm.asm");
+        assert_eq!(program.unwrap().text(), vec![0x03012000, 0x80000000]);
+    }
+
+    #[test]
+    fn test_read_str_skips_blank_lines_and_comments() {
+        let source = "\n; comment\n    \nmov r1, #0x2 ; trailing comment";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(program.unwrap().text(), vec![0x23100002]);
+    }
+
+    #[test]
+    fn test_label_only_line_does_not_shift_references() {
+        let source = "foo\n    mov r1, r2\nbar\n    b bar\n    b foo";
+        let program = Assembler::new().read_str(source, "pgrm.asm");
+        assert_eq!(
+            program.unwrap().text(),
+            vec![0x03012000, 0x80000001, 0x80000000]
+        );
+    }
+
+    #[test]
+    fn test_read_file_not_found() {
+        assert!(Assembler::new().read_file("does/not/exist.asm").is_err());
+    }
+
+    #[test]
+    fn test_error_location() {
+        let err = read_err("mov r1, #0x2\n    add r1, r2,, r3");
+        assert_eq!(err.file, "pgrm.asm");
+        assert_eq!((err.line, err.column), (2, 16));
+        assert_eq!(err.token, ",");
+        assert_eq!(
+            err.reason,
+            "expected a register, an immediate or `[`, found `,`"
+        );
+    }
+
+    #[test]
+    fn test_error_immediate_out_of_bounds() {
+        let err = read_err("add r1, r2, #0x1FFFF");
+        assert_eq!(err.column, 13);
+        assert_eq!(err.token, "#0x1FFFF");
+        assert_eq!(
+            err.reason,
+            "immediate 0x1FFFF does not fit in 16 bits for form Four"
+        );
+    }
+
+    #[test]
+    fn test_error_premature_end() {
+        let err = read_err("add r1, r2,");
+        assert_eq!(err.column, 12);
+        assert_eq!(err.token, "");
+    }
+
+    #[test]
+    fn test_error_invalid_immediate() {
+        let err = read_err("mov r1, #0x1FFFFFFFF");
+        assert_eq!(
+            err.reason,
+            "invalid immediate `#0x1FFFFFFFF`: invalid number `0x1FFFFFFFF`"
+        );
+    }
+
+    #[test]
+    fn test_error_undefined_label() {
+        let err = read_err("mov r1, r2\nbne foo");
+        assert_eq!((err.line, err.column), (2, 5));
+        assert_eq!(err.reason, "undefined label `foo`");
+    }
+
+    #[test]
+    fn test_error_every_line_reported() {
+        match Assembler::new().read_str("add r1\nmov r1, r2\nfoo bar", "pgrm.asm") {
+            Err(Error::Assembly(errors)) => {
+                let lines: Vec<usize> = errors.iter().map(|err| err.line).collect();
+                assert_eq!(lines, vec![1, 3]);
+            }
+            _ => panic!("expected an assembly error"),
+        }
+    }
+
+}
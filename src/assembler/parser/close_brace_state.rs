@@ -1,9 +1,48 @@
 use super::super::super::util::Form;
-use super::super::parser::{CloseBraceState, StateMachine};
+use super::super::lexer::{Separator, Token};
+use super::super::parser::{
+    CloseBraceState, CommaState, StateMachine, SyntaxError, WritebackState,
+};
+
+impl From<StateMachine<CloseBraceState>> for StateMachine<CommaState> {
+    fn from(machine: StateMachine<CloseBraceState>) -> StateMachine<CommaState> {
+        StateMachine {
+            state: CommaState,
+            tokens: machine.tokens,
+            forms: machine.forms,
+        }
+    }
+}
+
+impl From<StateMachine<CloseBraceState>> for StateMachine<WritebackState> {
+    fn from(machine: StateMachine<CloseBraceState>) -> StateMachine<WritebackState> {
+        StateMachine {
+            state: WritebackState,
+            tokens: machine.tokens,
+            forms: machine.forms,
+        }
+    }
+}
 
 impl StateMachine<CloseBraceState> {
-    pub fn handler(mut self) -> Result<Option<Form>, ()> {
-        if let None = self.tokens.pop() {
+    pub fn handler(mut self) -> Result<Option<Form>, SyntaxError> {
+        let token = self.tokens.pop();
+        match token {
+            // The base register is written back before the transfer.
+            Some(Token::Separator(Separator::Writeback))
+                if self.forms.contains(&Form::Fourteen) =>
+            {
+                self.forms = vec![Form::Fourteen];
+                return StateMachine::<WritebackState>::from(self).handler();
+            }
+            // The offset following the address is added to the base register after the transfer.
+            Some(Token::Separator(Separator::Comma)) if self.forms.contains(&Form::Fifteen) => {
+                self.forms = vec![Form::Fifteen];
+                return StateMachine::<CommaState>::from(self).handler();
+            }
+            _ => (),
+        }
+        if let None = token {
             if self.forms.contains(&Form::One) {
                 return Ok(Some(Form::One));
             }
@@ -16,7 +55,10 @@ impl StateMachine<CloseBraceState> {
             if self.forms.contains(&Form::Five) {
                 return Ok(Some(Form::Five));
             }
+            if self.forms.contains(&Form::Eleven) {
+                return Ok(Some(Form::Eleven));
+            }
         }
-        return Err(());
+        Err(self.unexpected(&token, "the end of the expression"))
     }
 }
@@ -2,7 +2,8 @@ use super::super::super::util::Form;
 use super::super::lexer::{Separator, Token};
 
 use super::super::parser::{
-    CommaState, ImmediateState, OpenBraceState, RegisterState, StateMachine,
+    CommaState, ImmediateState, OpenBraceState, RegisterListState, RegisterState, ShiftState,
+    StateMachine, SyntaxError,
 };
 
 impl From<StateMachine<CommaState>> for StateMachine<ImmediateState> {
@@ -35,25 +36,58 @@ impl From<StateMachine<CommaState>> for StateMachine<OpenBraceState> {
     }
 }
 
+impl From<StateMachine<CommaState>> for StateMachine<RegisterListState> {
+    fn from(machine: StateMachine<CommaState>) -> StateMachine<RegisterListState> {
+        StateMachine {
+            state: RegisterListState,
+            tokens: machine.tokens,
+            forms: machine.forms,
+        }
+    }
+}
+
+impl From<StateMachine<CommaState>> for StateMachine<ShiftState> {
+    fn from(machine: StateMachine<CommaState>) -> StateMachine<ShiftState> {
+        StateMachine {
+            state: ShiftState,
+            tokens: machine.tokens,
+            forms: machine.forms,
+        }
+    }
+}
+
 impl StateMachine<CommaState> {
-    pub fn handler(mut self) -> Result<Option<Form>, ()> {
-        match self.tokens.pop() {
+    pub fn handler(mut self) -> Result<Option<Form>, SyntaxError> {
+        let token = self.tokens.pop();
+        match token {
+            Some(Token::RegisterList(_))
+                if self.forms.contains(&Form::Eight) || self.forms.contains(&Form::Nine) =>
+            {
+                return StateMachine::<RegisterListState>::from(self).handler();
+            }
+            Some(Token::Shift(_))
+                if self.forms.contains(&Form::Eleven) || self.forms.contains(&Form::Twelve) =>
+            {
+                return StateMachine::<ShiftState>::from(self).handler();
+            }
+            // Block transfers only take a register list after the base register.
+            _ if self.forms.contains(&Form::Eight) || self.forms.contains(&Form::Nine) => (),
             Some(Token::Register(_)) => return StateMachine::<RegisterState>::from(self).handler(),
-            Some(Token::Literal(immed)) => {
-                let immed = immed.get_value();
-                if (self.forms.contains(&Form::Four) && 0xFFFF < immed)
-                    || (self.forms.contains(&Form::Five) && 0xFFFFF < immed)
-                {
-                    return Err(());
-                }
+            Some(Token::Literal(ref immed)) => {
+                self.check_immediate(&token, immed)?;
                 return StateMachine::<ImmediateState>::from(self).handler();
             }
-            Some(Token::Separator(separator)) => match separator {
-                Separator::OpenBrace => return StateMachine::<OpenBraceState>::from(self).handler(),
+            Some(Token::Separator(ref separator)) => match separator {
+                Separator::OpenBrace => {
+                    return StateMachine::<OpenBraceState>::from(self).handler()
+                }
                 _ => (),
             },
             _ => (),
         }
-        return Err(());
+        if self.forms.contains(&Form::Eight) || self.forms.contains(&Form::Nine) {
+            return Err(self.unexpected(&token, "a register list"));
+        }
+        Err(self.unexpected(&token, "a register, an immediate or `[`"))
     }
 }
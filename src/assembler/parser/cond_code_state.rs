@@ -1,10 +1,11 @@
 use super::super::super::util::Form;
 use super::super::lexer::Token;
-use super::super::parser::{ConditionCodeState, StateMachine};
+use super::super::parser::{ConditionCodeState, StateMachine, SyntaxError};
 
 impl StateMachine<ConditionCodeState> {
-    pub fn handler(mut self) -> Result<Option<Form>, ()> {
-        match self.tokens.pop() {
+    pub fn handler(mut self) -> Result<Option<Form>, SyntaxError> {
+        let token = self.tokens.pop();
+        match token {
             Some(Token::Label(_)) => {
                 if self.forms.contains(&Form::Six) {
                     return Ok(Some(Form::Six));
@@ -12,6 +13,6 @@ impl StateMachine<ConditionCodeState> {
             }
             _ => (),
         }
-        return Err(());
+        Err(self.unexpected(&token, "a label"))
     }
 }
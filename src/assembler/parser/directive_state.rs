@@ -0,0 +1,89 @@
+use super::super::super::util::{Form, Literal};
+use super::super::lexer::{Directive, Separator, Token};
+use super::super::parser::{DirectiveState, StateMachine, SyntaxError};
+
+/// The largest power of two `.align` may align to.
+const MAX_ALIGNMENT: u32 = 16;
+
+/// Ensure the value of the operand fits the directive.
+pub fn check_operand(directive: Directive, immed: &Literal) -> Result<(), String> {
+    let value = immed.clone().get_value();
+    let fits = match directive {
+        Directive::Byte if immed.is_negative() => value as i32 >= -0x80,
+        Directive::Byte => value <= 0xFF,
+        Directive::Align => !immed.is_negative() && value <= MAX_ALIGNMENT,
+        Directive::Space => !immed.is_negative(),
+        _ => true,
+    };
+    if fits {
+        return Ok(());
+    }
+    Err(match directive {
+        Directive::Byte => String::from("value does not fit in a byte"),
+        Directive::Align => format!("alignment must be between 0 and {}", MAX_ALIGNMENT),
+        _ => String::from("size must not be negative"),
+    })
+}
+
+impl StateMachine<DirectiveState> {
+    /// Parse the operands of the directive: one or more separated by commas for `.word`,
+    /// `.byte` and `.asciz`, one or more labels for `.global` and `.extern`, exactly one for `.space`, `.align` and `.section`, a symbol and its
+    /// value for `.equ` and `.set`, and none for `.ltorg` and the other section directives.
+    /// Operands that are expressions are checked once they are evaluated.
+    pub fn handler(mut self, directive: Directive) -> Result<Option<Form>, SyntaxError> {
+        if directive.is_definition() {
+            let token = self.tokens.pop();
+            if !matches!(token, Some(Token::Label(_))) {
+                return Err(self.unexpected(&token, "the name of a symbol"));
+            }
+            let token = self.tokens.pop();
+            if token != Some(Token::Separator(Separator::Comma)) {
+                return Err(self.unexpected(&token, "`,`"));
+            }
+        }
+        loop {
+            let token = self.tokens.pop();
+            match (directive, &token) {
+                (Directive::Section, Some(Token::Label(_))) => (),
+                (Directive::Section, Some(Token::Directive(name))) if name.is_section() => (),
+                (Directive::Section, _) => {
+                    return Err(self.unexpected(&token, "the name of a section"))
+                }
+                (_, None) if directive.takes_no_operands() => return Ok(None),
+                (_, _) if directive.takes_no_operands() => {
+                    return Err(self.unexpected(&token, "the end of the expression"))
+                }
+                (Directive::Asciz, Some(Token::Text(_))) => (),
+                (Directive::Asciz, _) => return Err(self.unexpected(&token, "a string")),
+                (Directive::Global, Some(Token::Label(_)))
+                | (Directive::Extern, Some(Token::Label(_))) => (),
+                (Directive::Global, _) | (Directive::Extern, _) => {
+                    return Err(self.unexpected(&token, "the name of a label"))
+                }
+                (_, Some(Token::Label(_))) => (),
+                (_, Some(Token::Literal(immed))) if immed.is_expression() => (),
+                (_, Some(Token::Literal(immed))) => {
+                    check_operand(directive, immed).map_err(|reason| self.reject(&token, reason))?
+                }
+                _ => return Err(self.unexpected(&token, "an immediate or a label")),
+            }
+            let token = self.tokens.pop();
+            match (directive, &token) {
+                (_, None) => return Ok(None),
+                (Directive::Word, Some(Token::Separator(Separator::Comma)))
+                | (Directive::Byte, Some(Token::Separator(Separator::Comma)))
+                | (Directive::Asciz, Some(Token::Separator(Separator::Comma)))
+                | (Directive::Global, Some(Token::Separator(Separator::Comma)))
+                | (Directive::Extern, Some(Token::Separator(Separator::Comma))) => (),
+                (Directive::Word, _)
+                | (Directive::Byte, _)
+                | (Directive::Asciz, _)
+                | (Directive::Global, _)
+                | (Directive::Extern, _) => {
+                    return Err(self.unexpected(&token, "`,` or the end of the expression"))
+                }
+                _ => return Err(self.unexpected(&token, "the end of the expression")),
+            }
+        }
+    }
+}
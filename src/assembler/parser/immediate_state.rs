@@ -1,6 +1,6 @@
 use super::super::super::util::Form;
 use super::super::lexer::{Separator, Token};
-use super::super::parser::{CloseBraceState, ImmediateState, StateMachine};
+use super::super::parser::{CloseBraceState, ImmediateState, StateMachine, SyntaxError};
 
 impl From<StateMachine<ImmediateState>> for StateMachine<CloseBraceState> {
     fn from(machine: StateMachine<ImmediateState>) -> StateMachine<CloseBraceState> {
@@ -13,9 +13,10 @@ impl From<StateMachine<ImmediateState>> for StateMachine<CloseBraceState> {
 }
 
 impl StateMachine<ImmediateState> {
-    pub fn handler(mut self) -> Result<Option<Form>, ()> {
-        match self.tokens.pop() {
-            Some(Token::Separator(separator)) => {
+    pub fn handler(mut self) -> Result<Option<Form>, SyntaxError> {
+        let token = self.tokens.pop();
+        match token {
+            Some(Token::Separator(ref separator)) => {
                 match separator {
                     Separator::CloseBrace => {
                         // Cannot be form one.
@@ -38,10 +39,21 @@ impl StateMachine<ImmediateState> {
                 if self.forms.contains(&Form::Five) {
                     return Ok(Some(Form::Five));
                 }
-                return Err(());
+                if self.forms.contains(&Form::Seven) {
+                    return Ok(Some(Form::Seven));
+                }
+                if self.forms.contains(&Form::Eleven) {
+                    return Ok(Some(Form::Eleven));
+                }
+                if self.forms.contains(&Form::Twelve) {
+                    return Ok(Some(Form::Twelve));
+                }
+                if self.forms.contains(&Form::Fifteen) {
+                    return Ok(Some(Form::Fifteen));
+                }
             }
             _ => (),
         }
-        return Err(());
+        Err(self.unexpected(&token, "`]`"))
     }
 }
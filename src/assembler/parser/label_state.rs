@@ -1,7 +1,7 @@
 use super::super::super::util::{reducer, Form};
 use super::super::lexer::Token;
 pub use super::super::parser::StateMachine;
-use super::super::parser::{LabelState, OpcodeState};
+use super::super::parser::{DirectiveState, LabelState, OpcodeState, SyntaxError};
 
 impl From<StateMachine<LabelState>> for StateMachine<OpcodeState> {
     fn from(machine: StateMachine<LabelState>) -> StateMachine<OpcodeState> {
@@ -13,18 +13,40 @@ impl From<StateMachine<LabelState>> for StateMachine<OpcodeState> {
     }
 }
 
+impl From<StateMachine<LabelState>> for StateMachine<DirectiveState> {
+    fn from(machine: StateMachine<LabelState>) -> StateMachine<DirectiveState> {
+        StateMachine {
+            state: DirectiveState,
+            tokens: machine.tokens,
+            forms: machine.forms,
+        }
+    }
+}
+
 impl StateMachine<LabelState> {
-    pub fn handler(mut self) -> Result<Option<Form>, ()> {
-        match self.tokens.pop() {
-            Some(Token::Opcode(opcode)) => {
-                self.forms = reducer(opcode.get_forms(), &opcode, self.tokens.len() + 1);
+    pub fn handler(mut self) -> Result<Option<Form>, SyntaxError> {
+        let token = self.tokens.pop();
+        match token {
+            Some(Token::Opcode(ref opcode)) => {
+                self.forms = reducer(opcode.get_forms(), opcode, self.expr_length());
                 if self.forms.is_empty() {
-                    return Err(());
+                    let err = self.reject(
+                        &token,
+                        format!("no form of {:?} matches this expression", opcode),
+                    );
+                    // Look for the offending token by parsing against every form of the opcode.
+                    self.forms = opcode.get_forms();
+                    return StateMachine::<OpcodeState>::from(self)
+                        .handler()
+                        .and(Err(err));
                 }
-                return StateMachine::<OpcodeState>::from(self).handler();
+                StateMachine::<OpcodeState>::from(self).handler()
+            }
+            Some(Token::Directive(directive)) => {
+                StateMachine::<DirectiveState>::from(self).handler(directive)
             }
-            None => return Ok(None),
-            _ => return Err(()),
+            None => Ok(None),
+            _ => Err(self.unexpected(&token, "an opcode or a directive after a label")),
         }
     }
 }
@@ -1,26 +1,36 @@
 mod close_brace_state;
 mod comma_state;
 mod cond_code_state;
+mod directive_state;
 mod immediate_state;
 mod label_state;
 mod opcode_state;
 mod open_brace_state;
 mod ready_state;
+mod register_list_state;
 mod register_state;
+mod shift_state;
+mod writeback_state;
 
-use super::super::util::Form;
-use super::lexer::Token;
+pub use self::directive_state::check_operand;
+
+use super::super::util::{ConditionCode, Form, Literal};
+use super::lexer::{Separator, Token};
 
 /// The syntax is analyzed using a finite state machine.
 struct CloseBraceState;
 struct CommaState;
 struct ConditionCodeState;
+struct DirectiveState;
 struct ImmediateState;
 struct OpcodeState;
 struct OpenBraceState;
 struct ReadyState;
 struct RegisterState;
+struct RegisterListState;
 struct LabelState;
+struct ShiftState;
+struct WritebackState;
 
 pub struct StateMachine<S> {
     // The current state of the state machine.
@@ -31,10 +41,158 @@ pub struct StateMachine<S> {
     pub forms: Vec<Form>,
 }
 
+/// A syntax error found by the state machine.
+#[derive(Debug, PartialEq)]
+pub struct SyntaxError {
+    /// The index of the offending token in the expression, or the length of the expression if it
+    /// ended prematurely.
+    pub index: usize,
+    /// A human-readable explanation of the error.
+    pub reason: String,
+}
+
+impl<S> StateMachine<S> {
+    /// Reject the token that was last popped from the parse tree. While the state machine is
+    /// running, the index counts the tokens from the end of the expression; `run` resolves it.
+    fn reject(&self, token: &Option<Token>, reason: String) -> SyntaxError {
+        SyntaxError {
+            index: match token {
+                Some(_) => self.tokens.len() + 1,
+                None => 0,
+            },
+            reason,
+        }
+    }
+    /// Ensure the immediate fits in the operand of every form this expression may satisfy.
+    /// Immediates offsetting an address, inside brackets or following them, are signed. An
+    /// expression is checked once it is evaluated.
+    fn check_immediate(&self, token: &Option<Token>, immed: &Literal) -> Result<(), SyntaxError> {
+        if immed.is_expression() {
+            return Ok(());
+        }
+        let is_offset = match self.tokens.last() {
+            Some(Token::Separator(Separator::CloseBrace)) => true,
+            _ => self.forms == [Form::Fifteen],
+        };
+        // An offset inside brackets is pre-indexed only when the base register is written back.
+        let forms: Vec<Form> = match self.tokens.as_slice() {
+            [.., Token::Separator(Separator::Writeback), Token::Separator(Separator::CloseBrace)]
+                if self.forms.contains(&Form::Fourteen) =>
+            {
+                vec![Form::Fourteen]
+            }
+            [.., Token::Separator(Separator::CloseBrace)] => self
+                .forms
+                .iter()
+                .filter(|form| !matches!(form, Form::Fourteen | Form::Fifteen))
+                .cloned()
+                .collect(),
+            _ => self.forms.clone(),
+        };
+        check_width(&forms, is_offset, immed).map_err(|reason| self.reject(token, reason))
+    }
+    /// Get the length of the expression led by the opcode that was last popped from the parse
+    /// tree. The condition code of an instruction is not an operand, so it does not count towards
+    /// the length.
+    fn expr_length(&self) -> usize {
+        let len = self.tokens.len() + 1;
+        match self.tokens.last() {
+            Some(Token::ConditionCode(_)) => len - 1,
+            _ => len,
+        }
+    }
+    /// Reject the token that was last popped from the parse tree as not being what was expected.
+    fn unexpected(&self, token: &Option<Token>, expected: &str) -> SyntaxError {
+        let found = match token {
+            Some(token) => token.describe(),
+            None => String::from("the end of the expression"),
+        };
+        self.reject(token, format!("expected {}, found {}", expected, found))
+    }
+}
+
+/// Ensure the immediate fits in the operand of every given form. Offsets are signed.
+pub fn check_width(forms: &[Form], is_offset: bool, immed: &Literal) -> Result<(), String> {
+    let value = immed.clone().get_value();
+    let widths = [
+        (Form::Four, 16, is_offset),
+        (Form::Five, 20, is_offset),
+        (Form::Seven, 20, false),
+        (Form::Eleven, 5, false),
+        (Form::Twelve, 5, false),
+        (Form::Fourteen, 12, true),
+        (Form::Fifteen, 12, true),
+        (Form::Sixteen, 12, is_offset),
+        (Form::Seventeen, 16, is_offset),
+    ];
+    for (form, n_bits, signed) in widths.iter() {
+        let fits = match signed {
+            true => {
+                let (value, bound) = (value as i32, 1 << (n_bits - 1));
+                -bound <= value && value < bound
+            }
+            false => !immed.is_negative() && value >> n_bits == 0,
+        };
+        if forms.contains(form) && !fits {
+            let value = match immed.is_negative() {
+                true => format!("-{:#X}", value.wrapping_neg()),
+                false => format!("{:#X}", value),
+            };
+            let signedness = if *signed { " signed" } else { "" };
+            return Err(format!(
+                "immediate {} does not fit in {}{} bits for form {:?}",
+                value, n_bits, signedness, form
+            ));
+        }
+    }
+    Ok(())
+}
+
 /// Run the state machine.
-pub fn run(tokens: &mut Vec<Token>) -> Result<Option<Form>, ()> {
+pub fn run(tokens: &mut [Token]) -> Result<Option<Form>, SyntaxError> {
+    let is_conditional = tokens
+        .iter()
+        .any(|token| matches!(token, Token::ConditionCode(cc) if *cc != ConditionCode::AL));
+    tokens.reverse();
+    let len = tokens.len();
+    let form = ready_state::StateMachine::new(tokens.to_vec())
+        .handler()
+        .map_err(|err| SyntaxError {
+            index: len - err.index,
+            reason: err.reason,
+        })?;
     tokens.reverse();
-    ready_state::StateMachine::new(tokens.to_vec()).handler()
+    match form {
+        Some(form) if is_conditional && !form.has_cc() => {
+            let form = form.get_conditional();
+            check_conditional_immediate(tokens, form)?;
+            Ok(Some(form))
+        }
+        _ => Ok(form),
+    }
+}
+
+/// Ensure the immediate of an instruction executing conditionally fits in the narrower immediate
+/// of the form holding its condition code.
+fn check_conditional_immediate(tokens: &[Token], form: Form) -> Result<(), SyntaxError> {
+    let index = match tokens
+        .iter()
+        .rposition(|token| matches!(token, Token::Literal(_)))
+    {
+        Some(index) => index,
+        None => return Ok(()),
+    };
+    match &tokens[index] {
+        // An expression is checked once it is evaluated.
+        Token::Literal(immed) if !immed.is_expression() => {
+            let is_offset = matches!(
+                tokens.get(index + 1),
+                Some(Token::Separator(Separator::CloseBrace))
+            );
+            check_width(&[form], is_offset, immed).map_err(|reason| SyntaxError { index, reason })
+        }
+        _ => Ok(()),
+    }
 }
 
 #[cfg(test)]
@@ -411,6 +569,604 @@ mod tests_ldr_str_form_five {
 
 }
 
+#[cfg(test)]
+mod tests_form_seven {
+
+    use super::super::super::util::{Form::*, Literal::*, Opcode::*, Register::*};
+    use super::super::lexer::Token::*;
+    use super::*;
+
+    #[test]
+    fn test_is_ok() {
+        let mut tokens = vec![Opcode(SVC), Literal(Immediate(String::from("0x0")))];
+        assert!(run(&mut tokens).is_ok());
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        let mut tokens = vec![Opcode(SVC), Literal(Immediate(String::from("0x1FFFFF")))];
+        assert!(run(&mut tokens).is_err());
+    }
+
+    #[test]
+    fn test_register_operand() {
+        let mut tokens = vec![Opcode(SVC), Register(R0)];
+        let err = run(&mut tokens).unwrap_err();
+        assert_eq!(err.reason, "expected an immediate, found a register");
+    }
+
+    #[test]
+    fn test_form() {
+        let mut tokens = vec![Opcode(SVC), Literal(Immediate(String::from("0x0")))];
+        if let Ok(Some(form)) = run(&mut tokens) {
+            assert_eq!(form, Seven);
+        } else {
+            assert!(false);
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests_branch {
+
+    use super::super::super::util::{ConditionCode::*, Form::*, Opcode::*, Register::*};
+    use super::super::lexer::{Label::*, Token::*};
+    use super::*;
+
+    #[test]
+    fn test_branch_without_cond_code() {
+        let mut tokens = vec![Opcode(B), Label(Name(String::from("foo")))];
+        assert_eq!(run(&mut tokens), Ok(Some(Six)));
+    }
+
+    #[test]
+    fn test_branch_with_link() {
+        let mut tokens = vec![Opcode(BL), Label(Name(String::from("foo")))];
+        assert_eq!(run(&mut tokens), Ok(Some(Six)));
+        let mut tokens = vec![
+            Opcode(BL),
+            ConditionCode(NE),
+            Label(Name(String::from("foo"))),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Six)));
+    }
+
+    #[test]
+    fn test_branch_to_register() {
+        let mut tokens = vec![Opcode(BL), Register(LR)];
+        let err = run(&mut tokens).unwrap_err();
+        assert_eq!(err.reason, "expected a label, found a register");
+    }
+
+    #[test]
+    fn test_branch_and_exchange() {
+        let mut tokens = vec![Opcode(BX), Register(LR)];
+        assert_eq!(run(&mut tokens), Ok(Some(Three)));
+        let mut tokens = vec![Opcode(BX), ConditionCode(EQ), Register(R3)];
+        assert_eq!(run(&mut tokens), Ok(Some(Three)));
+    }
+
+}
+
+#[cfg(test)]
+mod tests_block_transfer {
+
+    use super::super::super::util::{Form::*, Opcode::*, Register::*};
+    use super::super::lexer::{Separator::*, Token::*};
+    use super::*;
+
+    #[test]
+    fn test_form_eight() {
+        let mut tokens = vec![
+            Opcode(LDMIA),
+            Register(R0),
+            Separator(Comma),
+            RegisterList(vec![R1, R2]),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Eight)));
+    }
+
+    #[test]
+    fn test_form_nine() {
+        let mut tokens = vec![
+            Opcode(STMDB),
+            Register(SP),
+            Separator(Writeback),
+            Separator(Comma),
+            RegisterList(vec![R4, LR]),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Nine)));
+    }
+
+    #[test]
+    fn test_form_ten() {
+        let mut tokens = vec![Opcode(PUSH), RegisterList(vec![R4, LR])];
+        assert_eq!(run(&mut tokens), Ok(Some(Ten)));
+    }
+
+    #[test]
+    fn test_register_instead_of_list() {
+        let mut tokens = vec![Opcode(LDMIA), Register(R0), Separator(Comma), Register(R1)];
+        let err = run(&mut tokens).unwrap_err();
+        assert_eq!(err.index, 3);
+        assert_eq!(err.reason, "expected a register list, found a register");
+        let mut tokens = vec![Opcode(POP), Register(R1)];
+        let err = run(&mut tokens).unwrap_err();
+        assert_eq!(err.reason, "expected a register list, found a register");
+    }
+
+    #[test]
+    fn test_misplaced_writeback() {
+        let mut tokens = vec![
+            Opcode(LDMIA),
+            Register(R0),
+            Separator(Comma),
+            Separator(Writeback),
+            RegisterList(vec![R1]),
+        ];
+        assert!(run(&mut tokens).is_err());
+    }
+
+}
+
+#[cfg(test)]
+mod tests_shift {
+
+    use super::super::super::util::{self, Form::*, Literal::*, Register::*};
+    use super::super::lexer::{Separator::*, Token::*};
+    use super::*;
+
+    #[test]
+    fn test_form_eleven() {
+        let mut tokens = vec![
+            Opcode(util::Opcode::ADD),
+            Register(R0),
+            Separator(Comma),
+            Register(R1),
+            Separator(Comma),
+            Register(R2),
+            Separator(Comma),
+            Shift(util::Shift::LSL),
+            Literal(Immediate(String::from("2"))),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Eleven)));
+        let mut tokens = vec![
+            Opcode(util::Opcode::ADD),
+            Register(R0),
+            Separator(Comma),
+            Register(R1),
+            Separator(Comma),
+            Register(R2),
+            Separator(Comma),
+            Shift(util::Shift::ROR),
+            Register(R3),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Eleven)));
+    }
+
+    #[test]
+    fn test_form_twelve() {
+        let mut tokens = vec![
+            Opcode(util::Opcode::MOV),
+            Register(R0),
+            Separator(Comma),
+            Register(R1),
+            Separator(Comma),
+            Shift(util::Shift::ASR),
+            Literal(Immediate(String::from("31"))),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Twelve)));
+    }
+
+    #[test]
+    fn test_missing_shift() {
+        let mut tokens = vec![
+            Opcode(util::Opcode::MOV),
+            Register(R0),
+            Separator(Comma),
+            Register(R1),
+            Separator(Comma),
+            Register(R2),
+            Register(R3),
+        ];
+        assert!(run(&mut tokens).is_err());
+    }
+
+    #[test]
+    fn test_shift_amount_out_of_bounds() {
+        let mut tokens = vec![
+            Opcode(util::Opcode::MOV),
+            Register(R0),
+            Separator(Comma),
+            Register(R1),
+            Separator(Comma),
+            Shift(util::Shift::LSL),
+            Literal(Immediate(String::from("32"))),
+        ];
+        let err = run(&mut tokens).unwrap_err();
+        assert_eq!(err.index, 6);
+    }
+
+}
+
+#[cfg(test)]
+mod tests_indexed_addressing {
+
+    use super::super::super::util::{self, Form::*, Literal::*, Opcode::*, Register::*};
+    use super::super::lexer::{Separator::*, Token::*};
+    use super::*;
+
+    #[test]
+    fn test_form_four_negative_offset() {
+        let mut tokens = vec![
+            Opcode(LDR),
+            Register(R0),
+            Separator(Comma),
+            Separator(OpenBrace),
+            Register(R1),
+            Separator(Comma),
+            Literal(Immediate(String::from("-4"))),
+            Separator(CloseBrace),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Four)));
+    }
+
+    #[test]
+    fn test_form_fourteen() {
+        let mut tokens = vec![
+            Opcode(STR),
+            Register(R0),
+            Separator(Comma),
+            Separator(OpenBrace),
+            Register(R1),
+            Separator(Comma),
+            Literal(Immediate(String::from("-4"))),
+            Separator(CloseBrace),
+            Separator(Writeback),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Fourteen)));
+    }
+
+    #[test]
+    fn test_form_fifteen() {
+        let mut tokens = vec![
+            Opcode(LDRB),
+            Register(R0),
+            Separator(Comma),
+            Separator(OpenBrace),
+            Register(R1),
+            Separator(CloseBrace),
+            Separator(Comma),
+            Literal(Immediate(String::from("1"))),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Fifteen)));
+    }
+
+    #[test]
+    fn test_form_eleven_scaled_register_offset() {
+        let mut tokens = vec![
+            Opcode(LDR),
+            Register(R0),
+            Separator(Comma),
+            Separator(OpenBrace),
+            Register(R1),
+            Separator(Comma),
+            Register(R2),
+            Separator(Comma),
+            Shift(util::Shift::LSL),
+            Literal(Immediate(String::from("2"))),
+            Separator(CloseBrace),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Eleven)));
+    }
+
+    #[test]
+    fn test_writeback_without_offset() {
+        let mut tokens = vec![
+            Opcode(LDR),
+            Register(R0),
+            Separator(Comma),
+            Separator(OpenBrace),
+            Register(R1),
+            Separator(CloseBrace),
+            Separator(Writeback),
+        ];
+        assert!(run(&mut tokens).is_err());
+    }
+
+    #[test]
+    fn test_negative_offset_out_of_bounds() {
+        let mut tokens = vec![
+            Opcode(LDR),
+            Register(R0),
+            Separator(Comma),
+            Separator(OpenBrace),
+            Register(R1),
+            Separator(CloseBrace),
+            Separator(Comma),
+            Literal(Immediate(String::from("-2049"))),
+        ];
+        let err = run(&mut tokens).unwrap_err();
+        assert_eq!(err.index, 7);
+    }
+
+}
+
+#[cfg(test)]
+mod tests_directive {
+
+    use super::super::super::util::Literal::*;
+    use super::super::lexer::{
+        Directive::{Align, Asciz, Byte, Equ, Extern, Global, Set, Space, Word},
+        Label::*,
+        Separator::*,
+        Token::*,
+    };
+    use super::*;
+
+    #[test]
+    fn test_word() {
+        let mut tokens = vec![
+            Label(Name(String::from("table"))),
+            Directive(Word),
+            Literal(Immediate(String::from("-1"))),
+            Separator(Comma),
+            Label(Name(String::from("table"))),
+        ];
+        assert_eq!(run(&mut tokens), Ok(None));
+    }
+
+    #[test]
+    fn test_asciz() {
+        let mut tokens = vec![
+            Directive(Asciz),
+            Text(String::from("a")),
+            Separator(Comma),
+            Text(String::from("b")),
+        ];
+        assert_eq!(run(&mut tokens), Ok(None));
+    }
+
+    #[test]
+    fn test_missing_operand() {
+        let mut tokens = vec![Directive(Byte)];
+        let err = run(&mut tokens).unwrap_err();
+        assert_eq!(err.index, 1);
+        assert_eq!(
+            err.reason,
+            "expected an immediate or a label, found the end of the expression"
+        );
+    }
+
+    #[test]
+    fn test_trailing_comma() {
+        let mut tokens = vec![
+            Directive(Word),
+            Literal(Immediate(String::from("1"))),
+            Separator(Comma),
+        ];
+        assert!(run(&mut tokens).is_err());
+    }
+
+    #[test]
+    fn test_byte_out_of_bounds() {
+        let mut tokens = vec![
+            Directive(Byte),
+            Literal(Immediate(String::from("0xFF"))),
+            Separator(Comma),
+            Literal(Immediate(String::from("-129"))),
+        ];
+        let err = run(&mut tokens).unwrap_err();
+        assert_eq!(err.index, 3);
+        assert_eq!(err.reason, "value does not fit in a byte");
+    }
+
+    #[test]
+    fn test_space_takes_single_operand() {
+        let mut tokens = vec![
+            Directive(Space),
+            Literal(Immediate(String::from("4"))),
+            Separator(Comma),
+            Literal(Immediate(String::from("4"))),
+        ];
+        let err = run(&mut tokens).unwrap_err();
+        assert_eq!(err.index, 2);
+    }
+
+    #[test]
+    fn test_align_out_of_bounds() {
+        let mut tokens = vec![Directive(Align), Literal(Immediate(String::from("17")))];
+        let err = run(&mut tokens).unwrap_err();
+        assert_eq!(err.reason, "alignment must be between 0 and 16");
+    }
+
+    #[test]
+    fn test_symbol_in_byte() {
+        let mut tokens = vec![Directive(Byte), Label(Name(String::from("foo")))];
+        assert_eq!(run(&mut tokens), Ok(None));
+    }
+
+    #[test]
+    fn test_definition() {
+        let mut tokens = vec![
+            Directive(Equ),
+            Label(Name(String::from("LEN"))),
+            Separator(Comma),
+            Literal(Expression(String::from("4*5"))),
+        ];
+        assert_eq!(run(&mut tokens), Ok(None));
+        let mut tokens = vec![Directive(Set), Literal(Immediate(String::from("4")))];
+        let err = run(&mut tokens).unwrap_err();
+        assert_eq!(err.index, 1);
+        assert_eq!(
+            err.reason,
+            "expected the name of a symbol, found an immediate"
+        );
+        let mut tokens = vec![Directive(Equ), Label(Name(String::from("LEN")))];
+        let err = run(&mut tokens).unwrap_err();
+        assert_eq!(err.reason, "expected `,`, found the end of the expression");
+    }
+
+    #[test]
+    fn test_visibility() {
+        let mut tokens = vec![
+            Directive(Global),
+            Label(Name(String::from("main"))),
+            Separator(Comma),
+            Label(Name(String::from("exit"))),
+        ];
+        assert_eq!(run(&mut tokens), Ok(None));
+        let mut tokens = vec![Directive(Extern), Literal(Immediate(String::from("4")))];
+        let err = run(&mut tokens).unwrap_err();
+        assert_eq!(
+            err.reason,
+            "expected the name of a label, found an immediate"
+        );
+    }
+
+    #[test]
+    fn test_expression_checked_later() {
+        let mut tokens = vec![Directive(Align), Literal(Expression(String::from("LEN*8")))];
+        assert_eq!(run(&mut tokens), Ok(None));
+    }
+
+}
+
+#[cfg(test)]
+mod tests_multiply_divide {
+
+    use super::super::super::util::{Form::*, Opcode::*, Register::*};
+    use super::super::lexer::{Separator::*, Token::*};
+    use super::*;
+
+    #[test]
+    fn test_form_thirteen() {
+        let mut tokens = vec![
+            Opcode(UMLAL),
+            Register(R0),
+            Separator(Comma),
+            Register(R1),
+            Separator(Comma),
+            Register(R2),
+            Separator(Comma),
+            Register(R3),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Thirteen)));
+    }
+
+    #[test]
+    fn test_missing_accumulator() {
+        let mut tokens = vec![
+            Opcode(MLA),
+            Register(R0),
+            Separator(Comma),
+            Register(R1),
+            Separator(Comma),
+            Register(R2),
+        ];
+        assert!(run(&mut tokens).is_err());
+    }
+
+    #[test]
+    fn test_divide() {
+        let mut tokens = vec![
+            Opcode(SDIV),
+            Register(R0),
+            Separator(Comma),
+            Register(R1),
+            Separator(Comma),
+            Register(R2),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(One)));
+    }
+
+}
+
+#[cfg(test)]
+mod tests_conditional_execution {
+
+    use super::super::super::util::{
+        ConditionCode::*, Form::*, Literal::*, Opcode::*, Register::*,
+    };
+    use super::super::lexer::{Separator::*, Token::*};
+    use super::*;
+
+    #[test]
+    fn test_form_one() {
+        let mut tokens = vec![
+            Opcode(ADD),
+            ConditionCode(EQ),
+            Register(R0),
+            Separator(Comma),
+            Register(R0),
+            Separator(Comma),
+            Register(R0),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(One)));
+    }
+
+    #[test]
+    fn test_form_five() {
+        let mut tokens = vec![
+            Opcode(MOV),
+            ConditionCode(NE),
+            Register(R0),
+            Separator(Comma),
+            Literal(Immediate(String::from("0x1234"))),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Seventeen)));
+    }
+
+    #[test]
+    fn test_form_five_unconditional() {
+        let mut tokens = vec![
+            Opcode(MOV),
+            ConditionCode(AL),
+            Register(R0),
+            Separator(Comma),
+            Literal(Immediate(String::from("0x12345"))),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Five)));
+    }
+
+    #[test]
+    fn test_form_four_out_of_bounds() {
+        let mut tokens = vec![
+            Opcode(ADD),
+            ConditionCode(EQ),
+            Register(R0),
+            Separator(Comma),
+            Register(R0),
+            Separator(Comma),
+            Literal(Immediate(String::from("0x1000"))),
+        ];
+        assert!(run(&mut tokens).is_err());
+    }
+
+    #[test]
+    fn test_form_seven() {
+        let mut tokens = vec![
+            Opcode(SVC),
+            ConditionCode(GT),
+            Literal(Immediate(String::from("0x0"))),
+        ];
+        assert_eq!(run(&mut tokens), Ok(Some(Seven)));
+    }
+
+    #[test]
+    fn test_missing_operand() {
+        let mut tokens = vec![
+            Opcode(MOV),
+            ConditionCode(NE),
+            Register(R0),
+            Separator(Comma),
+        ];
+        assert!(run(&mut tokens).is_err());
+    }
+
+}
+
 #[cfg(test)]
 mod tests_incorrect_behaviour {
 
@@ -530,4 +1286,5 @@ mod tests_incorrect_behaviour {
         ];
         assert!(run(&mut tokens).is_err());
     }
+
 }
@@ -1,6 +1,9 @@
 use super::super::super::util::Form;
 use super::super::lexer::Token;
-use super::super::parser::{ConditionCodeState, OpcodeState, RegisterState, StateMachine};
+use super::super::parser::{
+    ConditionCodeState, ImmediateState, OpcodeState, RegisterListState, RegisterState,
+    StateMachine, SyntaxError,
+};
 
 impl From<StateMachine<OpcodeState>> for StateMachine<RegisterState> {
     fn from(machine: StateMachine<OpcodeState>) -> StateMachine<RegisterState> {
@@ -22,15 +25,64 @@ impl From<StateMachine<OpcodeState>> for StateMachine<ConditionCodeState> {
     }
 }
 
+impl From<StateMachine<OpcodeState>> for StateMachine<ImmediateState> {
+    fn from(machine: StateMachine<OpcodeState>) -> StateMachine<ImmediateState> {
+        StateMachine {
+            state: ImmediateState,
+            tokens: machine.tokens,
+            forms: machine.forms,
+        }
+    }
+}
+
+impl From<StateMachine<OpcodeState>> for StateMachine<RegisterListState> {
+    fn from(machine: StateMachine<OpcodeState>) -> StateMachine<RegisterListState> {
+        StateMachine {
+            state: RegisterListState,
+            tokens: machine.tokens,
+            forms: machine.forms,
+        }
+    }
+}
+
 impl StateMachine<OpcodeState> {
-    pub fn handler(mut self) -> Result<Option<Form>, ()> {
-        match self.tokens.pop() {
-            Some(Token::Register(_)) => return StateMachine::<RegisterState>::from(self).handler(),
+    pub fn handler(mut self) -> Result<Option<Form>, SyntaxError> {
+        let token = self.tokens.pop();
+        match token {
+            // Branches, supervisor calls and stack operations do not take a register.
+            Some(Token::Register(_))
+                if ![Form::Six, Form::Seven, Form::Ten]
+                    .iter()
+                    .any(|form| self.forms.contains(form)) =>
+            {
+                return StateMachine::<RegisterState>::from(self).handler();
+            }
+            Some(Token::RegisterList(_)) if self.forms.contains(&Form::Ten) => {
+                return StateMachine::<RegisterListState>::from(self).handler();
+            }
+            Some(Token::Label(_)) if self.forms.contains(&Form::Six) => return Ok(Some(Form::Six)),
             Some(Token::ConditionCode(_)) => {
-                return StateMachine::<ConditionCodeState>::from(self).handler();
+                if self.forms.contains(&Form::Six) {
+                    return StateMachine::<ConditionCodeState>::from(self).handler();
+                }
+                // Any other instruction is executed conditionally, with its operands following.
+                return self.handler();
+            }
+            Some(Token::Literal(ref immed)) if self.forms.contains(&Form::Seven) => {
+                self.check_immediate(&token, immed)?;
+                return StateMachine::<ImmediateState>::from(self).handler();
             }
             _ => (),
         }
-        return Err(());
+        if self.forms.contains(&Form::Six) {
+            return Err(self.unexpected(&token, "a label"));
+        }
+        if self.forms.contains(&Form::Ten) {
+            return Err(self.unexpected(&token, "a register list"));
+        }
+        if self.forms.contains(&Form::Seven) {
+            return Err(self.unexpected(&token, "an immediate"));
+        }
+        Err(self.unexpected(&token, "a register"))
     }
 }
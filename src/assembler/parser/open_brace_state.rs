@@ -1,6 +1,8 @@
 use super::super::super::util::Form;
 use super::super::lexer::Token;
-use super::super::parser::{ImmediateState, OpenBraceState, RegisterState, StateMachine};
+use super::super::parser::{
+    ImmediateState, OpenBraceState, RegisterState, StateMachine, SyntaxError,
+};
 
 impl From<StateMachine<OpenBraceState>> for StateMachine<RegisterState> {
     fn from(machine: StateMachine<OpenBraceState>) -> StateMachine<RegisterState> {
@@ -23,11 +25,15 @@ impl From<StateMachine<OpenBraceState>> for StateMachine<ImmediateState> {
 }
 
 impl StateMachine<OpenBraceState> {
-    pub fn handler(mut self) -> Result<Option<Form>, ()> {
-        match self.tokens.pop() {
-            Some(Token::Literal(_)) => StateMachine::<ImmediateState>::from(self).handler(),
+    pub fn handler(mut self) -> Result<Option<Form>, SyntaxError> {
+        let token = self.tokens.pop();
+        match token {
+            Some(Token::Literal(ref immed)) => {
+                self.check_immediate(&token, immed)?;
+                StateMachine::<ImmediateState>::from(self).handler()
+            }
             Some(Token::Register(_)) => StateMachine::<RegisterState>::from(self).handler(),
-            _ => Err(()),
+            _ => Err(self.unexpected(&token, "a register or an immediate")),
         }
     }
 }
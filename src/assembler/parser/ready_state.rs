@@ -1,7 +1,7 @@
-use super::super::super::util::{reducer, Form, Opcode};
+use super::super::super::util::{reducer, Form};
 use super::super::lexer::{Separator, Token};
 pub use super::super::parser::StateMachine;
-use super::super::parser::{LabelState, OpcodeState, ReadyState};
+use super::super::parser::{DirectiveState, LabelState, OpcodeState, ReadyState, SyntaxError};
 
 impl From<StateMachine<ReadyState>> for StateMachine<OpcodeState> {
     fn from(machine: StateMachine<ReadyState>) -> StateMachine<OpcodeState> {
@@ -13,6 +13,16 @@ impl From<StateMachine<ReadyState>> for StateMachine<OpcodeState> {
     }
 }
 
+impl From<StateMachine<ReadyState>> for StateMachine<DirectiveState> {
+    fn from(machine: StateMachine<ReadyState>) -> StateMachine<DirectiveState> {
+        StateMachine {
+            state: DirectiveState,
+            tokens: machine.tokens,
+            forms: machine.forms,
+        }
+    }
+}
+
 impl From<StateMachine<ReadyState>> for StateMachine<LabelState> {
     fn from(machine: StateMachine<ReadyState>) -> StateMachine<LabelState> {
         StateMachine {
@@ -31,28 +41,47 @@ impl StateMachine<ReadyState> {
             forms: Vec::new(),
         }
     }
-    pub fn handler(mut self) -> Result<Option<Form>, ()> {
-        match self.tokens.pop() {
-            Some(Token::Opcode(opcode)) => {
-                self.forms = reducer(opcode.get_forms(), &opcode, self.tokens.len() + 1);
+    pub fn handler(mut self) -> Result<Option<Form>, SyntaxError> {
+        let token = self.tokens.pop();
+        match token {
+            Some(Token::Opcode(ref opcode)) => {
+                self.forms = reducer(opcode.get_forms(), opcode, self.expr_length());
                 // Ensure there exist a valid form.
                 if self.forms.is_empty() {
-                    return Err(());
+                    let err = self.reject(
+                        &token,
+                        format!("no form of {:?} matches this expression", opcode),
+                    );
+                    // Look for the offending token by parsing against every form of the opcode.
+                    self.forms = opcode.get_forms();
+                    return StateMachine::<OpcodeState>::from(self)
+                        .handler()
+                        .and(Err(err));
                 }
-                // If LDR or STR instruction, ensure that expression ends with a close bracket.
-                match opcode {
-                    Opcode::STR | Opcode::LDR => match self.tokens.get(0) {
+                // If load or store instruction, ensure that expression ends with a close bracket
+                // unless it writes the base register back.
+                let writeback = [Form::Fourteen, Form::Fifteen];
+                if opcode.is_load_or_store()
+                    && !writeback.iter().any(|form| self.forms.contains(form))
+                {
+                    match self.tokens.get(0) {
                         Some(Token::Separator(Separator::CloseBrace)) => (),
-                        _ => return Err(()),
-                    },
-                    _ => (),
+                        _ => {
+                            // Point at the last token of the expression.
+                            return Err(SyntaxError {
+                                index: 1,
+                                reason: format!("expected {:?} expression to end with `]`", opcode),
+                            });
+                        }
+                    }
                 }
-                return StateMachine::<OpcodeState>::from(self).handler();
+                StateMachine::<OpcodeState>::from(self).handler()
             }
-            Some(Token::Label(_)) => {
-                return StateMachine::<LabelState>::from(self).handler();
+            Some(Token::Directive(directive)) => {
+                StateMachine::<DirectiveState>::from(self).handler(directive)
             }
-            _ => return Err(()),
+            Some(Token::Label(_)) => StateMachine::<LabelState>::from(self).handler(),
+            _ => Err(self.unexpected(&token, "an opcode, a directive or a label")),
         }
     }
 }
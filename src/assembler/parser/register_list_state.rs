@@ -0,0 +1,16 @@
+use super::super::super::util::Form;
+use super::super::parser::{RegisterListState, StateMachine, SyntaxError};
+
+impl StateMachine<RegisterListState> {
+    pub fn handler(mut self) -> Result<Option<Form>, SyntaxError> {
+        let token = self.tokens.pop();
+        if token.is_none() {
+            for form in [Form::Eight, Form::Nine, Form::Ten].iter() {
+                if self.forms.contains(form) {
+                    return Ok(Some(*form));
+                }
+            }
+        }
+        Err(self.unexpected(&token, "the end of the expression"))
+    }
+}
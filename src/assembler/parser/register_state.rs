@@ -1,6 +1,8 @@
 use super::super::super::util::Form;
 use super::super::lexer::{Separator, Token};
-use super::super::parser::{CloseBraceState, CommaState, RegisterState, StateMachine};
+use super::super::parser::{
+    CloseBraceState, CommaState, RegisterState, StateMachine, SyntaxError, WritebackState,
+};
 
 impl From<StateMachine<RegisterState>> for StateMachine<CloseBraceState> {
     fn from(machine: StateMachine<RegisterState>) -> StateMachine<CloseBraceState> {
@@ -22,15 +24,28 @@ impl From<StateMachine<RegisterState>> for StateMachine<CommaState> {
     }
 }
 
+impl From<StateMachine<RegisterState>> for StateMachine<WritebackState> {
+    fn from(machine: StateMachine<RegisterState>) -> StateMachine<WritebackState> {
+        StateMachine {
+            state: WritebackState,
+            tokens: machine.tokens,
+            forms: machine.forms,
+        }
+    }
+}
+
 impl StateMachine<RegisterState> {
-    pub fn handler(mut self) -> Result<Option<Form>, ()> {
+    pub fn handler(mut self) -> Result<Option<Form>, SyntaxError> {
         let token = self.tokens.pop();
         match token {
-            Some(Token::Separator(separator)) => match separator {
+            Some(Token::Separator(ref separator)) => match separator {
                 Separator::Comma => return StateMachine::<CommaState>::from(self).handler(),
                 Separator::CloseBrace => {
                     return StateMachine::<CloseBraceState>::from(self).handler();
                 }
+                Separator::Writeback if self.forms.contains(&Form::Nine) => {
+                    return StateMachine::<WritebackState>::from(self).handler();
+                }
                 _ => (),
             },
             None => {
@@ -40,9 +55,21 @@ impl StateMachine<RegisterState> {
                 if self.forms.contains(&Form::Two) {
                     return Ok(Some(Form::Two));
                 }
+                if self.forms.contains(&Form::Three) {
+                    return Ok(Some(Form::Three));
+                }
+                if self.forms.contains(&Form::Eleven) {
+                    return Ok(Some(Form::Eleven));
+                }
+                if self.forms.contains(&Form::Twelve) {
+                    return Ok(Some(Form::Twelve));
+                }
+                if self.forms.contains(&Form::Thirteen) {
+                    return Ok(Some(Form::Thirteen));
+                }
             }
             _ => (),
         }
-        return Err(());
+        Err(self.unexpected(&token, "`,` or `]`"))
     }
 }
@@ -0,0 +1,37 @@
+use super::super::super::util::Form;
+use super::super::lexer::Token;
+use super::super::parser::{ImmediateState, RegisterState, ShiftState, StateMachine, SyntaxError};
+
+impl From<StateMachine<ShiftState>> for StateMachine<ImmediateState> {
+    fn from(machine: StateMachine<ShiftState>) -> StateMachine<ImmediateState> {
+        StateMachine {
+            state: ImmediateState,
+            tokens: machine.tokens,
+            forms: machine.forms,
+        }
+    }
+}
+
+impl From<StateMachine<ShiftState>> for StateMachine<RegisterState> {
+    fn from(machine: StateMachine<ShiftState>) -> StateMachine<RegisterState> {
+        StateMachine {
+            state: RegisterState,
+            tokens: machine.tokens,
+            forms: machine.forms,
+        }
+    }
+}
+
+impl StateMachine<ShiftState> {
+    pub fn handler(mut self) -> Result<Option<Form>, SyntaxError> {
+        let token = self.tokens.pop();
+        match token {
+            Some(Token::Literal(ref immed)) => {
+                self.check_immediate(&token, immed)?;
+                StateMachine::<ImmediateState>::from(self).handler()
+            }
+            Some(Token::Register(_)) => StateMachine::<RegisterState>::from(self).handler(),
+            _ => Err(self.unexpected(&token, "an immediate or a register")),
+        }
+    }
+}
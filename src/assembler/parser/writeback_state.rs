@@ -0,0 +1,29 @@
+use super::super::super::util::Form;
+use super::super::lexer::{Separator, Token};
+use super::super::parser::{CommaState, StateMachine, SyntaxError, WritebackState};
+
+impl From<StateMachine<WritebackState>> for StateMachine<CommaState> {
+    fn from(machine: StateMachine<WritebackState>) -> StateMachine<CommaState> {
+        StateMachine {
+            state: CommaState,
+            tokens: machine.tokens,
+            forms: machine.forms,
+        }
+    }
+}
+
+impl StateMachine<WritebackState> {
+    pub fn handler(mut self) -> Result<Option<Form>, SyntaxError> {
+        let token = self.tokens.pop();
+        match token {
+            Some(Token::Separator(Separator::Comma)) if self.forms.contains(&Form::Nine) => {
+                StateMachine::<CommaState>::from(self).handler()
+            }
+            None if self.forms.contains(&Form::Fourteen) => Ok(Some(Form::Fourteen)),
+            _ if self.forms.contains(&Form::Fourteen) => {
+                Err(self.unexpected(&token, "the end of the expression"))
+            }
+            _ => Err(self.unexpected(&token, "`,`")),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+extern crate strum;
+#[macro_use]
+extern crate strum_macros;
+
+extern crate num_derive;
+
+pub mod assembler;
+pub mod util;
+pub mod vm;
@@ -1,33 +1,243 @@
-extern crate strum;
-#[macro_use]
-extern crate strum_macros;
+extern crate arm_vm;
+extern crate clap;
 
-extern crate num_derive;
+use arm_vm::util::{image, parse_address, Layout, Program};
+use arm_vm::{assembler, vm};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 
-extern crate clap;
-use clap::{App, Arg};
+use std::fs;
+use std::io::{self, Read};
+use std::process;
 
-mod assembler;
-mod util;
-mod vm;
+/// The path used on the command line to refer to the standard input.
+const STDIN_PATH: &str = "-";
 
 fn main() {
+    let source = Arg::with_name("SOURCE")
+        .help("Assembly source files to read, or - for the standard input")
+        .required(true)
+        .multiple(true)
+        .index(1);
+    let define = Arg::with_name("define")
+        .short("D")
+        .value_name("NAME=VALUE")
+        .help("Define a symbol in every source file, as `.equ` would, standing for 1 by default")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1);
+    let include = Arg::with_name("include")
+        .short("I")
+        .long("include")
+        .value_name("DIR")
+        .help("Search the directory for the files included by `.include`")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1);
+    let trace = Arg::with_name("trace")
+        .short("t")
+        .long("trace")
+        .help("Log every step of the execution cycle");
+    let trace_output = Arg::with_name("trace-output")
+        .long("trace-output")
+        .value_name("FILE")
+        .help("Record every executed instruction to a file")
+        .takes_value(true);
+    let trace_format = Arg::with_name("trace-format")
+        .long("trace-format")
+        .value_name("FORMAT")
+        .help("Format of the records written to the trace output")
+        .possible_values(&["jsonl", "csv"])
+        .default_value("jsonl");
+    let layout = Arg::with_name("layout")
+        .long("layout")
+        .value_name("FILE")
+        .help("File describing where sections are placed in memory and where execution starts")
+        .takes_value(true);
+    let section = Arg::with_name("section")
+        .long("section")
+        .value_name("NAME=ADDRESS")
+        .help("Place a section at the given address, overriding the layout file")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1);
+    let entry = Arg::with_name("entry")
+        .long("entry")
+        .value_name("SYMBOL")
+        .help("Start executing at the given symbol, overriding the layout file")
+        .takes_value(true);
+    let stack = Arg::with_name("stack")
+        .long("stack")
+        .value_name("ADDRESS")
+        .help("Initial value of the stack pointer, overriding the layout file")
+        .takes_value(true);
     let matches = App::new("arm-vm")
         .version("1.0")
         .author("Kyle Horne <me@kyhorne.com>")
         .about("Virtualization of a 32-bit ARM-like processor with native execution.")
-        .arg(
-            Arg::with_name("read")
-                .short("R")
-                .long("read")
-                .help("Load assembly/pgrm.asm into the main memory of the virtual machine and run")
-                .takes_value(false),
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Assemble source files, load them into main memory and run them")
+                .arg(source.clone())
+                .arg(include.clone())
+                .arg(define.clone())
+                .arg(layout.clone())
+                .arg(section.clone())
+                .arg(entry.clone())
+                .arg(stack.clone())
+                .arg(trace.clone())
+                .arg(trace_output.clone())
+                .arg(trace_format.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("assemble")
+                .about("Assemble source files into a program image")
+                .arg(source)
+                .arg(include)
+                .arg(define)
+                .arg(layout)
+                .arg(section)
+                .arg(entry)
+                .arg(stack)
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("OUT")
+                        .help("File the program image is written to")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("exec")
+                .about("Load a program image into main memory and run it")
+                .arg(
+                    Arg::with_name("IMAGE")
+                        .help("Program image produced by the assemble subcommand")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(trace)
+                .arg(trace_output)
+                .arg(trace_format),
         )
         .get_matches();
-    if matches.is_present("read") {
-        let program = assembler::Assembler::new().read_file();
-        let mut vm = vm::Processor::new();
-        vm.load_program(&program);
-        vm.run();
+    let result = match matches.subcommand() {
+        ("run", Some(matches)) => assemble(matches).and_then(|program| execute(&program, matches)),
+        ("assemble", Some(matches)) => assemble(matches).and_then(|program| {
+            let path = matches.value_of("output").unwrap();
+            fs::write(path, image::program_to_bytes(&program))
+                .map(|_| 0)
+                .map_err(|err| with_path(path, err))
+        }),
+        ("exec", Some(matches)) => {
+            let path = matches.value_of("IMAGE").unwrap();
+            fs::read(path)
+                .and_then(|bytes| image::program_from_bytes(&bytes))
+                .map_err(|err| with_path(path, err))
+                .and_then(|program| execute(&program, matches))
+        }
+        _ => Ok(0),
+    };
+    match result {
+        Ok(status) => process::exit(status),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Read the layout described by the layout file and the options given on the command line.
+fn layout(matches: &ArgMatches) -> Result<Layout, String> {
+    let mut layout = match matches.value_of("layout") {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|err| with_path(path, err))
+            .and_then(|source| {
+                Layout::parse(&source).map_err(|reason| format!("error: {}: {}", path, reason))
+            })?,
+        None => Layout::default(),
+    };
+    for section in matches.values_of("section").into_iter().flatten() {
+        let mut parts = section.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(address)) => layout.set_base(name, address),
+            _ => Err(format!("expected NAME=ADDRESS, found `{}`", section)),
+        }
+        .map_err(|reason| format!("error: --section: {}", reason))?;
+    }
+    if let Some(entry) = matches.value_of("entry") {
+        layout.entry = Some(entry.to_string());
+    }
+    if let Some(stack) = matches.value_of("stack") {
+        let stack = parse_address(stack).map_err(|reason| format!("error: --stack: {}", reason))?;
+        layout.stack_top = Some(stack);
+    }
+    Ok(layout)
+}
+
+/// Assemble the source files given on the command line into a single program.
+fn assemble(matches: &ArgMatches) -> Result<Program, String> {
+    let mut assembler = assembler::Assembler::with_layout(layout(matches)?);
+    for path in matches.values_of("include").into_iter().flatten() {
+        assembler.add_include_path(path);
+    }
+    for definition in matches.values_of("define").into_iter().flatten() {
+        let mut parts = definition.splitn(2, '=');
+        let (name, value) = (parts.next().unwrap(), parts.next().unwrap_or("1"));
+        assembler
+            .add_symbol(name, value)
+            .map_err(|reason| format!("error: -D: {}", reason))?;
+    }
+    let mut sources = Vec::new();
+    for path in matches.values_of("SOURCE").unwrap() {
+        let source = match path {
+            STDIN_PATH => {
+                let mut source = String::new();
+                io::stdin().read_to_string(&mut source).map(|_| source)
+            }
+            path => fs::read_to_string(path),
+        };
+        let name = match path {
+            STDIN_PATH => "<stdin>",
+            path => path,
+        };
+        sources.push((source.map_err(|err| with_path(name, err))?, name));
+    }
+    assembler
+        .read_all(
+            sources
+                .iter()
+                .map(|(source, name)| (source.as_bytes(), *name))
+                .collect(),
+        )
+        .map_err(|err| err.to_string())
+}
+
+/// Describe an I/O error along with the path of the file it occurred on.
+fn with_path(path: &str, err: io::Error) -> String {
+    format!("error: {}: {}", path, err)
+}
+
+/// Load the program into a new virtual machine and run it, returning the exit status of the
+/// program.
+fn execute(program: &Program, matches: &ArgMatches) -> Result<i32, String> {
+    let mut vm = vm::Processor::new();
+    if matches.is_present("trace") {
+        vm.attach_tracer(vm::ConsoleTracer::new());
+    }
+    if let Some(path) = matches.value_of("trace-output") {
+        let format = match matches.value_of("trace-format") {
+            Some("csv") => vm::TraceFormat::Csv,
+            _ => vm::TraceFormat::JsonLines,
+        };
+        let tracer = fs::File::create(path)
+            .and_then(|file| vm::TraceWriter::new(io::BufWriter::new(file), format))
+            .map_err(|err| with_path(path, err))?;
+        vm.attach_tracer(tracer);
     }
+    vm.load_sections(program);
+    vm.run().map_err(|err| format!("error: {}", err))?;
+    Ok(vm.get_exit_code().unwrap_or(0) as i32)
 }
@@ -1,5 +1,6 @@
-use core::mem::transmute;
 use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use std::str::FromStr;
 
 #[derive(Clone, EnumString, Eq, Debug, PartialEq, FromPrimitive)]
 pub enum ConditionCode {
@@ -36,8 +37,46 @@ pub enum ConditionCode {
 }
 
 impl ConditionCode {
-    pub fn get_cc(addr: usize) -> ConditionCode {
-        let cc: ConditionCode = unsafe { transmute(addr as u8) };
-        cc
+    /// Get the condition code from a given encoding, if it encodes one.
+    pub fn get_cc(addr: usize) -> Option<ConditionCode> {
+        ConditionCode::from_usize(addr)
     }
+    /// Get the condition code from the suffix of a mnemonic, such as `EQ` in `ADDEQ`. The suffix
+    /// must be written in the same case as the mnemonic it is attached to.
+    pub fn from_suffix(suffix: &str) -> Option<ConditionCode> {
+        let branch = if suffix.chars().all(|c| c.is_ascii_lowercase()) {
+            "b"
+        } else {
+            "B"
+        };
+        match suffix {
+            "" => None,
+            _ => ConditionCode::from_str(&format!("{}{}", branch, suffix)).ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_get_cc() {
+        assert_eq!(ConditionCode::get_cc(0x1), Some(ConditionCode::EQ));
+    }
+
+    #[test]
+    fn test_get_cc_undefined() {
+        assert_eq!(ConditionCode::get_cc(0xF), None);
+    }
+
+    #[test]
+    fn test_from_suffix() {
+        assert_eq!(ConditionCode::from_suffix("NE"), Some(ConditionCode::NE));
+        assert_eq!(ConditionCode::from_suffix("ge"), Some(ConditionCode::GE));
+        assert_eq!(ConditionCode::from_suffix("Eq"), None);
+        assert_eq!(ConditionCode::from_suffix(""), None);
+    }
+
 }
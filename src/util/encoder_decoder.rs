@@ -1,4 +1,4 @@
-use super::{ConditionCode, Literal, Register};
+use super::{ConditionCode, Literal, Register, Shift};
 use crate::util::opcode::*;
 use crate::vm::Address;
 
@@ -10,13 +10,21 @@ pub type Offset = u8;
 
 /// Payload list the types of information encoded into an instruction.
 pub enum Payload {
-    Opcode,  /* The opcode bits define the type of operation to execute. */
-    CC,      /* The condition code. */
-    DR,      /* The address of the destination register. */
-    RX,      /* The address of the register for the first operand. */
-    RY,      /* The address of the register for the second operand. */
-    Immed16, /* The immediate 16-bit value of the second operand. */
-    Immed20, /* The immediate 20-bit value of the second operand. */
+    Opcode,          /* The opcode bits define the type of operation to execute. */
+    CC,              /* The condition code. */
+    DR,              /* The address of the destination register. */
+    RX,              /* The address of the register for the first operand. */
+    RY,              /* The address of the register for the second operand. */
+    Immed12,         /* The immediate 12-bit value of the second operand. */
+    Immed16,         /* The immediate 16-bit value of the first operand. */
+    Immed20,         /* The immediate 20-bit value of the first operand. */
+    RegisterList,    /* The set of registers transferred by a block transfer. */
+    ShiftByRegister, /* Whether register z holds the shift amount. */
+    Shift,           /* The shift applied to the last operand. */
+    Immed5,          /* The immediate 5-bit shift amount. */
+    RZ,              /* The address of the register for the third operand or shift amount. */
+    UnconditionalDR, /* The address of the destination register of a form without condition. */
+    UnconditionalRX, /* The address of the register for the first operand of such a form. */
 }
 
 impl Payload {
@@ -25,20 +33,38 @@ impl Payload {
         match self {
             /* The opcode is encoded in the two most significant bytes. */
             Payload::Opcode => (0xFF000000, 0x18),
+            /* Every form but four and five encodes the condition code in the third most significant
+             * byte. */
             Payload::CC => (0x00F00000, 0x14),
-            /* The address of the destination register is encoded in the third most significant
+            /* The address of the destination register is encoded in the fourth most significant
              * byte. */
-            Payload::DR => (0x00F00000, 0x14),
-            /* The address of the register for the first operand is encoded in the fourth most
+            Payload::DR => (0x000F0000, 0x10),
+            /* The address of the register for the first operand is encoded in the fourth least
              * significant byte. */
-            Payload::RX => (0x000F0000, 0x10),
-            /* The address of the register for the second operand is encoded in the fourth least
+            Payload::RX => (0x0000F000, 0x0C),
+            /* The address of the register for the second operand is encoded in the third least
              * significant byte. */
-            Payload::RY => (0x0000F000, 0x0C),
+            Payload::RY => (0x00000F00, 0x08),
+            /* The immediate 12-bit value is encoded in the three least significant bytes. */
+            Payload::Immed12 => (0x00000FFF, 0x00),
             /* The immediate 16-bit value is encoded in the four least significant bytes. */
             Payload::Immed16 => (0x0000FFFF, 0x00),
-            /* The immediate 16-bit value is encoded in the four least significant bytes. */
+            /* The immediate 20-bit value is encoded in the five least significant bytes. */
             Payload::Immed20 => (0x000FFFFF, 0x00),
+            /* The register list is encoded in the four least significant bytes, one bit per
+             * register. */
+            Payload::RegisterList => (0x0000FFFF, 0x00),
+            /* The shift of the last operand is encoded in the least significant byte, the most
+             * significant bit of which selects a shift by register. */
+            Payload::ShiftByRegister => (0x00000080, 0x07),
+            Payload::Shift => (0x00000060, 0x05),
+            Payload::Immed5 => (0x0000001F, 0x00),
+            Payload::RZ => (0x0000000F, 0x00),
+            /* Forms four and five encode the address of the destination register in place of the
+             * condition code, and the address of the register for the first operand in place of
+             * the destination register. */
+            Payload::UnconditionalDR => (0x00F00000, 0x14),
+            Payload::UnconditionalRX => (0x000F0000, 0x10),
         }
     }
 }
@@ -52,7 +78,6 @@ pub struct EncoderDecoder {
 impl EncoderDecoder {
     pub fn new(init: Option<u32>) -> EncoderDecoder {
         if let Some(init) = init {
-            println!("{:17}{:>8} = {:#010X} ", "Instruction:", "MMem[[PC]]", init);
             EncoderDecoder { instr: init }
         } else {
             EncoderDecoder {
@@ -85,20 +110,62 @@ impl EncoderDecoder {
         let (_, rx_offset) = Payload::RX.get_mask_and_offset();
         self.instr |= (register as Mask) << rx_offset
     }
+    /// Encode the destination register of a form without condition code.
+    pub fn set_unconditional_dr(&mut self, register: Register) {
+        let (_, dr_offset) = Payload::UnconditionalDR.get_mask_and_offset();
+        self.instr |= (register as Mask) << dr_offset
+    }
+    /// Encode operand one of a form without condition code as a register address.
+    pub fn set_unconditional_rx(&mut self, register: Register) {
+        let (_, rx_offset) = Payload::UnconditionalRX.get_mask_and_offset();
+        self.instr |= (register as Mask) << rx_offset
+    }
     /// Encode operand two as a register address.
     pub fn set_ry(&mut self, register: Register) {
         let (_, ry_offset) = Payload::RY.get_mask_and_offset();
         self.instr |= (register as Mask) << ry_offset
     }
-    /// Encode operand two as a immediate 16-bit value.
+    /// Encode operand two as a immediate 12-bit value, negative values in two's complement.
+    pub fn set_immed12(&mut self, immed12: Literal) {
+        let (immed12_mask, immed12_offset) = Payload::Immed12.get_mask_and_offset();
+        self.instr |= (immed12.get_value() << immed12_offset) & immed12_mask
+    }
+    /// Encode operand one as a immediate 16-bit value, negative values in two's complement.
     pub fn set_immed16(&mut self, immed16: Literal) {
-        let (_, immed16_offset) = Payload::Immed16.get_mask_and_offset();
-        self.instr |= immed16.get_value() << immed16_offset
+        let (immed16_mask, immed16_offset) = Payload::Immed16.get_mask_and_offset();
+        self.instr |= (immed16.get_value() << immed16_offset) & immed16_mask
     }
-    /// Encode operand one as a immediate 20-bit value.
+    /// Encode operand one as a immediate 20-bit value, negative values in two's complement.
     pub fn set_immed20(&mut self, immed20: Literal) {
-        let (_, immed20_offset) = Payload::Immed20.get_mask_and_offset();
-        self.instr |= immed20.get_value() << immed20_offset
+        let (immed20_mask, immed20_offset) = Payload::Immed20.get_mask_and_offset();
+        self.instr |= (immed20.get_value() << immed20_offset) & immed20_mask
+    }
+    /// Encode the shift applied to the last operand.
+    pub fn set_shift(&mut self, shift: Shift) {
+        let (_, shift_offset) = Payload::Shift.get_mask_and_offset();
+        self.instr |= (shift as Mask) << shift_offset
+    }
+    /// Encode the shift amount as a immediate 5-bit value.
+    pub fn set_immed5(&mut self, immed5: Literal) {
+        let (_, immed5_offset) = Payload::Immed5.get_mask_and_offset();
+        self.instr |= immed5.get_value() << immed5_offset
+    }
+    /// Encode the shift amount as held in register z.
+    pub fn set_shift_by_register(&mut self) {
+        let (by_register_mask, _) = Payload::ShiftByRegister.get_mask_and_offset();
+        self.instr |= by_register_mask
+    }
+    /// Encode operand three, or the shift amount, as a register address.
+    pub fn set_rz(&mut self, register: Register) {
+        let (_, rz_offset) = Payload::RZ.get_mask_and_offset();
+        self.instr |= (register as Mask) << rz_offset
+    }
+    /// Encode the list of registers transferred by a block transfer.
+    pub fn set_register_list(&mut self, registers: &[Register]) {
+        let (_, register_list_offset) = Payload::RegisterList.get_mask_and_offset();
+        for register in registers {
+            self.instr |= (1 << register.clone() as Mask) << register_list_offset
+        }
     }
     // TODO: Explanation.
     pub fn get_form_and_opcode(&mut self) -> Result<((Form, Opcode)), ()> {
@@ -106,8 +173,8 @@ impl EncoderDecoder {
         let bytecode = ((self.instr & opcode_mask) >> opcode_offset) as u32;
         Opcode::get_opcode(bytecode)
     }
-    // TODO: Explanation.
-    pub fn get_cc(&mut self) -> ConditionCode {
+    // Parse the condition code from an instruction, if it encodes one.
+    pub fn get_cc(&mut self) -> Option<ConditionCode> {
         let (opcode_mask, opcode_offset) = Payload::CC.get_mask_and_offset();
         let cc = ((self.instr & opcode_mask) >> opcode_offset) as usize;
         ConditionCode::get_cc(cc)
@@ -122,23 +189,65 @@ impl EncoderDecoder {
         let (rx_mask, rx_offset) = Payload::RX.get_mask_and_offset();
         ((self.instr & rx_mask) >> rx_offset) as Address
     }
+    // Parse the address of the destination register from an instruction without condition code.
+    pub fn get_unconditional_dr(&mut self) -> Address {
+        let (dr_mask, dr_offset) = Payload::UnconditionalDR.get_mask_and_offset();
+        ((self.instr & dr_mask) >> dr_offset) as Address
+    }
+    // Parse the address of register x from an instruction without condition code.
+    pub fn get_unconditional_rx(&mut self) -> Address {
+        let (rx_mask, rx_offset) = Payload::UnconditionalRX.get_mask_and_offset();
+        ((self.instr & rx_mask) >> rx_offset) as Address
+    }
     // Parse the address of register y from an instruction.
     pub fn get_ry(&mut self) -> Address {
         let (ry_mask, ry_offset) = Payload::RY.get_mask_and_offset();
         ((self.instr & ry_mask) >> ry_offset) as Address
     }
-    // Parse the immediate 20-bit value from an instruction.
+    // Parse the immediate 12-bit value from an instruction.
+    pub fn get_immed12(&mut self) -> Instruction {
+        let (immed12_mask, immed12_offset) = Payload::Immed12.get_mask_and_offset();
+        (self.instr & immed12_mask) >> immed12_offset
+    }
+    // Parse the immediate 16-bit value from an instruction.
     pub fn get_immed16(&mut self) -> Instruction {
         let (immed16_mask, immed16_offset) = Payload::Immed16.get_mask_and_offset();
-        let immed16 = (self.instr & immed16_mask) >> immed16_offset;
-        println!("{:30}{:#010X}", "Immed16: ", immed16);
-        immed16
+        (self.instr & immed16_mask) >> immed16_offset
     }
     // Parse the immediate 20-bit value from an instruction.
     pub fn get_immed20(&mut self) -> Instruction {
         let (immed20_mask, immed20_offset) = Payload::Immed20.get_mask_and_offset();
-        let immed20 = (self.instr & immed20_mask) >> immed20_offset;
-        println!("{:30}{:#010X}", "Immed20: ", immed20);
-        immed20
+        (self.instr & immed20_mask) >> immed20_offset
+    }
+    // Parse the shift applied to the last operand from an instruction.
+    pub fn get_shift(&mut self) -> Shift {
+        let (shift_mask, shift_offset) = Payload::Shift.get_mask_and_offset();
+        let shift = ((self.instr & shift_mask) >> shift_offset) as usize;
+        // Every two-bit encoding is a shift.
+        Shift::get_shift(shift).unwrap()
+    }
+    // Parse whether the shift amount is held in a register from an instruction.
+    pub fn is_shift_by_register(&mut self) -> bool {
+        let (by_register_mask, _) = Payload::ShiftByRegister.get_mask_and_offset();
+        self.instr & by_register_mask != 0
+    }
+    // Parse the immediate 5-bit shift amount from an instruction.
+    pub fn get_immed5(&mut self) -> Instruction {
+        let (immed5_mask, immed5_offset) = Payload::Immed5.get_mask_and_offset();
+        (self.instr & immed5_mask) >> immed5_offset
+    }
+    // Parse the address of register z from an instruction.
+    pub fn get_rz(&mut self) -> Address {
+        let (rz_mask, rz_offset) = Payload::RZ.get_mask_and_offset();
+        ((self.instr & rz_mask) >> rz_offset) as Address
+    }
+    // Parse the addresses of the registers transferred by a block transfer, in ascending order.
+    pub fn get_register_list(&mut self) -> Vec<Address> {
+        let (register_list_mask, register_list_offset) =
+            Payload::RegisterList.get_mask_and_offset();
+        let register_list = (self.instr & register_list_mask) >> register_list_offset;
+        (0..16)
+            .filter(|register| register_list & (1 << register) != 0)
+            .collect()
     }
 }
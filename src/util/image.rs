@@ -0,0 +1,169 @@
+use super::{Instruction, Program, Segment};
+use std::io::{Error, ErrorKind, Result};
+
+/// The number of bytes used to store an instruction in a program image.
+pub const N_BYTES_IN_INSTRUCTION: usize = 4;
+
+/// The bytes leading an image that holds the layout of its program. They cannot lead an image of
+/// bare instructions, since 0xFF is not an opcode.
+const MAGIC: [u8; 4] = [b'A', b'V', b'M', 0xFF];
+
+/// Serialize a program into an image where every instruction is stored as a little-endian word.
+pub fn to_bytes(program: &[Instruction]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(program.len() * N_BYTES_IN_INSTRUCTION);
+    for instr in program {
+        bytes.extend_from_slice(&instr.to_le_bytes());
+    }
+    bytes
+}
+
+/// Deserialize a program from an image produced by `to_bytes`.
+pub fn from_bytes(bytes: &[u8]) -> Result<Vec<Instruction>> {
+    let chunks = bytes.chunks_exact(N_BYTES_IN_INSTRUCTION);
+    if !chunks.remainder().is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "image length of {} bytes is not a multiple of {}",
+                bytes.len(),
+                N_BYTES_IN_INSTRUCTION
+            ),
+        ));
+    }
+    let mut program = Vec::with_capacity(bytes.len() / N_BYTES_IN_INSTRUCTION);
+    for chunk in chunks {
+        let mut word = [0; N_BYTES_IN_INSTRUCTION];
+        word.copy_from_slice(chunk);
+        program.push(Instruction::from_le_bytes(word));
+    }
+    Ok(program)
+}
+
+/// Serialize a program along with its layout. The image holds, as little-endian words, the magic
+/// bytes, the entry address, whether the stack top is set, the stack top (or zero), the number of
+/// sections and then every
+/// section: its address, the length of its name in bytes, its number of words, its name padded
+/// with zero bytes to a whole number of words and finally its words.
+pub fn program_to_bytes(program: &Program) -> Vec<u8> {
+    let mut words = vec![
+        Instruction::from_le_bytes(MAGIC),
+        program.entry,
+        program.stack_top.is_some() as Instruction,
+        program.stack_top.unwrap_or(0),
+        program.segments.len() as Instruction,
+    ];
+    for segment in &program.segments {
+        let mut name = segment.name.clone().into_bytes();
+        let len = name.len();
+        name.resize(len.div_ceil(N_BYTES_IN_INSTRUCTION) * N_BYTES_IN_INSTRUCTION, 0);
+        words.extend(&[
+            segment.address,
+            len as Instruction,
+            segment.words.len() as Instruction,
+        ]);
+        words.extend(from_bytes(&name).unwrap());
+        words.extend(&segment.words);
+    }
+    to_bytes(&words)
+}
+
+/// Deserialize a program from an image produced by `program_to_bytes`, or from an image of bare
+/// instructions produced by `to_bytes`, which is loaded at address zero.
+pub fn program_from_bytes(bytes: &[u8]) -> Result<Program> {
+    let words = from_bytes(bytes)?;
+    if !bytes.starts_with(&MAGIC) {
+        return Ok(Program::from(words));
+    }
+    let truncated = || Error::new(ErrorKind::InvalidData, "image is truncated");
+    let mut words = words.into_iter().skip(1);
+    let mut next = || words.next().ok_or_else(truncated);
+    let entry = next()?;
+    let stack_top = match (next()?, next()?) {
+        (0, _) => None,
+        (_, stack_top) => Some(stack_top),
+    };
+    let mut segments = Vec::new();
+    for _ in 0..next()? {
+        let address = next()?;
+        let len = next()? as usize;
+        let n_words = next()? as usize;
+        let mut name = Vec::new();
+        for _ in 0..len.div_ceil(N_BYTES_IN_INSTRUCTION) {
+            name.extend_from_slice(&next()?.to_le_bytes());
+        }
+        name.truncate(len);
+        let name = String::from_utf8(name)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "section name is not UTF-8"))?;
+        let words = (0..n_words).map(|_| next()).collect::<Result<_>>()?;
+        segments.push(Segment {
+            name,
+            address,
+            words,
+        });
+    }
+    Ok(Program {
+        segments,
+        entry,
+        stack_top,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_to_bytes() {
+        assert_eq!(to_bytes(&[0x23100002]), vec![0x02, 0x00, 0x10, 0x23]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = vec![0x23100002, 0x21110003, 0x80000000];
+        assert_eq!(from_bytes(&to_bytes(&program)).unwrap(), program);
+    }
+
+    #[test]
+    fn test_program_round_trip() {
+        let program = Program {
+            segments: vec![
+                Segment {
+                    name: String::from(".text"),
+                    address: 0x0,
+                    words: vec![0x23100002, 0x90000000],
+                },
+                Segment {
+                    name: String::from(".rodata"),
+                    address: 0x8000,
+                    words: vec![0x6968],
+                },
+            ],
+            entry: 0x4,
+            stack_top: Some(0x10000),
+        };
+        assert_eq!(
+            program_from_bytes(&program_to_bytes(&program)).unwrap(),
+            program
+        );
+    }
+
+    #[test]
+    fn test_program_from_bare_instructions() {
+        let program = program_from_bytes(&to_bytes(&[0x23100002])).unwrap();
+        assert_eq!(program, Program::from(vec![0x23100002]));
+    }
+
+    #[test]
+    fn test_program_from_bytes_truncated() {
+        let mut bytes = program_to_bytes(&Program::from(vec![0x23100002]));
+        bytes.truncate(bytes.len() - 4);
+        assert!(program_from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_from_bytes_truncated() {
+        assert!(from_bytes(&[0x02, 0x00, 0x10]).is_err());
+    }
+
+}
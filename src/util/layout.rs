@@ -0,0 +1,112 @@
+use super::Literal;
+
+use std::collections::HashMap;
+
+/// Where the sections of a program are placed in main memory and how it starts executing.
+///
+/// A layout is described by a file holding one setting per line, in which `;` starts a comment:
+///
+/// ```text
+/// section .text 0x0
+/// section .data 0x8000
+/// entry main
+/// stack 0x00100000
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Layout {
+    /// The base address of the sections placed explicitly, by name. Any other section follows the
+    /// section preceding it in the source.
+    pub bases: HashMap<String, u32>,
+    /// The symbol execution starts at. Without it, execution starts at `_start` if the program
+    /// defines it, or at the start of `.text` otherwise.
+    pub entry: Option<String>,
+    /// The initial value of the stack pointer. Without it, the processor keeps its own.
+    pub stack_top: Option<u32>,
+}
+
+/// Parse an address such as `0x8000` or `32768`.
+pub fn parse_address(text: &str) -> Result<u32, String> {
+    let mut immed = Literal::Immediate(format!("#{}", text));
+    if !immed.is_valid() || immed.is_negative() {
+        return Err(format!("invalid address `{}`", text));
+    }
+    Ok(immed.get_value())
+}
+
+impl Layout {
+    /// Parse a layout description.
+    pub fn parse(source: &str) -> Result<Layout, String> {
+        let mut layout = Layout::default();
+        for (line, buf) in (1..).zip(source.lines()) {
+            let buf = buf.split(';').next().unwrap_or("");
+            let words: Vec<&str> = buf.split_whitespace().collect();
+            let result = match words.as_slice() {
+                [] => Ok(()),
+                ["section", name, address] => layout.set_base(name, address),
+                ["entry", symbol] => {
+                    layout.entry = Some(symbol.to_string());
+                    Ok(())
+                }
+                ["stack", address] => parse_address(address).map(|address| {
+                    layout.stack_top = Some(address);
+                }),
+                _ => Err(format!(
+                    "expected `section NAME ADDRESS`, `entry SYMBOL` or `stack ADDRESS`, found `{}`",
+                    buf.trim()
+                )),
+            };
+            result.map_err(|reason| format!("line {}: {}", line, reason))?;
+        }
+        Ok(layout)
+    }
+    /// Place the section with the given name at the given address, which must be word aligned.
+    pub fn set_base(&mut self, name: &str, address: &str) -> Result<(), String> {
+        let address = parse_address(address)?;
+        if address & 0x3 != 0 {
+            return Err(format!(
+                "base address {:#X} of section `{}` is not word aligned",
+                address, name
+            ));
+        }
+        self.bases.insert(name.to_string(), address);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let source = "; layout\nsection .text 0x0\n\nsection .data 0x8000 ; data\nentry main\n\
+                      stack 4096";
+        let layout = Layout::parse(source).unwrap();
+        assert_eq!(layout.bases[".text"], 0x0);
+        assert_eq!(layout.bases[".data"], 0x8000);
+        assert_eq!(layout.entry, Some(String::from("main")));
+        assert_eq!(layout.stack_top, Some(0x1000));
+    }
+
+    #[test]
+    fn test_parse_invalid_address() {
+        let err = Layout::parse("section .text 0x0\nstack foo").unwrap_err();
+        assert_eq!(err, "line 2: invalid address `foo`");
+    }
+
+    #[test]
+    fn test_parse_unaligned_base() {
+        let err = Layout::parse("section .data 0x2").unwrap_err();
+        assert_eq!(
+            err,
+            "line 1: base address 0x2 of section `.data` is not word aligned"
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_setting() {
+        assert!(Layout::parse("origin 0x0").is_err());
+    }
+
+}
@@ -2,6 +2,9 @@
 pub enum Literal {
     #[strum(default = "true")]
     Immediate(String),
+    /// An expression such as `ARRAY_LEN*4`, whose value is known once its symbols are.
+    #[strum(disabled = "true")]
+    Expression(String),
 }
 
 impl Literal {
@@ -17,19 +20,41 @@ impl Literal {
                     immed.pop(); // Remove suffix '.
                     return immed.contains(char::is_alphabetic) && immed.len() == 1;
                 }
-                let immed = immed.trim_start_matches("0x");
-                // Ensure value is parable to u32.
-                if let Err(_) = immed.parse::<u32>() {
+                // Ensure value is parable to u32, ignoring the sign of a negative value.
+                let magnitude = immed.trim_start_matches('-');
+                let parsed = if magnitude.starts_with("0x") {
+                    u32::from_str_radix(magnitude.trim_start_matches("0x"), 16)
+                } else {
+                    magnitude.parse::<u32>()
+                };
+                if parsed.is_err() {
                     is_valid = false
                 }
                 return is_valid;
             }
+            Literal::Expression(_) => false,
         }
     }
-    /// Get the immediate value.
+    /// Check whether the immediate is an expression that remains to be evaluated.
+    pub fn is_expression(&self) -> bool {
+        matches!(self, Literal::Expression(_))
+    }
+    /// Check whether the immediate is negative.
+    pub fn is_negative(&self) -> bool {
+        match self {
+            Literal::Immediate(immed) => immed.starts_with('-'),
+            Literal::Expression(_) => false,
+        }
+    }
+    /// Get the immediate value. Negative values are given in two's complement. Panics if the
+    /// immediate is an expression, which must be evaluated first.
     pub fn get_value(self) -> u32 {
         match self {
             Literal::Immediate(immed) => {
+                if let Some(magnitude) = immed.strip_prefix('-') {
+                    let magnitude = Literal::Immediate(magnitude.to_string()).get_value();
+                    return magnitude.wrapping_neg();
+                }
                 if immed.contains("0x") {
                     // Get the value encoded as base 16.
                     let immed = immed.trim_start_matches("0x");
@@ -41,6 +66,7 @@ impl Literal {
                 }
                 return immed.parse::<u32>().unwrap();
             }
+            Literal::Expression(expr) => panic!("expression `{}` was not evaluated", expr),
         }
     }
 }
@@ -65,6 +91,11 @@ mod tests {
         assert!(Literal::Immediate(String::from("#0x1234")).is_valid())
     }
 
+    #[test]
+    fn test_is_valid_with_base_16_letters() {
+        assert!(Literal::Immediate(String::from("#0xFF")).is_valid())
+    }
+
     #[test]
     fn test_is_valid_out_of_bounds() {
         assert!(!Literal::Immediate(String::from("#0x1FFFFFFFF")).is_valid())
@@ -80,6 +111,19 @@ mod tests {
         assert_eq!(Literal::Immediate(String::from("1234")).get_value(), 1234)
     }
 
+    #[test]
+    fn test_is_valid_negative() {
+        assert!(Literal::Immediate(String::from("#-0x4")).is_valid());
+        assert!(!Literal::Immediate(String::from("#-")).is_valid());
+    }
+
+    #[test]
+    fn test_get_value_negative() {
+        let immed = Literal::Immediate(String::from("-4"));
+        assert!(immed.is_negative());
+        assert_eq!(immed.get_value(), 0xFFFFFFFC)
+    }
+
     #[test]
     fn test_get_value_with_base_16() {
         assert_eq!(
@@ -1,11 +1,18 @@
 mod cond_code;
 mod encoder_decoder;
+pub mod image;
+mod layout;
 mod literal;
 mod opcode;
+mod program;
 mod register;
+mod shift;
 
 pub use cond_code::*;
 pub use encoder_decoder::*;
+pub use layout::*;
 pub use literal::*;
 pub use opcode::*;
+pub use program::*;
 pub use register::*;
+pub use shift::*;
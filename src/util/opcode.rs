@@ -4,6 +4,7 @@ use std::slice::Iter;
 
 use self::Form::*;
 use self::Opcode::*;
+use super::Shift;
 
 #[derive(Clone, EnumString, Eq, Debug, PartialEq, FromPrimitive)]
 pub enum Opcode {
@@ -23,23 +24,117 @@ pub enum Opcode {
     MOV,
     #[strum(serialize = "MVN", serialize = "mvn")]
     MVN,
+    #[strum(serialize = "ADDS", serialize = "adds")]
+    ADDS,
+    #[strum(serialize = "ANDS", serialize = "ands")]
+    ANDS,
+    #[strum(serialize = "EORS", serialize = "eors")]
+    EORS,
+    #[strum(serialize = "MULS", serialize = "muls")]
+    MULS,
+    #[strum(serialize = "ORRS", serialize = "orrs")]
+    ORRS,
+    #[strum(serialize = "SUBS", serialize = "subs")]
+    SUBS,
+    #[strum(serialize = "MOVS", serialize = "movs")]
+    MOVS,
+    #[strum(serialize = "MVNS", serialize = "mvns")]
+    MVNS,
+    #[strum(serialize = "MLA", serialize = "mla")]
+    MLA,
+    #[strum(serialize = "MLS", serialize = "mls")]
+    MLS,
+    #[strum(serialize = "UMULL", serialize = "umull")]
+    UMULL,
+    #[strum(serialize = "SMULL", serialize = "smull")]
+    SMULL,
+    #[strum(serialize = "UMLAL", serialize = "umlal")]
+    UMLAL,
+    #[strum(serialize = "SDIV", serialize = "sdiv")]
+    SDIV,
+    #[strum(serialize = "UDIV", serialize = "udiv")]
+    UDIV,
+    #[strum(serialize = "LSL", serialize = "lsl")]
+    LSL,
+    #[strum(serialize = "LSR", serialize = "lsr")]
+    LSR,
+    #[strum(serialize = "ASR", serialize = "asr")]
+    ASR,
+    #[strum(serialize = "ROR", serialize = "ror")]
+    ROR,
+    #[strum(serialize = "LSLS", serialize = "lsls")]
+    LSLS,
+    #[strum(serialize = "LSRS", serialize = "lsrs")]
+    LSRS,
+    #[strum(serialize = "ASRS", serialize = "asrs")]
+    ASRS,
+    #[strum(serialize = "RORS", serialize = "rors")]
+    RORS,
     #[strum(serialize = "STR", serialize = "str")]
     STR,
     #[strum(serialize = "LDR", serialize = "ldr")]
     LDR,
+    #[strum(serialize = "STRB", serialize = "strb")]
+    STRB,
+    #[strum(serialize = "LDRB", serialize = "ldrb")]
+    LDRB,
+    #[strum(serialize = "STRH", serialize = "strh")]
+    STRH,
+    #[strum(serialize = "LDRH", serialize = "ldrh")]
+    LDRH,
+    #[strum(serialize = "LDRSB", serialize = "ldrsb")]
+    LDRSB,
+    #[strum(serialize = "LDRSH", serialize = "ldrsh")]
+    LDRSH,
     #[strum(serialize = "CMP", serialize = "cmp")]
     CMP,
+    #[strum(
+        serialize = "LDMIA",
+        serialize = "ldmia",
+        serialize = "LDMFD",
+        serialize = "ldmfd",
+        serialize = "LDM",
+        serialize = "ldm"
+    )]
+    LDMIA,
+    #[strum(
+        serialize = "STMDB",
+        serialize = "stmdb",
+        serialize = "STMFD",
+        serialize = "stmfd"
+    )]
+    STMDB,
+    #[strum(serialize = "POP", serialize = "pop")]
+    POP,
+    #[strum(serialize = "PUSH", serialize = "push")]
+    PUSH,
     B,
+    #[strum(serialize = "BL", serialize = "bl")]
+    BL,
+    #[strum(serialize = "BX", serialize = "bx")]
+    BX,
+    #[strum(
+        serialize = "SVC",
+        serialize = "svc",
+        serialize = "SWI",
+        serialize = "swi"
+    )]
+    SVC,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Form {
+    /// Every form but four and five encodes a condition code, so that any instruction may be
+    /// suffixed with one to only execute when the condition holds. Forms four and five have no
+    /// room for one beside their immediate, so their instructions are encoded in form sixteen and
+    /// seventeen when they execute conditionally.
+    ///
     /// A form one instruction has the following encoding scheme:
     /// OP DR, RX, RY ; DR <- [RX] OP [RY]
     ///
     /// # Examples:
-    /// ```
-    ///	ADD R5, R1, R10
+    /// ```text
+    /// ADD R5, R1, R10
     /// XOR R4, R4, R4
     /// ```
     One,
@@ -47,64 +142,429 @@ pub enum Form {
     /// OP DR, RX ; DR <- OP([RX])
     ///
     /// # Examples:
-    /// ```
+    /// ```text
     /// MOV R5, R1
     /// MVN R10, R11
     /// ```
     Two,
+    /// A form three instruction has the following encoding scheme:
+    /// OP DR ; PC <- [DR]
+    ///
+    /// # Examples:
+    /// ```text
+    /// BX LR
+    /// BXNE R4
+    /// ```
+    Three,
     /// A form four instruction has the following encoding scheme:
     /// OP DR, RX, #immed16 ; DR <- [RX] OP #immed16
     ///
+    /// The immediate offset of a load or store is signed.
+    ///
     /// # Examples:
-    /// ```
-    ///	ADD R5, R1, #10
-    ///	AND R4, R4, #0x1
+    /// ```text
+    /// ADD R5, R1, #10
+    /// AND R4, R4, #0x1
     /// ```
     Four,
     /// A form five instruction has the following encoding scheme:
     /// OP DR, #immed20 ; DR <- OP(#immed20)
     ///
     /// # Examples:
-    /// ```
-    ///	MOV R5, #0xF1234
+    /// ```text
+    /// MOV R5, #0xF1234
     /// MVN R9, #0x0
     /// ```
     Five,
-    /// TODO: Explanation.
+    /// A form six instruction has the following encoding scheme:
+    /// OP label ; PC <- label
+    ///
+    /// The label is encoded as the word index of the instruction it points to.
+    ///
+    /// # Examples:
+    /// ```text
+    /// BNE loop
+    /// BL factorial ; LR <- [PC] + 4
+    /// ```
     Six,
+    /// A form seven instruction has the following encoding scheme:
+    /// OP #immed20 ; OP(#immed20)
+    ///
+    /// # Examples:
+    /// ```text
+    /// SVC #0x0
+    /// ```
+    Seven,
+    /// A form eight instruction has the following encoding scheme:
+    /// OP DR, {reglist} ; Transfer the registers in the list to or from the memory at [DR]
+    ///
+    /// # Examples:
+    /// ```text
+    /// LDMIA R0, {R4-R7}
+    /// ```
+    Eight,
+    /// A form nine instruction has the following encoding scheme:
+    /// OP DR!, {reglist} ; As form eight, then DR <- [DR] +/- the number of registers
+    ///
+    /// # Examples:
+    /// ```text
+    /// STMDB SP!, {R4, R5, LR}
+    /// LDMIA R0!, {R1-R3}
+    /// ```
+    Nine,
+    /// A form ten instruction has the following encoding scheme:
+    /// OP {reglist} ; Transfer the registers in the list to or from the stack at [SP]
+    ///
+    /// # Examples:
+    /// ```text
+    /// PUSH {R4-R7, LR}
+    /// POP {R4-R7, PC}
+    /// ```
+    Ten,
+    /// A form eleven instruction has the following encoding scheme:
+    /// OP DR, RX, RY, SHIFT #immed5 ; DR <- [RX] OP ([RY] SHIFT #immed5)
+    /// OP DR, RX, RY, SHIFT RZ ; DR <- [RX] OP ([RY] SHIFT [RZ])
+    ///
+    /// # Examples:
+    /// ```text
+    /// ADD R0, R1, R2, LSL #2
+    /// EORS R3, R3, R4, ROR R5
+    /// LDR R0, [R1, R2, LSL #2]
+    /// ```
+    Eleven,
+    /// A form twelve instruction has the following encoding scheme:
+    /// OP DR, RX, SHIFT #immed5 ; DR <- OP([RX] SHIFT #immed5)
+    /// OP DR, RX, SHIFT RZ ; DR <- OP([RX] SHIFT [RZ])
+    ///
+    /// # Examples:
+    /// ```text
+    /// MOV R0, R1, ASR #31
+    /// CMP R2, R3, LSR R4
+    /// ```
+    Twelve,
+    /// A form thirteen instruction has the following encoding scheme:
+    /// OP DR, RX, RY, RZ ; DR <- [RZ] OP ([RX] * [RY])
+    /// OP DR, RX, RY, RZ ; DR:RX <- [RY] * [RZ], the low word in DR and the high word in RX
+    ///
+    /// # Examples:
+    /// ```text
+    /// MLA R0, R1, R2, R3
+    /// UMULL R0, R1, R2, R3
+    /// ```
+    Thirteen,
+    /// A form fourteen instruction has the following encoding scheme:
+    /// OP DR, [RX, #immed12]! ; RX <- [RX] + #immed12, then OP DR at [RX]
+    ///
+    /// The immediate offset is signed.
+    ///
+    /// # Examples:
+    /// ```text
+    /// LDR R0, [R1, #4]!
+    /// STRB R0, [R1, #-1]!
+    /// ```
+    Fourteen,
+    /// A form fifteen instruction has the following encoding scheme:
+    /// OP DR, [RX], #immed12 ; OP DR at [RX], then RX <- [RX] + #immed12
+    ///
+    /// The immediate offset is signed.
+    ///
+    /// # Examples:
+    /// ```text
+    /// LDR R0, [R1], #4
+    /// STRH R0, [R1], #-2
+    /// ```
+    Fifteen,
+    /// A form sixteen instruction is a form four instruction executing conditionally:
+    /// OPcc DR, RX, #immed12 ; DR <- [RX] OP #immed12 if cc holds
+    ///
+    /// # Examples:
+    /// ```text
+    /// ANDEQ R4, R4, #0x1
+    /// LDRNE R0, [R1, #-4]
+    /// ```
+    Sixteen,
+    /// A form seventeen instruction is a form five instruction executing conditionally:
+    /// OPcc DR, #immed16 ; DR <- OP(#immed16) if cc holds
+    ///
+    /// # Examples:
+    /// ```text
+    /// MOVNE R5, #0xF123
+    /// CMPGT R0, #0x1
+    /// ```
+    Seventeen,
 }
 
 impl Opcode {
     fn iter() -> Iter<'static, Opcode> {
-        static OPCODE: [Opcode; 12] = [ADD, SUB, MOV, AND, ORR, EOR, MVN, MUL, LDR, STR, CMP, B];
+        static OPCODE: [Opcode; 48] = [
+            ADD, SUB, MOV, AND, ORR, EOR, MVN, MUL, ADDS, SUBS, MOVS, ANDS, ORRS, EORS, MVNS, MULS,
+            MLA, MLS, UMULL, SMULL, UMLAL, SDIV, UDIV, LSL, LSR, ASR, ROR, LSLS, LSRS, ASRS, RORS,
+            LDR, STR, LDRB, STRB, LDRH, STRH, LDRSB, LDRSH, CMP, LDMIA, STMDB, POP, PUSH, B, BL,
+            BX, SVC,
+        ];
         OPCODE.into_iter()
     }
     /// Get the bytecode and form associated from a given opcode.
     pub fn get_bytecode(&self) -> HashMap<Form, u32> {
         match *self {
-            ADD => [(One, 0x1), (Four, 0x21)].iter().cloned().collect(),
-            SUB => [(One, 0x2), (Four, 0x22)].iter().cloned().collect(),
-            MOV => [(Two, 0x3), (Five, 0x23)].iter().cloned().collect(),
-            AND => [(One, 0x4), (Four, 0x24)].iter().cloned().collect(),
-            ORR => [(One, 0x5), (Four, 0x25)].iter().cloned().collect(),
-            EOR => [(One, 0x6), (Four, 0x26)].iter().cloned().collect(),
-            MVN => [(Two, 0x7), (Five, 0x27)].iter().cloned().collect(),
-            MUL => [(One, 0x8), (Four, 0x28)].iter().cloned().collect(),
-            LDR => [(Two, 0x30), (Four, 0x31), (One, 0x32), (Five, 0x33)]
+            ADD => [(One, 0x1), (Four, 0x21), (Sixteen, 0x91), (Eleven, 0xB1)]
+                .iter()
+                .cloned()
+                .collect(),
+            SUB => [(One, 0x2), (Four, 0x22), (Sixteen, 0x92), (Eleven, 0xB2)]
+                .iter()
+                .cloned()
+                .collect(),
+            MOV => [(Two, 0x3), (Five, 0x23), (Seventeen, 0x93), (Twelve, 0xB3)]
+                .iter()
+                .cloned()
+                .collect(),
+            AND => [(One, 0x4), (Four, 0x24), (Sixteen, 0x94), (Eleven, 0xB4)]
+                .iter()
+                .cloned()
+                .collect(),
+            ORR => [(One, 0x5), (Four, 0x25), (Sixteen, 0x95), (Eleven, 0xB5)]
+                .iter()
+                .cloned()
+                .collect(),
+            EOR => [(One, 0x6), (Four, 0x26), (Sixteen, 0x96), (Eleven, 0xB6)]
+                .iter()
+                .cloned()
+                .collect(),
+            MVN => [(Two, 0x7), (Five, 0x27), (Seventeen, 0x97), (Twelve, 0xB7)]
+                .iter()
+                .cloned()
+                .collect(),
+            MUL => [(One, 0x8), (Four, 0x28), (Sixteen, 0x98)]
+                .iter()
+                .cloned()
+                .collect(),
+            MLA => [(Thirteen, 0xE0)].iter().cloned().collect(),
+            MLS => [(Thirteen, 0xE1)].iter().cloned().collect(),
+            UMULL => [(Thirteen, 0xE2)].iter().cloned().collect(),
+            SMULL => [(Thirteen, 0xE3)].iter().cloned().collect(),
+            UMLAL => [(Thirteen, 0xE4)].iter().cloned().collect(),
+            SDIV => [(One, 0xD)].iter().cloned().collect(),
+            UDIV => [(One, 0xE)].iter().cloned().collect(),
+            LSL => [(One, 0x9), (Four, 0x29), (Sixteen, 0x99)]
+                .iter()
+                .cloned()
+                .collect(),
+            LSR => [(One, 0xA), (Four, 0x2A), (Sixteen, 0x9A)]
+                .iter()
+                .cloned()
+                .collect(),
+            ASR => [(One, 0xB), (Four, 0x2B), (Sixteen, 0x9B)]
                 .iter()
                 .cloned()
                 .collect(),
-            STR => [(Two, 0x34), (Four, 0x35), (One, 0x36), (Five, 0x37)]
+            ROR => [(One, 0xC), (Four, 0x2C), (Sixteen, 0x9C)]
                 .iter()
                 .cloned()
                 .collect(),
-            CMP => [(Two, 0x47), (Five, 0x57)].iter().cloned().collect(),
+            ADDS => [(One, 0x11), (Four, 0x61), (Sixteen, 0xC8), (Eleven, 0xC1)]
+                .iter()
+                .cloned()
+                .collect(),
+            SUBS => [(One, 0x12), (Four, 0x62), (Sixteen, 0xC9), (Eleven, 0xC2)]
+                .iter()
+                .cloned()
+                .collect(),
+            MOVS => [(Two, 0x13), (Five, 0x63), (Seventeen, 0xCA), (Twelve, 0xC3)]
+                .iter()
+                .cloned()
+                .collect(),
+            ANDS => [(One, 0x14), (Four, 0x64), (Sixteen, 0xCB), (Eleven, 0xC4)]
+                .iter()
+                .cloned()
+                .collect(),
+            ORRS => [(One, 0x15), (Four, 0x65), (Sixteen, 0xCC), (Eleven, 0xC5)]
+                .iter()
+                .cloned()
+                .collect(),
+            EORS => [(One, 0x16), (Four, 0x66), (Sixteen, 0xCD), (Eleven, 0xC6)]
+                .iter()
+                .cloned()
+                .collect(),
+            MVNS => [(Two, 0x17), (Five, 0x67), (Seventeen, 0xCE), (Twelve, 0xC7)]
+                .iter()
+                .cloned()
+                .collect(),
+            MULS => [(One, 0x18), (Four, 0x68), (Sixteen, 0xCF)]
+                .iter()
+                .cloned()
+                .collect(),
+            LSLS => [(One, 0x19), (Four, 0x69), (Sixteen, 0xD0)]
+                .iter()
+                .cloned()
+                .collect(),
+            LSRS => [(One, 0x1A), (Four, 0x6A), (Sixteen, 0xD1)]
+                .iter()
+                .cloned()
+                .collect(),
+            ASRS => [(One, 0x1B), (Four, 0x6B), (Sixteen, 0xD2)]
+                .iter()
+                .cloned()
+                .collect(),
+            RORS => [(One, 0x1C), (Four, 0x6C), (Sixteen, 0xD3)]
+                .iter()
+                .cloned()
+                .collect(),
+            LDR => [
+                (Two, 0x30),
+                (Four, 0x31),
+                (Sixteen, 0xB8),
+                (One, 0x32),
+                (Five, 0x33),
+                (Seventeen, 0xE8),
+                (Fourteen, 0x70),
+                (Fifteen, 0x78),
+                (Eleven, 0xF0),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            STR => [
+                (Two, 0x34),
+                (Four, 0x35),
+                (Sixteen, 0xB9),
+                (One, 0x36),
+                (Five, 0x37),
+                (Seventeen, 0xE9),
+                (Fourteen, 0x71),
+                (Fifteen, 0x79),
+                (Eleven, 0xF1),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            LDRB => [
+                (Two, 0x38),
+                (Four, 0x39),
+                (Sixteen, 0xBA),
+                (One, 0x3A),
+                (Five, 0x3B),
+                (Seventeen, 0xEA),
+                (Fourteen, 0x72),
+                (Fifteen, 0x7A),
+                (Eleven, 0xF2),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            STRB => [
+                (Two, 0x3C),
+                (Four, 0x3D),
+                (Sixteen, 0xBB),
+                (One, 0x3E),
+                (Five, 0x3F),
+                (Seventeen, 0xEB),
+                (Fourteen, 0x73),
+                (Fifteen, 0x7B),
+                (Eleven, 0xF3),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            LDRH => [
+                (Two, 0x40),
+                (Four, 0x41),
+                (Sixteen, 0xBC),
+                (One, 0x42),
+                (Five, 0x43),
+                (Seventeen, 0xEC),
+                (Fourteen, 0x74),
+                (Fifteen, 0x7C),
+                (Eleven, 0xF4),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            STRH => [
+                (Two, 0x48),
+                (Four, 0x49),
+                (Sixteen, 0xBD),
+                (One, 0x4A),
+                (Five, 0x4B),
+                (Seventeen, 0xED),
+                (Fourteen, 0x75),
+                (Fifteen, 0x7D),
+                (Eleven, 0xF5),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            LDRSB => [
+                (Two, 0x4C),
+                (Four, 0x4D),
+                (Sixteen, 0xBE),
+                (One, 0x4E),
+                (Five, 0x4F),
+                (Seventeen, 0xEE),
+                (Fourteen, 0x76),
+                (Fifteen, 0x7E),
+                (Eleven, 0xF6),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            LDRSH => [
+                (Two, 0x50),
+                (Four, 0x51),
+                (Sixteen, 0xBF),
+                (One, 0x52),
+                (Five, 0x53),
+                (Seventeen, 0xEF),
+                (Fourteen, 0x77),
+                (Fifteen, 0x7F),
+                (Eleven, 0xF7),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+            CMP => [(Two, 0x47), (Five, 0x57), (Seventeen, 0xDF), (Twelve, 0xD7)]
+                .iter()
+                .cloned()
+                .collect(),
+            LDMIA => [(Eight, 0xA0), (Nine, 0xA1)].iter().cloned().collect(),
+            STMDB => [(Eight, 0xA2), (Nine, 0xA3)].iter().cloned().collect(),
+            POP => [(Ten, 0xA4)].iter().cloned().collect(),
+            PUSH => [(Ten, 0xA5)].iter().cloned().collect(),
             B => [(Six, 0x80)].iter().cloned().collect(),
+            BL => [(Six, 0x81)].iter().cloned().collect(),
+            BX => [(Three, 0x82)].iter().cloned().collect(),
+            SVC => [(Seven, 0x90)].iter().cloned().collect(),
         }
     }
-    /// Get the forms associated with a given opcode.
+    /// Check whether the opcode updates the condition flags from its result.
+    pub fn sets_flags(&self) -> bool {
+        matches!(
+            *self,
+            ADDS | SUBS | MOVS | ANDS | ORRS | EORS | MVNS | MULS | LSLS | LSRS | ASRS | RORS | CMP
+        )
+    }
+    /// Check whether the opcode loads a single register from, or stores it to, main memory.
+    pub fn is_load_or_store(&self) -> bool {
+        matches!(*self, LDR | STR | LDRB | STRB | LDRH | STRH | LDRSB | LDRSH)
+    }
+    /// Get the shift applied by a shift instruction.
+    pub fn get_shift(&self) -> Option<Shift> {
+        match *self {
+            LSL | LSLS => Some(Shift::LSL),
+            LSR | LSRS => Some(Shift::LSR),
+            ASR | ASRS => Some(Shift::ASR),
+            ROR | RORS => Some(Shift::ROR),
+            _ => None,
+        }
+    }
+    /// Get the forms associated with a given opcode. Forms sixteen and seventeen are left out, as
+    /// they are only told apart from forms four and five by the condition code.
     pub fn get_forms(&self) -> Vec<Form> {
-        self.get_bytecode().keys().map(|key| key.clone()).collect()
+        self.get_bytecode()
+            .keys()
+            .filter(|key| !matches!(key, Sixteen | Seventeen))
+            .map(|key| key.clone())
+            .collect()
     }
     /// Get the opcode from a given bytecode.
     pub fn get_opcode(bytecode: u32) -> Result<(Form, Opcode), ()> {
@@ -120,17 +580,36 @@ impl Opcode {
 }
 
 impl Form {
+    /// Get the form encoding an instruction of this form when it executes conditionally.
+    pub fn get_conditional(&self) -> Form {
+        match *self {
+            Four => Sixteen,
+            Five => Seventeen,
+            form => form,
+        }
+    }
+    /// Check whether the form encodes a condition code.
+    pub fn has_cc(&self) -> bool {
+        !matches!(*self, Four | Five)
+    }
     /// Get the expression length associated with a given form and opcode.
     fn get_expr_length(&self, opcode: &Opcode) -> usize {
         let mut delta = 0;
-        match *opcode {
-            Opcode::STR | Opcode::LDR => delta = 2,
-            _ => (),
+        // The address of a load or store is enclosed in brackets.
+        if opcode.is_load_or_store() {
+            delta = 2;
         }
         match *self {
-            One | Four => 6 + delta,
-            Two | Five => 4 + delta,
-            Six => 3,
+            One | Four | Sixteen => 6 + delta,
+            Two | Five | Seventeen => 4 + delta,
+            Three | Six | Seven | Ten => 2,
+            Eight => 4,
+            Nine => 5,
+            Eleven => 9 + delta,
+            Twelve => 7,
+            Thirteen => 8,
+            Fourteen => 7 + delta,
+            Fifteen => 6 + delta,
         }
     }
 }
@@ -155,13 +634,54 @@ mod tests_opcode {
     fn test_get_bytecode() {
         assert_eq!(
             ADD.get_bytecode(),
-            [(One, 0x1), (Four, 0x21)].iter().cloned().collect()
+            [(One, 0x1), (Four, 0x21), (Sixteen, 0x91), (Eleven, 0xB1)]
+                .iter()
+                .cloned()
+                .collect()
         );
     }
 
     #[test]
     fn test_get_form() {
         assert!(ADD.get_forms().contains(&One) && ADD.get_forms().contains(&Four));
+        assert!(!ADD.get_forms().contains(&Sixteen));
+    }
+
+    #[test]
+    fn test_get_conditional() {
+        assert_eq!(Four.get_conditional(), Sixteen);
+        assert_eq!(Five.get_conditional(), Seventeen);
+        assert_eq!(One.get_conditional(), One);
+        assert!(!Four.has_cc() && Sixteen.has_cc());
+    }
+
+    #[test]
+    fn test_get_opcode_is_unique() {
+        let mut bytecodes: Vec<u32> = Opcode::iter()
+            .flat_map(|opcode| {
+                opcode
+                    .get_bytecode()
+                    .values()
+                    .cloned()
+                    .collect::<Vec<u32>>()
+            })
+            .collect();
+        let len = bytecodes.len();
+        bytecodes.sort();
+        bytecodes.dedup();
+        assert_eq!(bytecodes.len(), len);
+    }
+
+    #[test]
+    fn test_sets_flags() {
+        assert!(ADDS.sets_flags() && CMP.sets_flags());
+        assert!(!ADD.sets_flags() && !MOV.sets_flags());
+    }
+
+    #[test]
+    fn test_get_shift() {
+        assert_eq!(ASRS.get_shift(), Some(Shift::ASR));
+        assert_eq!(ADD.get_shift(), None);
     }
 
     #[test]
@@ -232,7 +752,36 @@ mod tests_form {
 
     #[test]
     fn test_form_six() {
-        assert_eq!(Six.get_expr_length(&B), 3);
+        assert_eq!(Six.get_expr_length(&B), 2);
+        assert_eq!(Six.get_expr_length(&BL), 2);
+    }
+
+    #[test]
+    fn test_block_transfer_forms() {
+        assert_eq!(Eight.get_expr_length(&LDMIA), 4);
+        assert_eq!(Nine.get_expr_length(&STMDB), 5);
+        assert_eq!(Ten.get_expr_length(&PUSH), 2);
+    }
+
+    #[test]
+    fn test_shifted_operand_forms() {
+        assert_eq!(Eleven.get_expr_length(&ADD), 9);
+        assert_eq!(Twelve.get_expr_length(&MOV), 7);
+    }
+
+    #[test]
+    fn test_form_thirteen() {
+        assert_eq!(Thirteen.get_expr_length(&UMLAL), 8);
+    }
+
+    #[test]
+    fn test_form_three() {
+        assert_eq!(Three.get_expr_length(&BX), 2);
+    }
+
+    #[test]
+    fn test_form_seven() {
+        assert_eq!(Seven.get_expr_length(&SVC), 2);
     }
 
 }
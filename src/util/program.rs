@@ -0,0 +1,54 @@
+use super::Instruction;
+
+/// The name of the section holding the instructions of a program.
+pub const TEXT_SECTION: &str = ".text";
+
+/// A section of a program placed at a fixed address of main memory.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Segment {
+    /// The name of the section, such as `.text`.
+    pub name: String,
+    /// The byte address the section is loaded at.
+    pub address: u32,
+    /// The contents of the section, as little-endian words.
+    pub words: Vec<Instruction>,
+}
+
+/// A program along with where to load it into main memory and where to start executing it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Program {
+    pub segments: Vec<Segment>,
+    /// The address of the first instruction to execute.
+    pub entry: u32,
+    /// The initial value of the stack pointer, if the program sets it.
+    pub stack_top: Option<u32>,
+}
+
+impl Program {
+    /// Get the section of the program with the given name.
+    pub fn segment(&self, name: &str) -> Option<&Segment> {
+        self.segments.iter().find(|segment| segment.name == name)
+    }
+    /// Get the words of the `.text` section, which is empty if the program has no instructions.
+    pub fn text(&self) -> Vec<Instruction> {
+        match self.segment(TEXT_SECTION) {
+            Some(segment) => segment.words.clone(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// A program made of the given instructions, loaded at address zero and executed from its start.
+impl From<Vec<Instruction>> for Program {
+    fn from(words: Vec<Instruction>) -> Program {
+        Program {
+            segments: vec![Segment {
+                name: String::from(TEXT_SECTION),
+                address: 0,
+                words,
+            }],
+            entry: 0,
+            stack_top: None,
+        }
+    }
+}
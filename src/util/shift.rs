@@ -0,0 +1,40 @@
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+
+/// The shifts applied by the barrel shifter, either by a shift instruction or to the last operand
+/// of a data-processing instruction.
+#[derive(Clone, Copy, EnumString, Eq, Debug, PartialEq, FromPrimitive)]
+pub enum Shift {
+    /// Logical shift left, filling with zeroes.
+    #[strum(serialize = "LSL", serialize = "lsl")]
+    LSL = 0x0,
+    /// Logical shift right, filling with zeroes.
+    #[strum(serialize = "LSR", serialize = "lsr")]
+    LSR,
+    /// Arithmetic shift right, filling with the sign bit.
+    #[strum(serialize = "ASR", serialize = "asr")]
+    ASR,
+    /// Rotate right.
+    #[strum(serialize = "ROR", serialize = "ror")]
+    ROR,
+}
+
+impl Shift {
+    /// Get the shift from a given encoding, if it encodes one.
+    pub fn get_shift(addr: usize) -> Option<Shift> {
+        Shift::from_usize(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_get_shift() {
+        assert_eq!(Shift::get_shift(0x2), Some(Shift::ASR));
+        assert_eq!(Shift::get_shift(0x4), None);
+    }
+
+}
@@ -0,0 +1,67 @@
+use std::fmt;
+
+use super::Address;
+use crate::util::Instruction;
+
+/// A fault raised by the processor while executing an instruction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Fault {
+    /// The instruction does not encode a known opcode, form or condition code.
+    UndefinedInstruction,
+    /// An arithmetic operation overflowed while overflow trapping is enabled.
+    ArithmeticOverflow,
+    /// A division by zero occurred while division by zero trapping is enabled.
+    DivideByZero,
+    /// The effective address of a memory access does not fit in the address space.
+    MemoryOutOfRange,
+    /// A halfword or word was accessed at an address that is not a multiple of its size while
+    /// alignment checking is enabled, or an instruction was fetched from such an address.
+    UnalignedAccess,
+    /// The stack grew below the configured stack limit.
+    StackOverflow,
+    /// The processor has halted and cannot execute further instructions.
+    Halted,
+    /// The processor has executed the maximum number of instructions it is allowed to.
+    StepLimitExceeded,
+    /// No service is provided under the number of a supervisor call.
+    UndefinedSyscall,
+    /// The host failed to carry out a supervisor call.
+    SyscallFailed,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::UndefinedInstruction => write!(f, "undefined instruction"),
+            Fault::ArithmeticOverflow => write!(f, "arithmetic overflow"),
+            Fault::DivideByZero => write!(f, "division by zero"),
+            Fault::MemoryOutOfRange => write!(f, "memory access out of range"),
+            Fault::UnalignedAccess => write!(f, "unaligned memory access"),
+            Fault::StackOverflow => write!(f, "stack overflow"),
+            Fault::Halted => write!(f, "processor is halted"),
+            Fault::StepLimitExceeded => write!(f, "step limit exceeded"),
+            Fault::UndefinedSyscall => write!(f, "undefined supervisor call"),
+            Fault::SyscallFailed => write!(f, "supervisor call failed"),
+        }
+    }
+}
+
+/// A fault along with the instruction that raised it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VmError {
+    pub fault: Fault,
+    /// The address of the faulting instruction.
+    pub pc: Address,
+    /// The faulting instruction.
+    pub instr: Instruction,
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at PC {:#010X} (instruction {:#010X})",
+            self.fault, self.pc, self.instr
+        )
+    }
+}
@@ -1,3 +1,4 @@
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Flag {
     v: bool, // oVerflow.
     z: bool, // Zero.
@@ -14,42 +15,42 @@ impl Flag {
             c: false,
         }
     }
-    pub fn update(&mut self, op1: u32, op2: u32) {
-        // Update carry flag.
-        if let Some(result) = op1.checked_sub(op2) {
-            self.c = false;
-            // Update zero flag.
-            if result == 0 {
-                self.z = true;
-            } else {
-                self.z = false;
-            }
-            // Always positive under the unsigned interpretation.
-            self.n = false;
-        } else {
-            self.c = true;
-        }
-        let op1 = op1 as i32;
-        let op2 = op2 as i32;
-        // Check overflow flag.
-        if let Some(result) = op1.checked_sub(op2) {
-            self.v = false;
-            // Update zero flag.
-            if result == 0 {
-                self.z = true;
-            } else {
-                self.z = false;
-            }
-            // Update negative flag.
-            if result < 0 {
-                self.n = true;
-            } else {
-                self.n = false;
-            }
-        } else {
-            self.v = true;
+    /// Update the negative and zero flags from the result of an operation.
+    fn set_nz(&mut self, result: u32) {
+        self.n = (result as i32) < 0;
+        self.z = result == 0;
+    }
+    /// Update the flags from the addition op1 + op2, as computed by ADDS. The carry flag is set
+    /// when the unsigned addition carries out, and the overflow flag when the signed addition
+    /// overflows.
+    pub fn set_add(&mut self, op1: u32, op2: u32) {
+        let (result, carry) = op1.overflowing_add(op2);
+        self.set_nz(result);
+        self.c = carry;
+        self.v = (op1 as i32).checked_add(op2 as i32).is_none();
+    }
+    /// Update the flags from the subtraction op1 - op2, as computed by CMP and SUBS. As on ARM,
+    /// the carry flag is set when the unsigned subtraction does not borrow.
+    pub fn set_sub(&mut self, op1: u32, op2: u32) {
+        let (result, borrow) = op1.overflowing_sub(op2);
+        self.set_nz(result);
+        self.c = !borrow;
+        self.v = (op1 as i32).checked_sub(op2 as i32).is_none();
+    }
+    /// Update the flags from the result of a logical operation, as computed by ANDS or MOVS. The
+    /// carry flag is set to the carry out of the shifter, if it produced one, and the overflow
+    /// flag is left untouched.
+    pub fn set_logical(&mut self, result: u32, carry: Option<bool>) {
+        self.set_nz(result);
+        if let Some(carry) = carry {
+            self.c = carry;
         }
     }
+    /// Update the flags from the result of a multiplication, as computed by MULS. The carry and
+    /// overflow flags are left untouched.
+    pub fn set_mul(&mut self, result: u32) {
+        self.set_nz(result);
+    }
     pub fn get_z(&self) -> bool {
         self.z
     }
@@ -63,3 +64,103 @@ impl Flag {
         self.c
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// Pack the flags into the bits 0bNZCV.
+    fn nzcv(flag: &Flag) -> u8 {
+        (flag.n as u8) << 3 | (flag.z as u8) << 2 | (flag.c as u8) << 1 | flag.v as u8
+    }
+
+    /// Unpack the flags from the bits 0bNZCV.
+    fn from_nzcv(bits: u8) -> Flag {
+        Flag {
+            n: bits & 0b1000 != 0,
+            z: bits & 0b0100 != 0,
+            c: bits & 0b0010 != 0,
+            v: bits & 0b0001 != 0,
+        }
+    }
+
+    #[test]
+    fn test_set_sub() {
+        // Reference results of CMP op1, op2 on ARM.
+        let cases = [
+            (0x00000000, 0x00000000, 0b0110),
+            (0x00000001, 0x00000002, 0b1000),
+            (0x00000002, 0x00000001, 0b0010),
+            (0x00000003, 0x00000003, 0b0110),
+            (0x80000000, 0x00000001, 0b0011),
+            (0x7FFFFFFF, 0xFFFFFFFF, 0b1001),
+            (0x00000000, 0x80000000, 0b1001),
+            (0xFFFFFFFF, 0xFFFFFFFF, 0b0110),
+            (0xFFFFFFFF, 0x00000001, 0b1010),
+            (0x00000001, 0xFFFFFFFF, 0b0000),
+            (0x80000000, 0x80000000, 0b0110),
+            (0x7FFFFFFF, 0x80000000, 0b1001),
+        ];
+        for &(op1, op2, expected) in cases.iter() {
+            let mut flag = Flag::new();
+            flag.set_sub(op1, op2);
+            assert_eq!(nzcv(&flag), expected, "{:#X} - {:#X}", op1, op2);
+        }
+    }
+
+    #[test]
+    fn test_set_add() {
+        // Reference results of ADDS rd, op1, op2 on ARM.
+        let cases = [
+            (0x00000000, 0x00000000, 0b0100),
+            (0x00000001, 0x00000001, 0b0000),
+            (0xFFFFFFFF, 0x00000001, 0b0110),
+            (0x7FFFFFFF, 0x00000001, 0b1001),
+            (0x80000000, 0x80000000, 0b0111),
+            (0xFFFFFFFF, 0xFFFFFFFF, 0b1010),
+            (0x80000000, 0xFFFFFFFF, 0b0011),
+            (0x7FFFFFFF, 0x7FFFFFFF, 0b1001),
+            (0x00000001, 0xFFFFFFFE, 0b1000),
+        ];
+        for &(op1, op2, expected) in cases.iter() {
+            let mut flag = Flag::new();
+            flag.set_add(op1, op2);
+            assert_eq!(nzcv(&flag), expected, "{:#X} + {:#X}", op1, op2);
+        }
+    }
+
+    #[test]
+    fn test_set_logical() {
+        // The overflow flag is preserved, and so is the carry flag unless the shifter sets it.
+        let cases = [
+            (0b0000, 0x00000000, None, 0b0100),
+            (0b0011, 0x00000000, None, 0b0111),
+            (0b0011, 0x80000000, None, 0b1011),
+            (0b0011, 0x00000001, Some(false), 0b0001),
+            (0b0000, 0x80000000, Some(true), 0b1010),
+            (0b1100, 0x00000001, None, 0b0000),
+        ];
+        for &(before, result, carry, expected) in cases.iter() {
+            let mut flag = from_nzcv(before);
+            flag.set_logical(result, carry);
+            assert_eq!(nzcv(&flag), expected, "{:#X}", result);
+        }
+    }
+
+    #[test]
+    fn test_set_mul() {
+        // Only the negative and zero flags are updated.
+        let cases = [
+            (0b0000, 0x00000000, 0b0100),
+            (0b0011, 0x00000006, 0b0011),
+            (0b0111, 0xFFFFFFFA, 0b1011),
+        ];
+        for &(before, result, expected) in cases.iter() {
+            let mut flag = from_nzcv(before);
+            flag.set_mul(result);
+            assert_eq!(nzcv(&flag), expected, "{:#X}", result);
+        }
+    }
+
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use super::{Address, Payload};
+
+/// The number of addressable bytes in main memory. A 32-bit processor has 2^32 addressable
+/// memory locations, which only a 64-bit integer can count on every target.
+pub const N_BYTES_IN_MAIN_MEMORY: u64 = 1 << 32;
+
+/// The number of bytes held by a single page of main memory.
+const N_BYTES_IN_PAGE: Address = 4096;
+
+type Page = Box<[u8; N_BYTES_IN_PAGE]>;
+
+/// The order in which the bytes of a halfword or word are laid out in main memory.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Endianness {
+    /// The least significant byte is stored at the lowest address.
+    Little,
+    /// The most significant byte is stored at the lowest address.
+    Big,
+}
+
+/// The amount of data transferred by a single memory access.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Width {
+    Byte = 1,
+    Halfword = 2,
+    Word = 4,
+}
+
+impl Width {
+    /// Get the number of bytes spanned by an access of this width.
+    pub fn n_bytes(self) -> Address {
+        self as Address
+    }
+}
+
+/// Main memory is sparse and byte-addressed: the address space is split into pages which are only
+/// allocated the first time they are written to. Reading from a page that has never been written
+/// to yields zero, and accesses running past the end of main memory wrap around to its start.
+pub struct Memory {
+    pages: HashMap<Address, Page>,
+    endianness: Endianness,
+}
+
+impl Default for Memory {
+    fn default() -> Memory {
+        Memory::with_endianness(Endianness::Little)
+    }
+}
+
+impl Memory {
+    /// Instantiate an empty little-endian main memory.
+    pub fn new() -> Memory {
+        Memory::default()
+    }
+    /// Instantiate an empty main memory laying out halfwords and words in the given byte order.
+    pub fn with_endianness(endianness: Endianness) -> Memory {
+        Memory {
+            pages: HashMap::new(),
+            endianness,
+        }
+    }
+    /// Split an address into the number of its page and its offset within that page.
+    fn locate(address: Address) -> (Address, Address) {
+        debug_assert!((address as u64) < N_BYTES_IN_MAIN_MEMORY);
+        (address / N_BYTES_IN_PAGE, address % N_BYTES_IN_PAGE)
+    }
+    /// Get the number of bits the byte at the given position of an access is shifted by within
+    /// the data transferred.
+    fn byte_shift(&self, width: Width, position: Address) -> Address {
+        match self.endianness {
+            Endianness::Little => 8 * position,
+            Endianness::Big => 8 * (width.n_bytes() - 1 - position),
+        }
+    }
+    /// Read the byte stored at the given address.
+    fn read_byte(&self, address: Address) -> u8 {
+        let (page, offset) = Memory::locate(address);
+        match self.pages.get(&page) {
+            Some(page) => page[offset],
+            None => 0,
+        }
+    }
+    /// Write a byte to the given address, allocating its page if needed.
+    fn write_byte(&mut self, address: Address, byte: u8) {
+        let (page, offset) = Memory::locate(address);
+        let page = self
+            .pages
+            .entry(page)
+            .or_insert_with(|| Box::new([0; N_BYTES_IN_PAGE]));
+        page[offset] = byte
+    }
+    /// Read the data of the given width stored from the given address, zero extended to a word.
+    pub fn read(&self, address: Address, width: Width) -> Payload {
+        (0..width.n_bytes()).fold(0, |payload, position| {
+            let address = (address as u64 + position as u64) % N_BYTES_IN_MAIN_MEMORY;
+            let byte = self.read_byte(address as Address) as Payload;
+            payload | byte << self.byte_shift(width, position)
+        })
+    }
+    /// Write the least significant bytes of the payload fitting in the given width from the given
+    /// address.
+    pub fn write(&mut self, address: Address, width: Width, payload: Payload) {
+        for position in 0..width.n_bytes() {
+            let byte = (payload >> self.byte_shift(width, position)) as u8;
+            let address = (address as u64 + position as u64) % N_BYTES_IN_MAIN_MEMORY;
+            self.write_byte(address as Address, byte);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_read_untouched_is_zero() {
+        let memory = Memory::new();
+        assert_eq!(memory.read(0xFFFFFFFC, Width::Word), 0x0);
+    }
+
+    #[test]
+    fn test_read_does_not_allocate() {
+        let memory = Memory::new();
+        memory.read(0x1234, Width::Word);
+        assert_eq!(memory.pages.len(), 0);
+    }
+
+    #[test]
+    fn test_write_then_read() {
+        let mut memory = Memory::new();
+        memory.write(0x1234, Width::Word, 0xDEADBEEF);
+        assert_eq!(memory.read(0x1234, Width::Word), 0xDEADBEEF);
+        assert_eq!(memory.read(0x1238, Width::Word), 0x0);
+    }
+
+    #[test]
+    fn test_little_endian() {
+        let mut memory = Memory::new();
+        memory.write(0x100, Width::Word, 0x11223344);
+        assert_eq!(memory.read(0x100, Width::Byte), 0x44);
+        assert_eq!(memory.read(0x102, Width::Halfword), 0x1122);
+    }
+
+    #[test]
+    fn test_big_endian() {
+        let mut memory = Memory::with_endianness(Endianness::Big);
+        memory.write(0x100, Width::Word, 0x11223344);
+        assert_eq!(memory.read(0x100, Width::Byte), 0x11);
+        assert_eq!(memory.read(0x102, Width::Halfword), 0x3344);
+    }
+
+    #[test]
+    fn test_write_truncates_to_width() {
+        let mut memory = Memory::new();
+        memory.write(0x100, Width::Byte, 0x1234);
+        assert_eq!(memory.read(0x100, Width::Word), 0x34);
+    }
+
+    #[test]
+    fn test_write_allocates_one_page() {
+        let mut memory = Memory::new();
+        memory.write(0x0, Width::Byte, 0x1);
+        memory.write(N_BYTES_IN_PAGE - 4, Width::Word, 0x2);
+        assert_eq!(memory.pages.len(), 1);
+        memory.write(N_BYTES_IN_PAGE - 2, Width::Word, 0x3);
+        assert_eq!(memory.pages.len(), 2);
+    }
+
+    #[test]
+    fn test_write_highest_address() {
+        let mut memory = Memory::new();
+        let address = (N_BYTES_IN_MAIN_MEMORY - 4) as Address;
+        memory.write(address, Width::Word, 0x1234);
+        assert_eq!(memory.read(address, Width::Word), 0x1234);
+    }
+
+}
@@ -1,294 +1,1086 @@
+mod fault;
 mod flag;
+mod memory;
+mod shifter;
+mod syscall;
+mod trace_writer;
+mod tracer;
 
 use super::util::{
-    get_name, ConditionCode::*, EncoderDecoder, Form, Instruction, Opcode, Register,
+    ConditionCode::{self, *},
+    EncoderDecoder, Form, Instruction, Opcode, Program, Register, Shift,
 };
-use flag::*;
+pub use fault::*;
+pub use flag::*;
+pub use memory::*;
+pub use shifter::*;
+pub use syscall::*;
+pub use trace_writer::*;
+pub use tracer::*;
+
+use std::ops::Range;
 
 /// The initial value of all registers in the processor.
 pub const INIT_REGISTER_VALUE: Payload = 0;
 
+/// The default initial value of the stack pointer. The stack is full descending, so it grows down
+/// from this address, away from the program loaded at address zero.
+pub const INIT_STACK_POINTER: Payload = 0x00100000;
+
 /// The number of addressable registers in this processor. A 32-bit ARM processor has 16
 /// registers.
 const N_REGISTERS_IN_PROCESSOR: Address = 16;
 
-/// The number of addressable registers in main memory. A 32-bit processor has 2^32 addressable
-/// memory locations.
-const N_REGISTERS_IN_MAIN_MEMORY: Address = std::u32::MAX as usize;
+/// The number of bytes occupied by an instruction in main memory.
+const N_BYTES_IN_INSTRUCTION: Payload = 4;
+
+/// Options controlling how the processor reacts to exceptional conditions.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Raise a fault when an arithmetic operation overflows under the signed interpretation
+    /// instead of wrapping around.
+    pub trap_on_overflow: bool,
+    /// Raise a fault when dividing by zero instead of producing zero, as ARM does by default.
+    pub trap_on_divide_by_zero: bool,
+    /// The maximum number of instructions the processor may execute.
+    pub step_limit: Option<u64>,
+    /// The initial value of the stack pointer.
+    pub stack_pointer: Payload,
+    /// The lowest address the stack may grow down to. Pushing below it raises a fault.
+    pub stack_limit: Option<Payload>,
+    /// The order in which the bytes of halfwords and words are laid out in main memory.
+    pub endianness: Endianness,
+    /// Raise a fault when a halfword or word is accessed at an address that is not a multiple of
+    /// its size.
+    pub check_alignment: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            trap_on_overflow: false,
+            trap_on_divide_by_zero: false,
+            step_limit: None,
+            stack_pointer: INIT_STACK_POINTER,
+            stack_limit: None,
+            endianness: Endianness::Little,
+            check_alignment: true,
+        }
+    }
+}
 
 /// A virtual processor has virtual registers and memory.
 pub struct Processor {
     registers: Vec<u32>,
-    main_memory: Vec<u32>,
+    main_memory: Memory,
     flag: Flag,
+    config: Config,
+    syscall_handler: Box<dyn SyscallHandler>,
+    tracers: Vec<Box<dyn Tracer>>,
+    /// The number of instructions executed so far.
+    n_steps: u64,
+    /// Whether the processor has stopped executing instructions.
+    halted: bool,
+    /// The exit status of the program once it has exited.
+    exit_code: Option<Payload>,
 }
 
 pub type Payload = u32;
 pub type Address = usize;
 
+/// A description of an instruction executed by the processor.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Step {
+    /// The address the instruction was fetched from.
+    pub pc: Address,
+    /// The instruction that was executed.
+    pub instr: Instruction,
+    pub form: Form,
+    pub opcode: Opcode,
+}
+
+/// A condition on the state of the processor used to stop execution.
+pub trait StopCondition {
+    fn is_met(&self, vm: &Processor) -> bool;
+}
+
+/// Stop once the program counter reaches the given address.
+impl StopCondition for Address {
+    fn is_met(&self, vm: &Processor) -> bool {
+        vm.get_pc() == *self
+    }
+}
+
+/// Stop once the predicate holds.
+impl<F: Fn(&Processor) -> bool> StopCondition for F {
+    fn is_met(&self, vm: &Processor) -> bool {
+        self(vm)
+    }
+}
+
+/// An operation computing the result to be saved to the destination register.
+type Operation = Box<dyn Fn() -> Result<Payload, Fault>>;
+
+/// Apply an arithmetic operation, raising a fault on signed overflow if trapping is enabled.
+fn arithmetic(
+    trap_on_overflow: bool,
+    op1: Payload,
+    op2: Payload,
+    checked: fn(i32, i32) -> Option<i32>,
+    wrapping: fn(Payload, Payload) -> Payload,
+) -> Result<Payload, Fault> {
+    if trap_on_overflow && checked(op1 as i32, op2 as i32).is_none() {
+        return Err(Fault::ArithmeticOverflow);
+    }
+    Ok(wrapping(op1, op2))
+}
+
+/// Apply a division, raising a fault on division by zero if trapping is enabled and producing zero
+/// otherwise.
+fn divide(
+    trap_on_divide_by_zero: bool,
+    op1: Payload,
+    op2: Payload,
+    quotient: fn(Payload, Payload) -> Payload,
+) -> Result<Payload, Fault> {
+    match op2 {
+        0 if trap_on_divide_by_zero => Err(Fault::DivideByZero),
+        0 => Ok(0),
+        _ => Ok(quotient(op1, op2)),
+    }
+}
+
+/// Compute the address of a memory access from a base address and a signed offset.
+fn effective_address(base: Payload, offset: i64) -> Result<Address, Fault> {
+    let address = base as i64 + offset;
+    match 0 <= address && address < N_BYTES_IN_MAIN_MEMORY as i64 {
+        true => Ok(address as Address),
+        false => Err(Fault::MemoryOutOfRange),
+    }
+}
+
+/// Interpret the given number of least significant bits of a value as a two's complement integer.
+fn sign_extend(value: Payload, n_bits: u32) -> i32 {
+    let shift = 32 - n_bits;
+    (value << shift) as i32 >> shift
+}
+
+impl Default for Processor {
+    fn default() -> Processor {
+        Processor::with_config(Config::default())
+    }
+}
+
 impl Processor {
     /// Instantiate a new processor.
     pub fn new() -> Processor {
+        Processor::default()
+    }
+    /// Instantiate a new processor with the given options.
+    pub fn with_config(config: Config) -> Processor {
+        let mut registers = vec![INIT_REGISTER_VALUE; N_REGISTERS_IN_PROCESSOR];
+        registers[Register::SP as Address] = config.stack_pointer;
         Processor {
-            registers: vec![INIT_REGISTER_VALUE; N_REGISTERS_IN_PROCESSOR],
-            main_memory: vec![INIT_REGISTER_VALUE; N_REGISTERS_IN_MAIN_MEMORY],
+            registers,
+            main_memory: Memory::with_endianness(config.endianness),
             flag: Flag::new(),
+            config,
+            syscall_handler: Box::new(DefaultSyscallHandler::new()),
+            tracers: Vec::new(),
+            n_steps: 0,
+            halted: false,
+            exit_code: None,
         }
     }
+    /// Replace the handler invoked when an SVC instruction executes.
+    pub fn set_syscall_handler<H: SyscallHandler + 'static>(&mut self, handler: H) {
+        self.syscall_handler = Box::new(handler)
+    }
+    /// Attach a tracer notified of every event raised while executing instructions.
+    pub fn attach_tracer<T: Tracer + 'static>(&mut self, tracer: T) {
+        self.tracers.push(Box::new(tracer))
+    }
     /// Get the contents stored in the program counter.
-    fn get_pc(&self) -> Address {
+    pub fn get_pc(&self) -> Address {
         self.registers[Register::PC as Address] as Address
     }
     fn set_pc(&mut self, payload: Payload) {
         self.registers[Register::PC as Address] = payload
     }
-    /// Increment the program counter by 1.
+    /// Increment the program counter past the current instruction.
     fn incr_pc(&mut self) {
-        self.registers[Register::PC as Address] += 1
+        let pc = &mut self.registers[Register::PC as Address];
+        *pc = pc.wrapping_add(N_BYTES_IN_INSTRUCTION)
+    }
+    /// Write a word to main memory pointed to by the given byte address.
+    pub fn write_to_mm(&mut self, address: Address, payload: Payload) {
+        self.main_memory.write(address, Width::Word, payload)
+    }
+    /// Read the word from the main memory pointed to by the given byte address.
+    pub fn read_from_mm(&self, address: Address) -> Payload {
+        self.main_memory.read(address, Width::Word)
     }
-    /// Write data to main memory pointed to by the given address.
-    pub fn write_to_mm(&mut self, address: Address, instruction: Payload) {
-        self.main_memory[address] = instruction
+    /// Read the words stored in a range of main memory.
+    pub fn read_range_from_mm(&self, range: Range<Address>) -> Vec<Payload> {
+        range
+            .step_by(Width::Word.n_bytes())
+            .map(|address| self.read_from_mm(address))
+            .collect()
     }
-    /// Read data from the main memory pointed to by the program counter.
-    fn read_from_mm(&self) -> &Payload {
-        &self.main_memory[self.get_pc()]
+    /// Get the contents of every register in the processor, indexed by register address.
+    pub fn get_registers(&self) -> &[Payload] {
+        &self.registers
+    }
+    /// Get the contents of the given register.
+    pub fn get_register(&self, register: Register) -> Payload {
+        self.registers[register as Address]
+    }
+    /// Get the condition flags.
+    pub fn get_flag(&self) -> &Flag {
+        &self.flag
+    }
+    /// Get the number of instructions executed so far.
+    pub fn get_n_steps(&self) -> u64 {
+        self.n_steps
+    }
+    /// Check whether the processor has stopped executing instructions.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+    /// Get the exit status of the program, if it has exited.
+    pub fn get_exit_code(&self) -> Option<Payload> {
+        self.exit_code
+    }
+    /// Notify every attached tracer of an event.
+    fn trace(&mut self, event: Event) {
+        for tracer in self.tracers.iter_mut() {
+            tracer.trace(&event);
+        }
+    }
+    /// Read the contents of a register used as an operand by the current instruction.
+    fn read_operand(&mut self, operand: Operand, register: Address) -> Payload {
+        let value = self.registers[register];
+        self.trace(Event::RegisterRead {
+            operand,
+            register,
+            value,
+        });
+        value
+    }
+    /// Extract an immediate value from the current instruction.
+    fn read_immediate(&mut self, n_bits: u8, value: Payload) -> Payload {
+        self.trace(Event::Immediate { n_bits, value });
+        value
+    }
+    /// Write the result of an instruction to a register. Writing to the program counter branches
+    /// to the given address.
+    fn write_register(&mut self, register: Address, value: Payload) {
+        let before = self.registers[register];
+        self.registers[register] = match register == Register::PC as Address {
+            // The program counter is incremented once the instruction has executed.
+            true => value.wrapping_sub(N_BYTES_IN_INSTRUCTION),
+            false => value,
+        };
+        self.trace(Event::RegisterWrite {
+            register,
+            before,
+            value,
+        });
+    }
+    /// Ensure an access of the given width fits in main memory and, if alignment checking is
+    /// enabled, is aligned to its width.
+    fn check_access(&self, address: Address, width: Width) -> Result<(), Fault> {
+        if address as u64 + width.n_bytes() as u64 > N_BYTES_IN_MAIN_MEMORY {
+            return Err(Fault::MemoryOutOfRange);
+        }
+        if self.config.check_alignment && address & (width.n_bytes() - 1) != 0 {
+            return Err(Fault::UnalignedAccess);
+        }
+        Ok(())
+    }
+    /// Load the data of the given width stored at the given address of main memory into a
+    /// register, sign extending it if asked to.
+    fn load(
+        &mut self,
+        dr_addr: Address,
+        address: Address,
+        width: Width,
+        signed: bool,
+    ) -> Result<(), Fault> {
+        self.check_access(address, width)?;
+        let mut value = self.main_memory.read(address, width);
+        self.trace(Event::MemoryRead { address, value });
+        if signed {
+            value = sign_extend(value, 8 * width.n_bytes() as u32) as Payload;
+        }
+        self.write_register(dr_addr, value);
+        Ok(())
+    }
+    /// Store the least significant bytes of a value fitting in the given width at the given
+    /// address of main memory.
+    fn store(&mut self, address: Address, width: Width, value: Payload) -> Result<(), Fault> {
+        self.check_access(address, width)?;
+        let before = self.main_memory.read(address, width);
+        self.main_memory.write(address, width, value);
+        let value = self.main_memory.read(address, width);
+        self.trace(Event::MemoryWrite {
+            address,
+            before,
+            value,
+        });
+        Ok(())
+    }
+    /// Transfer a single register to or from main memory at the given address, as done by load
+    /// and store instructions.
+    fn transfer(
+        &mut self,
+        opcode: &Opcode,
+        dr_addr: Address,
+        dr_cont: Payload,
+        address: Address,
+    ) -> Result<(), Fault> {
+        match opcode {
+            Opcode::LDR => self.load(dr_addr, address, Width::Word, false),
+            Opcode::LDRB => self.load(dr_addr, address, Width::Byte, false),
+            Opcode::LDRH => self.load(dr_addr, address, Width::Halfword, false),
+            Opcode::LDRSB => self.load(dr_addr, address, Width::Byte, true),
+            Opcode::LDRSH => self.load(dr_addr, address, Width::Halfword, true),
+            Opcode::STR => self.store(address, Width::Word, dr_cont),
+            Opcode::STRB => self.store(address, Width::Byte, dr_cont),
+            Opcode::STRH => self.store(address, Width::Halfword, dr_cont),
+            _ => Err(Fault::UndefinedInstruction),
+        }
+    }
+    /// Update the condition flags from the operands and result of an instruction setting them.
+    /// The carry out of the shifter, if any, sets the carry flag of logical operations.
+    fn update_flags(
+        &mut self,
+        opcode: &Opcode,
+        op1: Payload,
+        op2: Payload,
+        result: Payload,
+        carry: Option<bool>,
+    ) {
+        let before = self.flag;
+        match opcode {
+            Opcode::ADDS => self.flag.set_add(op1, op2),
+            Opcode::SUBS | Opcode::CMP => self.flag.set_sub(op1, op2),
+            Opcode::MULS => self.flag.set_mul(result),
+            _ => self.flag.set_logical(result, carry),
+        }
+        let after = self.flag;
+        self.trace(Event::FlagUpdate { before, after });
     }
     /// Fetch and decode instruction pointed to by the program counter.
-    fn fetch_and_decode(&mut self) {
-        println!("----------------------------------------");
-        println!("{:30}{:#010X} ", "Pc:", self.get_pc());
+    fn fetch_and_decode(&mut self) -> Result<(Form, Opcode), Fault> {
         // Read data from the main memory pointed to by the program counter.
-        let instr = self.read_from_mm();
-        let mut decoder = EncoderDecoder::new(Some(*instr));
+        let pc = self.get_pc();
+        // Instructions are always fetched from word aligned addresses.
+        if pc & (N_BYTES_IN_INSTRUCTION as Address - 1) != 0 {
+            return Err(Fault::UnalignedAccess);
+        }
+        let instr = self.read_from_mm(pc);
+        self.trace(Event::Fetch { pc, instr });
+        let mut decoder = EncoderDecoder::new(Some(instr));
         // Extract the opcode and form from the payload.
-        if let Ok((form, opcode)) = decoder.get_form_and_opcode() {
-            match opcode {
-                Opcode::B => println!("{:26}{:?}", "Opcode:", opcode),
-                _ => println!("{:24}{:?}", "Opcode:", opcode),
+        let (form, opcode) = match decoder.get_form_and_opcode() {
+            Ok(form_and_opcode) => form_and_opcode,
+            Err(_) => return Err(Fault::UndefinedInstruction),
+        };
+        self.trace(Event::Decode {
+            form,
+            opcode: opcode.clone(),
+        });
+        // Forms without a condition code always execute.
+        let cc = match decoder.get_cc() {
+            _ if !form.has_cc() => ConditionCode::AL,
+            Some(cc) => cc,
+            None => return Err(Fault::UndefinedInstruction),
+        };
+        // Branches report whether they are taken, other instructions are skipped when their
+        // condition does not hold.
+        if form != Form::Six && !self.check_condition(&cc) {
+            self.trace(Event::Skip { cc });
+            return Ok((form, opcode));
+        }
+        // Execute the handler based on instruction form.
+        match form {
+            Form::One => self.form_one_handler(opcode.clone(), decoder)?,
+            Form::Two => self.form_two_handler(opcode.clone(), decoder)?,
+            Form::Three => self.form_three_handler(opcode.clone(), decoder)?,
+            Form::Four => self.form_four_handler(opcode.clone(), decoder)?,
+            Form::Five => self.form_five_handler(opcode.clone(), decoder)?,
+            Form::Six => self.form_six_handler(opcode.clone(), decoder)?,
+            Form::Seven => self.form_seven_handler(opcode.clone(), decoder)?,
+            Form::Eight => self.form_eight_handler(opcode.clone(), decoder)?,
+            Form::Nine => self.form_nine_handler(opcode.clone(), decoder)?,
+            Form::Ten => self.form_ten_handler(opcode.clone(), decoder)?,
+            Form::Eleven => self.form_eleven_handler(opcode.clone(), decoder)?,
+            Form::Twelve => self.form_twelve_handler(opcode.clone(), decoder)?,
+            Form::Thirteen => self.form_thirteen_handler(opcode.clone(), decoder)?,
+            Form::Fourteen => self.form_fourteen_handler(opcode.clone(), decoder)?,
+            Form::Fifteen => self.form_fifteen_handler(opcode.clone(), decoder)?,
+            Form::Sixteen => self.form_sixteen_handler(opcode.clone(), decoder)?,
+            Form::Seventeen => self.form_seventeen_handler(opcode.clone(), decoder)?,
+        }
+        Ok((form, opcode))
+    }
+    /// Get the operation applied by arithmetic and logical instructions of form one and four.
+    fn get_operation(&self, opcode: &Opcode, op1: Payload, op2: Payload) -> Option<Operation> {
+        // Instructions setting the flags report overflow through them rather than trapping.
+        let trap = self.config.trap_on_overflow && !opcode.sets_flags();
+        let operation: Operation = match opcode {
+            Opcode::ADD | Opcode::ADDS => Box::new(move || {
+                arithmetic(trap, op1, op2, i32::checked_add, Payload::wrapping_add)
+            }),
+            Opcode::AND | Opcode::ANDS => Box::new(move || Ok(op1 & op2)),
+            Opcode::EOR | Opcode::EORS => Box::new(move || Ok(op1 ^ op2)),
+            Opcode::MUL | Opcode::MULS => Box::new(move || {
+                arithmetic(trap, op1, op2, i32::checked_mul, Payload::wrapping_mul)
+            }),
+            Opcode::ORR | Opcode::ORRS => Box::new(move || Ok(op1 | op2)),
+            Opcode::SUB | Opcode::SUBS => Box::new(move || {
+                arithmetic(trap, op1, op2, i32::checked_sub, Payload::wrapping_sub)
+            }),
+            Opcode::SDIV => {
+                let trap = self.config.trap_on_divide_by_zero;
+                // The quotient is rounded towards zero, and dividing the most negative integer by
+                // -1 wraps around to itself.
+                Box::new(move || {
+                    divide(trap, op1, op2, |op1, op2| {
+                        (op1 as i32).wrapping_div(op2 as i32) as Payload
+                    })
+                })
             }
-            // Execute the handler based on instruction form.
-            match form {
-                Form::One => self.form_one_handler(opcode, decoder),
-                Form::Two => self.form_two_handler(opcode, decoder),
-                Form::Four => self.form_four_handler(opcode, decoder),
-                Form::Five => self.form_five_handler(opcode, decoder),
-                Form::Six => self.form_six_handler(decoder),
+            Opcode::UDIV => {
+                let trap = self.config.trap_on_divide_by_zero;
+                Box::new(move || divide(trap, op1, op2, |op1, op2| op1 / op2))
             }
-        }
+            _ => return None,
+        };
+        Some(operation)
     }
-    fn form_one_handler(&mut self, opcode: Opcode, mut decoder: EncoderDecoder) {
+    fn form_one_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
         // Parse the destination address.
         let dr_addr = decoder.get_dr();
-        let dr_cont = self.registers[dr_addr];
-        println!("{:23}[{}] = {:#010X}", "Dr: ", get_name(dr_addr), dr_cont);
+        let dr_cont = self.read_operand(Operand::Dr, dr_addr);
         // Define operand 1 by retrieving the content pointed to by register x.
-        let rx_addr = decoder.get_rx();
-        let op1 = self.registers[rx_addr];
-        println!("{:23}[{}] = {:#010X}", "Rx:", get_name(rx_addr), op1);
+        let op1 = self.read_operand(Operand::Rx, decoder.get_rx());
         // Define operand 1 by retrieving the content pointed to by register y.
-        let ry_addr = decoder.get_ry();
-        let op2 = self.registers[ry_addr];
-        println!("{:23}[{}] = {:#010X}", "Ry:", get_name(ry_addr), op2);
+        let op2 = self.read_operand(Operand::Ry, decoder.get_ry());
+        if let Some(kind) = opcode.get_shift() {
+            // Only the least significant byte of the register is used as the amount.
+            return self.shift_operation(&opcode, dr_addr, op1, op2 & 0xFF, kind);
+        }
+        if let Some(operation) = self.get_operation(&opcode, op1, op2) {
+            return self.data_processing(&opcode, dr_addr, op1, op2, operation, None);
+        }
+        // The offset register holds a two's complement integer.
+        let address = effective_address(op1, op2 as i32 as i64)?;
+        self.transfer(&opcode, dr_addr, dr_cont, address)
+    }
+
+    fn form_two_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        // Parse the destination address.
+        let dr_addr = decoder.get_dr();
+        // The destination register contents.
+        let dr_cont = self.read_operand(Operand::Dr, dr_addr);
+        // Define operand 1 by retrieving the content pointed to by register x.
+        let op1 = self.read_operand(Operand::Rx, decoder.get_rx());
         // Execute instruction based on the opcode.
         match opcode {
-            Opcode::ADD => self.execute(dr_addr, Box::new(move || op1 + op2)),
-            Opcode::AND => self.execute(dr_addr, Box::new(move || op1 & op2)),
-            Opcode::EOR => self.execute(dr_addr, Box::new(move || op1 ^ op2)),
-            Opcode::MUL => self.execute(dr_addr, Box::new(move || op1 * op2)),
-            Opcode::ORR => self.execute(dr_addr, Box::new(move || op1 | op2)),
-            Opcode::SUB => self.execute(dr_addr, Box::new(move || op1 - op2)),
-            Opcode::LDR => {
-                let ptr = self.main_memory[(op1 + op2) as usize].clone();
-                println!(
-                    "{:10}MMem[[{}] + [{}]] = {:#010X}",
-                    "Ptr:",
-                    get_name(rx_addr),
-                    get_name(ry_addr),
-                    ptr
-                );
-                self.registers[dr_addr] = ptr;
-                println!("{:23}[{}] = {:#010X}", "Result:", get_name(dr_addr), ptr);
+            Opcode::MOV | Opcode::MOVS => {
+                let operation = Box::new(move || Ok(op1));
+                return self.data_processing(&opcode, dr_addr, 0, op1, operation, None);
             }
-            Opcode::STR => {
-                let payload = self.registers[dr_addr];
-                self.main_memory[(op1 + op2) as usize] = payload;
-                println!(
-                    "{:10}MMem[[{}] + [{}]] = {:#010X}",
-                    "Result:",
-                    get_name(rx_addr),
-                    get_name(ry_addr),
-                    payload
-                );
+            Opcode::MVN | Opcode::MVNS => {
+                let operation = Box::new(move || Ok(!op1));
+                return self.data_processing(&opcode, dr_addr, 0, op1, operation, None);
             }
-            _ => (),
+            Opcode::CMP => self.update_flags(&opcode, dr_cont, op1, 0, None),
+            _ => return self.transfer(&opcode, dr_addr, dr_cont, op1 as Address),
         }
+        Ok(())
     }
 
-    fn form_two_handler(&mut self, opcode: Opcode, mut decoder: EncoderDecoder) {
-        // Parse the destination address.
-        let dr_addr = decoder.get_dr();
-        let dr_cont = self.registers[dr_addr]; // The destination register contents.
-        println!("{:23}[{}] = {:#010X}", "Dr: ", get_name(dr_addr), dr_cont);
+    fn form_three_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        let cc = match decoder.get_cc() {
+            Some(cc) => cc,
+            None => return Err(Fault::UndefinedInstruction),
+        };
+        // Define the target by retrieving the content pointed to by the destination register.
+        let target = self.read_operand(Operand::Dr, decoder.get_dr());
+        match opcode {
+            Opcode::BX => {
+                // The program counter is incremented once the instruction has executed.
+                self.set_pc(target.wrapping_sub(N_BYTES_IN_INSTRUCTION));
+                self.trace(Event::Branch {
+                    cc,
+                    taken: true,
+                    target: target as Address,
+                });
+            }
+            _ => return Err(Fault::UndefinedInstruction),
+        }
+        Ok(())
+    }
+
+    fn form_four_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        let (dr_addr, rx_addr) = (
+            decoder.get_unconditional_dr(),
+            decoder.get_unconditional_rx(),
+        );
+        self.immediate_operation(opcode, dr_addr, rx_addr, 16, decoder.get_immed16())
+    }
+    fn form_sixteen_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        let (dr_addr, rx_addr) = (decoder.get_dr(), decoder.get_rx());
+        self.immediate_operation(opcode, dr_addr, rx_addr, 12, decoder.get_immed12())
+    }
+    /// Execute an instruction of form four or sixteen, whose second operand is an immediate value
+    /// of the given width.
+    fn immediate_operation(
+        &mut self,
+        opcode: Opcode,
+        dr_addr: Address,
+        rx_addr: Address,
+        n_bits: u8,
+        immed: Payload,
+    ) -> Result<(), Fault> {
+        let dr_cont = self.read_operand(Operand::Dr, dr_addr);
         // Define operand 1 by retrieving the content pointed to by register x.
-        let rx_addr = decoder.get_rx();
-        let op1 = self.registers[rx_addr];
-        println!("{:23}[{}] = {:#010X}", "Rx:", get_name(rx_addr), op1);
+        let op1 = self.read_operand(Operand::Rx, rx_addr);
+        // Define operand 2 by extracting the immediate value.
+        let op2 = self.read_immediate(n_bits, immed);
+        if let Some(kind) = opcode.get_shift() {
+            return self.shift_operation(&opcode, dr_addr, op1, op2, kind);
+        }
+        if let Some(operation) = self.get_operation(&opcode, op1, op2) {
+            return self.data_processing(&opcode, dr_addr, op1, op2, operation, None);
+        }
+        let address = effective_address(op1, sign_extend(op2, n_bits as u32) as i64)?;
+        self.transfer(&opcode, dr_addr, dr_cont, address)
+    }
+    fn form_five_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        let dr_addr = decoder.get_unconditional_dr();
+        self.immediate_operand_operation(opcode, dr_addr, 20, decoder.get_immed20())
+    }
+    fn form_seventeen_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        let dr_addr = decoder.get_dr();
+        self.immediate_operand_operation(opcode, dr_addr, 16, decoder.get_immed16())
+    }
+    /// Execute an instruction of form five or seventeen, whose only operand is an immediate value
+    /// of the given width.
+    fn immediate_operand_operation(
+        &mut self,
+        opcode: Opcode,
+        dr_addr: Address,
+        n_bits: u8,
+        immed: Payload,
+    ) -> Result<(), Fault> {
+        let dr_cont = self.read_operand(Operand::Dr, dr_addr);
+        // Define operand 1 by extracting the immediate value.
+        let op1 = self.read_immediate(n_bits, immed);
         // Execute instruction based on the opcode.
         match opcode {
-            Opcode::MOV => self.execute(dr_addr, Box::new(move || op1)),
-            Opcode::MVN => self.execute(dr_addr, Box::new(move || !op1)),
-            Opcode::LDR => {
-                let ptr = self.main_memory[op1 as usize].clone();
-                println!("{:17}MMem[[{}]] = {:#010X}", "Ptr:", get_name(rx_addr), ptr);
-                self.registers[dr_addr] = ptr;
-                println!("{:23}[{}] = {:#010X}", "Result:", get_name(dr_addr), ptr);
+            Opcode::MOV | Opcode::MOVS => {
+                let operation = Box::new(move || Ok(op1));
+                return self.data_processing(&opcode, dr_addr, 0, op1, operation, None);
+            }
+            Opcode::MVN | Opcode::MVNS => {
+                let operation = Box::new(move || Ok(!op1));
+                return self.data_processing(&opcode, dr_addr, 0, op1, operation, None);
             }
-            Opcode::STR => {
-                let payload = self.registers[dr_addr];
-                self.main_memory[op1 as usize] = payload;
-                println!(
-                    "{:17}MMem[[{}]] = {:#010X}",
-                    "Result:",
-                    get_name(rx_addr),
-                    payload
-                );
+            Opcode::CMP => self.update_flags(&opcode, dr_cont, op1, 0, None),
+            // PC reletive mode.
+            _ => {
+                let offset = sign_extend(op1, n_bits as u32) as i64;
+                let address = effective_address(self.get_pc() as Payload, offset)?;
+                return self.transfer(&opcode, dr_addr, dr_cont, address);
             }
-            Opcode::CMP => self.flag.update(dr_cont, op1),
-            _ => (),
         }
+        Ok(())
     }
-
-    fn form_four_handler(&mut self, opcode: Opcode, mut decoder: EncoderDecoder) {
+    /// Check whether the condition flags satisfy the condition code.
+    fn check_condition(&self, cc: &ConditionCode) -> bool {
+        match cc {
+            AL => true,
+            EQ => self.flag.get_z(),
+            NE => !self.flag.get_z(),
+            HS => self.flag.get_c(),
+            LO => !self.flag.get_c(),
+            MI => self.flag.get_n(),
+            PL => !self.flag.get_n(),
+            VS => self.flag.get_v(),
+            VC => !self.flag.get_v(),
+            HI => self.flag.get_c() && !self.flag.get_z(),
+            LS => !self.flag.get_c() || self.flag.get_z(),
+            GE => self.flag.get_n() == self.flag.get_v(),
+            LT => self.flag.get_n() != self.flag.get_v(),
+            GT => !self.flag.get_z() && (self.flag.get_n() == self.flag.get_v()),
+            LE => self.flag.get_z() || (self.flag.get_n() != self.flag.get_v()),
+        }
+    }
+    fn form_six_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        let cc = match decoder.get_cc() {
+            Some(cc) => cc,
+            None => return Err(Fault::UndefinedInstruction),
+        };
+        let cond = self.check_condition(&cc);
+        // The target is encoded as the word index of the instruction it points to.
+        let target = self.read_immediate(20, decoder.get_immed20()) * N_BYTES_IN_INSTRUCTION;
+        if cond {
+            match opcode {
+                Opcode::B => (),
+                Opcode::BL => {
+                    // Link the address of the instruction following the branch to return to it.
+                    let pc = self.get_pc() as Payload;
+                    let lr = pc.wrapping_add(N_BYTES_IN_INSTRUCTION);
+                    self.write_register(Register::LR as Address, lr);
+                }
+                _ => return Err(Fault::UndefinedInstruction),
+            }
+            // The program counter is incremented once the instruction has executed.
+            self.set_pc(target.wrapping_sub(N_BYTES_IN_INSTRUCTION));
+        }
+        self.trace(Event::Branch {
+            cc,
+            taken: cond,
+            target: target as Address,
+        });
+        Ok(())
+    }
+    fn form_seven_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        // Extract the number of the supervisor call from the immediate 20-bit value.
+        let number = self.read_immediate(20, decoder.get_immed20());
+        match opcode {
+            Opcode::SVC => {
+                let syscall = self.syscall_handler.call(
+                    number,
+                    &mut self.registers,
+                    &mut self.main_memory,
+                )?;
+                if let Syscall::Exit(status) = syscall {
+                    self.halted = true;
+                    self.exit_code = Some(status);
+                }
+            }
+            _ => return Err(Fault::UndefinedInstruction),
+        }
+        Ok(())
+    }
+    fn form_eight_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        // Parse the address of the base register.
+        let base_addr = decoder.get_dr();
+        self.block_transfer(&opcode, base_addr, &decoder.get_register_list(), false)
+    }
+    fn form_nine_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        // Parse the address of the base register, which is written back.
+        let base_addr = decoder.get_dr();
+        self.block_transfer(&opcode, base_addr, &decoder.get_register_list(), true)
+    }
+    fn form_ten_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        // The stack pointer is the base register, and is always written back.
+        let base_addr = Register::SP as Address;
+        self.block_transfer(&opcode, base_addr, &decoder.get_register_list(), true)
+    }
+    fn form_eleven_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
         // Parse the destination address.
         let dr_addr = decoder.get_dr();
-        let dr_cont = self.registers[dr_addr];
-        println!("{:23}[{}] = {:#010X}", "Dr: ", get_name(dr_addr), dr_cont);
+        if opcode.is_load_or_store() {
+            let dr_cont = self.read_operand(Operand::Dr, dr_addr);
+            // The address is offset from the base register by the shifted register y.
+            let base = self.read_operand(Operand::Rx, decoder.get_rx());
+            let ry_addr = decoder.get_ry();
+            let (offset, _) = self.shifted_operand(Operand::Ry, ry_addr, &mut decoder);
+            let address = effective_address(base, offset as i32 as i64)?;
+            return self.transfer(&opcode, dr_addr, dr_cont, address);
+        }
         // Define operand 1 by retrieving the content pointed to by register x.
+        let op1 = self.read_operand(Operand::Rx, decoder.get_rx());
+        // Define operand 2 by shifting the content pointed to by register y.
+        let ry_addr = decoder.get_ry();
+        let (op2, carry) = self.shifted_operand(Operand::Ry, ry_addr, &mut decoder);
+        match self.get_operation(&opcode, op1, op2) {
+            Some(operation) => self.data_processing(&opcode, dr_addr, op1, op2, operation, carry),
+            None => Err(Fault::UndefinedInstruction),
+        }
+    }
+    fn form_twelve_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        // Parse the destination address.
+        let dr_addr = decoder.get_dr();
+        let dr_cont = self.read_operand(Operand::Dr, dr_addr);
+        // Define operand 1 by shifting the content pointed to by register x.
         let rx_addr = decoder.get_rx();
-        let op1 = self.registers[rx_addr];
-        println!("{:23}[{}] = {:#010X}", "Rx:", get_name(rx_addr), op1);
-        // Define operand 2 by extracting the immediate 16-bit value.
-        let op2 = decoder.get_immed16();
+        let (op1, carry) = self.shifted_operand(Operand::Rx, rx_addr, &mut decoder);
         // Execute instruction based on the opcode.
         match opcode {
-            Opcode::ADD => self.execute(dr_addr, Box::new(move || op1 + op2)),
-            Opcode::AND => self.execute(dr_addr, Box::new(move || op1 & op2)),
-            Opcode::EOR => self.execute(dr_addr, Box::new(move || op1 ^ op2)),
-            Opcode::MUL => self.execute(dr_addr, Box::new(move || op1 * op2)),
-            Opcode::ORR => self.execute(dr_addr, Box::new(move || op1 | op2)),
-            Opcode::SUB => self.execute(dr_addr, Box::new(move || op1 - op2)),
-            Opcode::LDR => {
-                let ptr = self.main_memory[(op1 + op2) as usize].clone();
-                println!(
-                    "{:11}MMem[[{}] + {:#0X}] = {:#010X}",
-                    "Ptr:",
-                    get_name(rx_addr),
-                    op2,
-                    ptr
-                );
-                self.registers[dr_addr] = ptr;
-                println!("{:23}[{}] = {:#010X}", "Result:", get_name(dr_addr), ptr);
+            Opcode::MOV | Opcode::MOVS => {
+                let operation = Box::new(move || Ok(op1));
+                return self.data_processing(&opcode, dr_addr, 0, op1, operation, carry);
             }
-            Opcode::STR => {
-                let payload = self.registers[dr_addr];
-                self.main_memory[(op1 + op2) as usize] = payload;
-                println!(
-                    "{:9}MMem[[{}] + [{:#0X}]] = {:#010X}",
-                    "Result:",
-                    get_name(rx_addr),
-                    op2,
-                    payload
-                );
+            Opcode::MVN | Opcode::MVNS => {
+                let operation = Box::new(move || Ok(!op1));
+                return self.data_processing(&opcode, dr_addr, 0, op1, operation, carry);
             }
-            Opcode::CMP => self.flag.update(dr_cont, op1),
-            _ => (),
+            Opcode::CMP => self.update_flags(&opcode, dr_cont, op1, 0, carry),
+            _ => return Err(Fault::UndefinedInstruction),
         }
+        Ok(())
     }
-    fn form_five_handler(&mut self, opcode: Opcode, mut decoder: EncoderDecoder) {
-        // Parse the destination address.
+    fn form_thirteen_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        // Parse the destination address, and the address of the register receiving the high word
+        // of a long multiplication.
         let dr_addr = decoder.get_dr();
-        let dr_cont = self.registers[dr_addr];
-        println!("{:23}[{}] = {:#010X}", "Dr: ", get_name(dr_addr), dr_cont);
-        // Define operand1  by extracting the immediate 20-bit value.
-        let op1 = decoder.get_immed20();
-        // Execute instruction based on the opcode.
+        let rx_addr = decoder.get_rx();
+        let (ry_addr, rz_addr) = (decoder.get_ry(), decoder.get_rz());
+        // Products wrap around as on ARM, so overflow never traps.
         match opcode {
-            Opcode::MOV => self.execute(dr_addr, Box::new(move || op1)),
-            Opcode::MVN => self.execute(dr_addr, Box::new(move || !op1)),
-            Opcode::LDR => {
-                // PC reletive mode.
-                let ptr = self.main_memory[(self.registers[Register::PC as usize] + op1) as usize]
-                    .clone();
-                println!("{:18}MMem[{:#0X}] = {:#010X}", "Ptr:", op1, ptr);
-                self.registers[dr_addr] = ptr;
-                println!("{:23}[{}] = {:#010X}", "Result:", get_name(dr_addr), ptr);
+            Opcode::MLA | Opcode::MLS => {
+                let op1 = self.read_operand(Operand::Rx, rx_addr);
+                let op2 = self.read_operand(Operand::Ry, ry_addr);
+                let acc = self.read_operand(Operand::Rz, rz_addr);
+                let product = op1.wrapping_mul(op2);
+                let result = match opcode {
+                    Opcode::MLA => acc.wrapping_add(product),
+                    _ => acc.wrapping_sub(product),
+                };
+                self.write_register(dr_addr, result);
             }
-            Opcode::STR => {
-                let payload = self.registers[dr_addr];
-                // PC reletive mode.
-                self.main_memory[(self.registers[Register::PC as usize] + op1) as usize] = payload;
-                println!("{:18}MMem[{:#0X}] = {:#010X}", "Result:", op1, payload);
+            Opcode::UMULL | Opcode::SMULL | Opcode::UMLAL => {
+                let op1 = self.read_operand(Operand::Ry, ry_addr);
+                let op2 = self.read_operand(Operand::Rz, rz_addr);
+                let product = match opcode {
+                    Opcode::SMULL => (op1 as i32 as i64).wrapping_mul(op2 as i32 as i64) as u64,
+                    _ => (op1 as u64) * (op2 as u64),
+                };
+                let result = match opcode {
+                    Opcode::UMLAL => {
+                        let lo = self.read_operand(Operand::Dr, dr_addr) as u64;
+                        let hi = self.read_operand(Operand::Rx, rx_addr) as u64;
+                        (hi << 32 | lo).wrapping_add(product)
+                    }
+                    _ => product,
+                };
+                self.write_register(dr_addr, result as Payload);
+                self.write_register(rx_addr, (result >> 32) as Payload);
             }
-            Opcode::CMP => self.flag.update(dr_cont, op1),
-            _ => (),
+            _ => return Err(Fault::UndefinedInstruction),
         }
+        Ok(())
     }
-    fn form_six_handler(&mut self, mut decoder: EncoderDecoder) {
-        let cond;
-        let cc = decoder.get_cc();
-        match cc {
-            AL => cond = true,
-            EQ => cond = self.flag.get_z(),
-            NE => cond = !self.flag.get_z(),
-            HS => cond = self.flag.get_c(),
-            LO => cond = !self.flag.get_c(),
-            MI => cond = self.flag.get_n(),
-            PL => cond = !self.flag.get_n(),
-            VS => cond = self.flag.get_v(),
-            VC => cond = !self.flag.get_v(),
-            HI => cond = self.flag.get_c() && !self.flag.get_z(),
-            LS => cond = !self.flag.get_c() || self.flag.get_z(),
-            GE => cond = self.flag.get_n() == self.flag.get_v(),
-            LT => cond = self.flag.get_n() != self.flag.get_v(),
-            GT => cond = !self.flag.get_z() && (self.flag.get_n() == self.flag.get_v()),
-            LE => cond = self.flag.get_z() || (self.flag.get_n() != self.flag.get_v()),
+    fn form_fourteen_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        // Parse the destination address.
+        let dr_addr = decoder.get_dr();
+        let dr_cont = self.read_operand(Operand::Dr, dr_addr);
+        // Pre-index: the address is offset from the base register before the transfer.
+        let rx_addr = decoder.get_rx();
+        let base = self.read_operand(Operand::Rx, rx_addr);
+        let offset = self.read_immediate(12, decoder.get_immed12());
+        let address = effective_address(base, sign_extend(offset, 12) as i64)?;
+        // Storing through the stack pointer while writing it back pushes onto the stack.
+        let is_store = matches!(opcode, Opcode::STR | Opcode::STRB | Opcode::STRH);
+        if is_store && rx_addr == Register::SP as Address {
+            self.check_stack(address as Payload)?;
         }
-        if cond {
-            self.set_pc(decoder.get_immed20() - 1);
+        self.transfer(&opcode, dr_addr, dr_cont, address)?;
+        // The base register is only written back once the transfer has succeeded.
+        self.write_register(rx_addr, address as Payload);
+        Ok(())
+    }
+    fn form_fifteen_handler(
+        &mut self,
+        opcode: Opcode,
+        mut decoder: EncoderDecoder,
+    ) -> Result<(), Fault> {
+        // Parse the destination address.
+        let dr_addr = decoder.get_dr();
+        let dr_cont = self.read_operand(Operand::Dr, dr_addr);
+        // Post-index: the transfer uses the base register, which is offset afterwards.
+        let rx_addr = decoder.get_rx();
+        let base = self.read_operand(Operand::Rx, rx_addr);
+        let offset = self.read_immediate(12, decoder.get_immed12());
+        let next = effective_address(base, sign_extend(offset, 12) as i64)?;
+        self.transfer(&opcode, dr_addr, dr_cont, base as Address)?;
+        // The base register is only written back once the transfer has succeeded.
+        self.write_register(rx_addr, next as Payload);
+        Ok(())
+    }
+    /// Read the register holding the last operand and pass its content through the barrel
+    /// shifter, returning the shifted operand along with the carry out of the shifter.
+    fn shifted_operand(
+        &mut self,
+        operand: Operand,
+        register: Address,
+        decoder: &mut EncoderDecoder,
+    ) -> (Payload, Option<bool>) {
+        let value = self.read_operand(operand, register);
+        let amount = match decoder.is_shift_by_register() {
+            // Only the least significant byte of the register is used as the amount.
+            true => self.read_operand(Operand::Rz, decoder.get_rz()) & 0xFF,
+            false => self.read_immediate(5, decoder.get_immed5()),
+        };
+        shift(decoder.get_shift(), value, amount)
+    }
+    /// Execute a shift instruction, shifting operand 1 by the amount given by operand 2.
+    fn shift_operation(
+        &mut self,
+        opcode: &Opcode,
+        dr_addr: Address,
+        op1: Payload,
+        op2: Payload,
+        kind: Shift,
+    ) -> Result<(), Fault> {
+        let (result, carry) = shift(kind, op1, op2);
+        let operation = Box::new(move || Ok(result));
+        self.data_processing(opcode, dr_addr, op1, op2, operation, carry)
+    }
+    /// Ensure the stack pointer has not grown below the stack limit, if there is one.
+    fn check_stack(&self, sp: Payload) -> Result<(), Fault> {
+        match self.config.stack_limit {
+            Some(stack_limit) if sp < stack_limit => Err(Fault::StackOverflow),
+            _ => Ok(()),
+        }
+    }
+    /// Transfer the registers in the list to or from consecutive words of main memory, the lowest
+    /// register being stored at the lowest address, and write the base address past the
+    /// transferred words back to the base register if asked to.
+    fn block_transfer(
+        &mut self,
+        opcode: &Opcode,
+        base_addr: Address,
+        registers: &[Address],
+        writeback: bool,
+    ) -> Result<(), Fault> {
+        let base = self.read_operand(Operand::Dr, base_addr);
+        let n_bytes = (registers.len() * Width::Word.n_bytes()) as Payload;
+        let addresses = |start: Payload| (start as Address..).step_by(Width::Word.n_bytes());
+        match opcode {
+            Opcode::LDMIA | Opcode::POP => {
+                // Increment after: the words are read upwards from the base address.
+                let end = base.wrapping_add(n_bytes);
+                for (address, &register) in addresses(base).zip(registers) {
+                    self.load(register, address, Width::Word, false)?;
+                }
+                // The base register is only written back once every word is loaded, and a loaded
+                // base register takes precedence over the written back address.
+                if writeback && !registers.contains(&base_addr) {
+                    self.write_register(base_addr, end);
+                }
+            }
+            Opcode::STMDB | Opcode::PUSH => {
+                // Decrement before: the words are written below the base address.
+                let start = match base.checked_sub(n_bytes) {
+                    Some(start) => start,
+                    None => return Err(Fault::MemoryOutOfRange),
+                };
+                if base_addr == Register::SP as Address {
+                    self.check_stack(start)?;
+                }
+                for (address, &register) in addresses(start).zip(registers) {
+                    let value = self.registers[register];
+                    self.store(address, Width::Word, value)?;
+                }
+                if writeback {
+                    self.write_register(base_addr, start);
+                }
+            }
+            _ => return Err(Fault::UndefinedInstruction),
         }
-        println!("{:25}{:?} = {}", "Cc:", cc, cond);
+        Ok(())
     }
     /// Execute instruction and save the result to the destination register.
-    fn execute(&mut self, dr_addr: Address, lambda: Box<Fn() -> Payload>) {
-        let result = (*lambda)();
-        println!("{:23}[{}] = {:#010X}", "Result:", get_name(dr_addr), result);
-        self.registers[dr_addr] = result;
+    fn execute(&mut self, dr_addr: Address, lambda: Operation) -> Result<Payload, Fault> {
+        let result = (*lambda)()?;
+        self.write_register(dr_addr, result);
+        Ok(result)
+    }
+    /// Execute an arithmetic or logical instruction, updating the condition flags if its opcode
+    /// sets them.
+    fn data_processing(
+        &mut self,
+        opcode: &Opcode,
+        dr_addr: Address,
+        op1: Payload,
+        op2: Payload,
+        operation: Operation,
+        carry: Option<bool>,
+    ) -> Result<(), Fault> {
+        let result = self.execute(dr_addr, operation)?;
+        if opcode.sets_flags() {
+            self.update_flags(opcode, op1, op2, result, carry);
+        }
+        Ok(())
     }
     /// Load program into main memory.
-    pub fn load_program(&mut self, program: &Vec<Instruction>) {
+    pub fn load_program(&mut self, program: &[Instruction]) {
         let mut instr_ptr = 0;
         for instr in program {
             self.write_to_mm(instr_ptr, *instr);
-            instr_ptr += 1;
+            instr_ptr += N_BYTES_IN_INSTRUCTION as Address;
+        }
+    }
+    /// Load every section of the program at its address in main memory, then point the program
+    /// counter at its entry point and, if the program sets it, the stack pointer at its stack top.
+    pub fn load_sections(&mut self, program: &Program) {
+        for segment in &program.segments {
+            let mut address = segment.address as Address;
+            for word in &segment.words {
+                self.write_to_mm(address, *word);
+                address += N_BYTES_IN_INSTRUCTION as Address;
+            }
+        }
+        self.set_pc(program.entry);
+        if let Some(stack_top) = program.stack_top {
+            self.registers[Register::SP as Address] = stack_top;
+        }
+    }
+    /// Execute the instruction pointed to by the program counter.
+    pub fn step(&mut self) -> Result<Step, VmError> {
+        let pc = self.get_pc();
+        let instr = self.read_from_mm(pc);
+        let fault = |fault| VmError { fault, pc, instr };
+        if self.halted {
+            return Err(fault(Fault::Halted));
+        }
+        if let Some(step_limit) = self.config.step_limit {
+            if self.n_steps >= step_limit {
+                return Err(fault(Fault::StepLimitExceeded));
+            }
+        }
+        // Fetch and decode a new instruction.
+        let (form, opcode) = self.fetch_and_decode().map_err(fault)?; // This function will invoke the execute function.
+        self.incr_pc(); // Increment the program counter.
+        self.n_steps += 1;
+        self.trace(Event::Retire {
+            cycle: self.n_steps,
+            flag: self.flag,
+        });
+        Ok(Step {
+            pc,
+            instr,
+            form,
+            opcode,
+        })
+    }
+    /// Execute instructions until the processor halts, raises a fault or the given predicate,
+    /// which is checked after every instruction, no longer holds.
+    fn run_while<F: FnMut(&Processor) -> bool>(&mut self, mut predicate: F) -> Result<(), VmError> {
+        while !self.halted {
+            self.step()?;
+            if !predicate(self) {
+                break;
+            }
         }
+        Ok(())
     }
     /// Run program loaded into main memory.
-    pub fn run(&mut self) {
-        while self.main_memory[self.get_pc()] != 0 {
-            // Fetch and decode a new instruction.
-            self.fetch_and_decode(); // This function will invoke the execute function.
-            self.incr_pc(); // Increment the program counter.
+    pub fn run(&mut self) -> Result<(), VmError> {
+        self.run_while(|_| true)
+    }
+    /// Execute at most the given number of instructions.
+    pub fn run_for(&mut self, n_instructions: u64) -> Result<(), VmError> {
+        let mut n_remaining = n_instructions;
+        if n_remaining == 0 {
+            return Ok(());
         }
+        self.run_while(|_| {
+            n_remaining -= 1;
+            n_remaining > 0
+        })
+    }
+    /// Execute instructions until the condition is met after an instruction has executed, or the
+    /// processor halts.
+    ///
+    /// # Examples:
+    /// ```
+    /// # use arm_vm::util::Register;
+    /// # use arm_vm::vm::Processor;
+    /// let mut vm = Processor::new();
+    /// // mov r6, #0x2
+    /// // mov r7, #0x3
+    /// // svc #0x0
+    /// vm.load_program(&[0x23600002, 0x23700003, 0x90000000]);
+    /// vm.run_until(|vm: &Processor| vm.get_register(Register::R6) == 0x2).unwrap();
+    /// assert_eq!(vm.get_pc(), 0x4);
+    /// ```
+    pub fn run_until<C: StopCondition>(&mut self, condition: C) -> Result<(), VmError> {
+        self.run_while(|vm| !condition.is_met(vm))
     }
 }
 
 #[cfg(test)]
 mod tests_translator {
 
-    use super::super::util::{Opcode::*, Register::*};
+    use super::super::util::{Opcode::*, Register::*, Segment};
     use super::*;
 
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
     #[test]
     fn test_set_pc() {
         let mut vm = Processor::new();
@@ -300,14 +1092,14 @@ mod tests_translator {
     fn test_incr_pc() {
         let mut vm = Processor::new();
         vm.incr_pc();
-        assert_eq!(vm.registers[PC as Address], 0x1);
+        assert_eq!(vm.registers[PC as Address], 0x4);
     }
 
     #[test]
     fn test_write_to_mm() {
         let mut vm = Processor::new();
         vm.write_to_mm(vm.get_pc(), 0x1234);
-        assert_eq!(*vm.read_from_mm(), 0x1234);
+        assert_eq!(vm.read_from_mm(vm.get_pc()), 0x1234);
     }
 
     #[test]
@@ -315,8 +1107,8 @@ mod tests_translator {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x2;
         vm.registers[R3 as usize] = 0x3;
-        let decoder = EncoderDecoder::new(Some(0x01123000));
-        vm.form_one_handler(ADD, decoder);
+        let decoder = EncoderDecoder::new(Some(0x01012300));
+        vm.form_one_handler(ADD, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x5);
     }
 
@@ -325,8 +1117,8 @@ mod tests_translator {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x2;
         vm.registers[R3 as usize] = 0x3;
-        let decoder = EncoderDecoder::new(Some(0x04123000));
-        vm.form_one_handler(AND, decoder);
+        let decoder = EncoderDecoder::new(Some(0x04012300));
+        vm.form_one_handler(AND, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x2);
     }
 
@@ -335,8 +1127,8 @@ mod tests_translator {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x2;
         vm.registers[R3 as usize] = 0x3;
-        let decoder = EncoderDecoder::new(Some(0x06123000));
-        vm.form_one_handler(EOR, decoder);
+        let decoder = EncoderDecoder::new(Some(0x06012300));
+        vm.form_one_handler(EOR, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x1);
     }
 
@@ -345,8 +1137,8 @@ mod tests_translator {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x2;
         vm.registers[R3 as usize] = 0x3;
-        let decoder = EncoderDecoder::new(Some(0x08123000));
-        vm.form_one_handler(MUL, decoder);
+        let decoder = EncoderDecoder::new(Some(0x08012300));
+        vm.form_one_handler(MUL, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x6);
     }
 
@@ -355,8 +1147,8 @@ mod tests_translator {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x2;
         vm.registers[R3 as usize] = 0x3;
-        let decoder = EncoderDecoder::new(Some(0x05123000));
-        vm.form_one_handler(ORR, decoder);
+        let decoder = EncoderDecoder::new(Some(0x05012300));
+        vm.form_one_handler(ORR, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x3);
     }
 
@@ -365,19 +1157,43 @@ mod tests_translator {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x3;
         vm.registers[R3 as usize] = 0x2;
-        let decoder = EncoderDecoder::new(Some(0x02123000));
-        vm.form_one_handler(SUB, decoder);
+        let decoder = EncoderDecoder::new(Some(0x02012300));
+        vm.form_one_handler(SUB, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x1);
     }
 
     #[test]
     fn test_form_one_ldr() {
         let mut vm = Processor::new();
-        vm.registers[R2 as usize] = 0x2;
-        vm.registers[R3 as usize] = 0x3;
-        vm.main_memory[0x2 + 0x3] = 0x1234;
-        let decoder = EncoderDecoder::new(Some(0x32123000));
-        vm.form_one_handler(LDR, decoder);
+        vm.registers[R2 as usize] = 0x4;
+        vm.registers[R3 as usize] = 0x8;
+        vm.write_to_mm(0x4 + 0x8, 0x1234);
+        let decoder = EncoderDecoder::new(Some(0x32012300));
+        vm.form_one_handler(LDR, decoder).unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0x1234);
+    }
+
+    #[test]
+    fn test_form_one_ldr_negative_index() {
+        let mut vm = Processor::new();
+        vm.registers[R2 as usize] = 0x104;
+        vm.registers[R3 as usize] = 0xFFFFFFFC;
+        vm.write_to_mm(0x100, 0x1234);
+        // ldr r1, [r2, r3]
+        vm.form_one_handler(LDR, EncoderDecoder::new(Some(0x32012300)))
+            .unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0x1234);
+    }
+
+    #[test]
+    fn test_form_one_ldr_most_negative_index() {
+        let mut vm = Processor::new();
+        vm.registers[R2 as usize] = 0x80000100;
+        vm.registers[R3 as usize] = 0x80000000;
+        vm.write_to_mm(0x100, 0x1234);
+        // ldr r1, [r2, r3]
+        vm.form_one_handler(LDR, EncoderDecoder::new(Some(0x32012300)))
+            .unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x1234);
     }
 
@@ -385,19 +1201,19 @@ mod tests_translator {
     fn test_form_one_str() {
         let mut vm = Processor::new();
         vm.registers[R1 as usize] = 0x1234;
-        vm.registers[R2 as usize] = 0x2;
-        vm.registers[R3 as usize] = 0x3;
-        let decoder = EncoderDecoder::new(Some(0x36123000));
-        vm.form_one_handler(STR, decoder);
-        assert_eq!(vm.main_memory[0x2 + 0x3], 0x1234);
+        vm.registers[R2 as usize] = 0x4;
+        vm.registers[R3 as usize] = 0x8;
+        let decoder = EncoderDecoder::new(Some(0x36012300));
+        vm.form_one_handler(STR, decoder).unwrap();
+        assert_eq!(vm.read_from_mm(0x4 + 0x8), 0x1234);
     }
 
     #[test]
     fn test_form_two_mov() {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x2;
-        let decoder = EncoderDecoder::new(Some(0x03120000));
-        vm.form_two_handler(MOV, decoder);
+        let decoder = EncoderDecoder::new(Some(0x03012000));
+        vm.form_two_handler(MOV, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x2);
     }
 
@@ -405,18 +1221,18 @@ mod tests_translator {
     fn test_form_two_mvn() {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x2;
-        let decoder = EncoderDecoder::new(Some(0x07120000));
-        vm.form_two_handler(MVN, decoder);
+        let decoder = EncoderDecoder::new(Some(0x07012000));
+        vm.form_two_handler(MVN, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0xFFFFFFFD);
     }
 
     #[test]
     fn test_form_two_ldr() {
         let mut vm = Processor::new();
-        vm.registers[R2 as usize] = 0x2;
-        vm.main_memory[0x2] = 0x1234;
-        let decoder = EncoderDecoder::new(Some(0x30120000));
-        vm.form_two_handler(LDR, decoder);
+        vm.registers[R2 as usize] = 0x4;
+        vm.write_to_mm(0x4, 0x1234);
+        let decoder = EncoderDecoder::new(Some(0x30012000));
+        vm.form_two_handler(LDR, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x1234);
     }
 
@@ -424,10 +1240,10 @@ mod tests_translator {
     fn test_form_two_str() {
         let mut vm = Processor::new();
         vm.registers[R1 as usize] = 0x1234;
-        vm.registers[R2 as usize] = 0x2;
-        let decoder = EncoderDecoder::new(Some(0x34120000));
-        vm.form_two_handler(STR, decoder);
-        assert_eq!(vm.main_memory[0x2], 0x1234);
+        vm.registers[R2 as usize] = 0x4;
+        let decoder = EncoderDecoder::new(Some(0x34012000));
+        vm.form_two_handler(STR, decoder).unwrap();
+        assert_eq!(vm.read_from_mm(0x4), 0x1234);
     }
 
     #[test]
@@ -435,7 +1251,7 @@ mod tests_translator {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x2;
         let decoder = EncoderDecoder::new(Some(0x21120004));
-        vm.form_four_handler(ADD, decoder);
+        vm.form_four_handler(ADD, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x6);
     }
 
@@ -444,7 +1260,7 @@ mod tests_translator {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x2;
         let decoder = EncoderDecoder::new(Some(0x24120003));
-        vm.form_four_handler(AND, decoder);
+        vm.form_four_handler(AND, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x2);
     }
 
@@ -453,7 +1269,7 @@ mod tests_translator {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x2;
         let decoder = EncoderDecoder::new(Some(0x26120003));
-        vm.form_four_handler(EOR, decoder);
+        vm.form_four_handler(EOR, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x1);
     }
 
@@ -462,7 +1278,7 @@ mod tests_translator {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x2;
         let decoder = EncoderDecoder::new(Some(0x28120003));
-        vm.form_four_handler(MUL, decoder);
+        vm.form_four_handler(MUL, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x6);
     }
 
@@ -471,7 +1287,7 @@ mod tests_translator {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x2;
         let decoder = EncoderDecoder::new(Some(0x25120003));
-        vm.form_four_handler(ORR, decoder);
+        vm.form_four_handler(ORR, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x3);
     }
 
@@ -480,17 +1296,17 @@ mod tests_translator {
         let mut vm = Processor::new();
         vm.registers[R2 as usize] = 0x3;
         let decoder = EncoderDecoder::new(Some(0x22120002));
-        vm.form_four_handler(SUB, decoder);
+        vm.form_four_handler(SUB, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x1);
     }
 
     #[test]
     fn test_form_four_ldr() {
         let mut vm = Processor::new();
-        vm.registers[R2 as usize] = 0x1;
-        vm.main_memory[0x2] = 0x1234;
-        let decoder = EncoderDecoder::new(Some(0x31120001));
-        vm.form_four_handler(LDR, decoder);
+        vm.registers[R2 as usize] = 0x4;
+        vm.write_to_mm(0x8, 0x1234);
+        let decoder = EncoderDecoder::new(Some(0x31120004));
+        vm.form_four_handler(LDR, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x1234);
     }
 
@@ -498,53 +1314,997 @@ mod tests_translator {
     fn test_form_four_str() {
         let mut vm = Processor::new();
         vm.registers[R1 as usize] = 0x1234;
-        vm.registers[R2 as usize] = 0x1;
-        let decoder = EncoderDecoder::new(Some(0x35120001));
-        vm.form_four_handler(STR, decoder);
-        assert_eq!(vm.main_memory[0x2], 0x1234);
+        vm.registers[R2 as usize] = 0x4;
+        let decoder = EncoderDecoder::new(Some(0x35120004));
+        vm.form_four_handler(STR, decoder).unwrap();
+        assert_eq!(vm.read_from_mm(0x8), 0x1234);
+    }
+
+    #[test]
+    fn test_form_four_ldrb() {
+        let mut vm = Processor::new();
+        vm.registers[R2 as usize] = 0x100;
+        vm.write_to_mm(0x100, 0x11223344);
+        // ldrb r1, [r2, #0x2]
+        let decoder = EncoderDecoder::new(Some(0x39120002));
+        vm.form_four_handler(LDRB, decoder).unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0x22);
+    }
+
+    #[test]
+    fn test_form_two_ldrsb_and_ldrsh() {
+        let mut vm = Processor::new();
+        vm.registers[R2 as usize] = 0x100;
+        vm.write_to_mm(0x100, 0x80F0);
+        // ldrsb r1, [r2]
+        vm.form_two_handler(LDRSB, EncoderDecoder::new(Some(0x4C012000)))
+            .unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0xFFFFFFF0);
+        // ldrsh r1, [r2]
+        vm.form_two_handler(LDRSH, EncoderDecoder::new(Some(0x50012000)))
+            .unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0xFFFF80F0);
+        // ldrh r1, [r2]
+        vm.form_two_handler(LDRH, EncoderDecoder::new(Some(0x40012000)))
+            .unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0x80F0);
+    }
+
+    #[test]
+    fn test_form_one_strb_and_strh() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0x12345678;
+        vm.registers[R2 as usize] = 0x100;
+        vm.registers[R3 as usize] = 0x1;
+        // strb r1, [r2, r3]
+        vm.form_one_handler(STRB, EncoderDecoder::new(Some(0x3E012300)))
+            .unwrap();
+        vm.registers[R3 as usize] = 0x2;
+        // strh r1, [r2, r3]
+        vm.form_one_handler(STRH, EncoderDecoder::new(Some(0x4A012300)))
+            .unwrap();
+        assert_eq!(vm.read_from_mm(0x100), 0x56787800);
+    }
+
+    #[test]
+    fn test_big_endian() {
+        let mut vm = Processor::with_config(Config {
+            endianness: Endianness::Big,
+            ..Config::default()
+        });
+        vm.registers[R2 as usize] = 0x100;
+        vm.write_to_mm(0x100, 0x11223344);
+        // ldrb r1, [r2]
+        vm.form_two_handler(LDRB, EncoderDecoder::new(Some(0x38012000)))
+            .unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0x11);
+    }
+
+    #[test]
+    fn test_unaligned_access() {
+        let mut vm = Processor::new();
+        vm.registers[R2 as usize] = 0x102;
+        // ldr r1, [r2]
+        let result = vm.form_two_handler(LDR, EncoderDecoder::new(Some(0x30012000)));
+        assert_eq!(result, Err(Fault::UnalignedAccess));
+        // ldrh r1, [r2]
+        vm.form_two_handler(LDRH, EncoderDecoder::new(Some(0x40012000)))
+            .unwrap();
+        vm.registers[R2 as usize] = 0x101;
+        let result = vm.form_two_handler(LDRH, EncoderDecoder::new(Some(0x40012000)));
+        assert_eq!(result, Err(Fault::UnalignedAccess));
+    }
+
+    #[test]
+    fn test_unaligned_access_without_alignment_checking() {
+        let mut vm = Processor::with_config(Config {
+            check_alignment: false,
+            ..Config::default()
+        });
+        vm.write_to_mm(0x100, 0x33221100);
+        vm.write_to_mm(0x104, 0x77665544);
+        vm.registers[R2 as usize] = 0x102;
+        // ldr r1, [r2]
+        vm.form_two_handler(LDR, EncoderDecoder::new(Some(0x30012000)))
+            .unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0x55443322);
+        vm.registers[R2 as usize] = 0xFFFFFFFE;
+        let result = vm.form_two_handler(LDR, EncoderDecoder::new(Some(0x30012000)));
+        assert_eq!(result, Err(Fault::MemoryOutOfRange));
+    }
+
+    #[test]
+    fn test_form_four_negative_offset() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0x104;
+        vm.write_to_mm(0x100, 0x1234);
+        // ldr r0, [r1, #-4]
+        vm.form_four_handler(LDR, EncoderDecoder::new(Some(0x3101FFFC)))
+            .unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0x1234);
+    }
+
+    #[test]
+    fn test_form_fourteen_pre_index() {
+        let mut vm = Processor::new();
+        vm.registers[R0 as usize] = 0x7;
+        vm.registers[R1 as usize] = 0x100;
+        // str r0, [r1, #-4]!
+        vm.form_fourteen_handler(STR, EncoderDecoder::new(Some(0x71001FFC)))
+            .unwrap();
+        assert_eq!(vm.read_from_mm(0xFC), 0x7);
+        assert_eq!(vm.registers[R1 as usize], 0xFC);
+    }
+
+    #[test]
+    fn test_form_fifteen_post_index() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0x100;
+        vm.write_to_mm(0x100, 0x44332211);
+        // ldrb r0, [r1], #1
+        vm.form_fifteen_handler(LDRB, EncoderDecoder::new(Some(0x7A001001)))
+            .unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0x11);
+        assert_eq!(vm.registers[R1 as usize], 0x101);
+    }
+
+    #[test]
+    fn test_form_fourteen_fault_leaves_base() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0x100;
+        // ldr r0, [r1, #2]!
+        let result = vm.form_fourteen_handler(LDR, EncoderDecoder::new(Some(0x70001002)));
+        assert_eq!(result, Err(Fault::UnalignedAccess));
+        assert_eq!(vm.registers[R1 as usize], 0x100);
+    }
+
+    #[test]
+    fn test_form_eleven_scaled_register_offset() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0x100;
+        vm.registers[R2 as usize] = 0x3;
+        vm.write_to_mm(0x10C, 0x5);
+        // ldr r0, [r1, r2, lsl #2]
+        vm.form_eleven_handler(LDR, EncoderDecoder::new(Some(0xF0001202)))
+            .unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0x5);
+        vm.registers[R1 as usize] = 0x118;
+        vm.registers[R2 as usize] = 0xFFFFFFFD;
+        vm.form_eleven_handler(LDR, EncoderDecoder::new(Some(0xF0001202)))
+            .unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0x5);
+    }
+
+    #[test]
+    fn test_load_sections() {
+        let mut vm = Processor::new();
+        let mut program = Program::from(vec![0x90000000, 0x23000007]);
+        program.segments.push(Segment {
+            name: String::from(".data"),
+            address: 0x8000,
+            words: vec![0x12345678],
+        });
+        program.entry = 0x4;
+        program.stack_top = Some(0x10000);
+        vm.load_sections(&program);
+        assert_eq!(vm.read_from_mm(0x4), 0x23000007);
+        assert_eq!(vm.read_from_mm(0x8000), 0x12345678);
+        assert_eq!(vm.get_pc(), 0x4);
+        assert_eq!(vm.get_register(SP), 0x10000);
+    }
+
+    #[test]
+    fn test_fetch_unaligned() {
+        let mut vm = Processor::new();
+        vm.set_pc(0x2);
+        let err = vm.step().unwrap_err();
+        assert_eq!(err.fault, Fault::UnalignedAccess);
     }
 
     #[test]
     fn test_form_five_mov() {
         let mut vm = Processor::new();
-        let decoder = EncoderDecoder::new(Some(0x23112345));
-        vm.form_five_handler(MOV, decoder);
-        assert_eq!(vm.registers[R1 as usize], 0x12345);
+        let decoder = EncoderDecoder::new(Some(0x23102345));
+        vm.form_five_handler(MOV, decoder).unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0x2345);
     }
 
     #[test]
     fn test_form_five_mvn() {
         let mut vm = Processor::new();
         let decoder = EncoderDecoder::new(Some(0x27100000));
-        vm.form_five_handler(MVN, decoder);
+        vm.form_five_handler(MVN, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0xFFFFFFFF);
     }
 
     #[test]
     fn test_form_five_ldr() {
         let mut vm = Processor::new();
-        vm.set_pc(0x2);
-        vm.main_memory[0x3] = 0x1234;
-        let decoder = EncoderDecoder::new(Some(0x33100001));
-        vm.form_five_handler(LDR, decoder);
+        vm.set_pc(0x8);
+        vm.write_to_mm(0xC, 0x1234);
+        let decoder = EncoderDecoder::new(Some(0x33100004));
+        vm.form_five_handler(LDR, decoder).unwrap();
         assert_eq!(vm.registers[R1 as usize], 0x1234);
     }
 
     #[test]
     fn test_form_five_str() {
         let mut vm = Processor::new();
-        vm.set_pc(0x2);
+        vm.set_pc(0x8);
         vm.registers[R1 as usize] = 0x1234;
-        let decoder = EncoderDecoder::new(Some(0x37100001));
-        vm.form_five_handler(STR, decoder);
-        assert_eq!(vm.main_memory[0x3], 0x1234);
+        let decoder = EncoderDecoder::new(Some(0x37100004));
+        vm.form_five_handler(STR, decoder).unwrap();
+        assert_eq!(vm.read_from_mm(0xC), 0x1234);
     }
 
     #[test]
     fn test_execute() {
         let mut vm = Processor::new();
-        vm.execute(R1 as Address, Box::new(move || 1 + 2));
+        vm.execute(R1 as Address, Box::new(move || Ok(1 + 2)))
+            .unwrap();
         assert_eq!(vm.registers[R1 as Address], 3);
     }
 
+    #[test]
+    fn test_form_one_sub_wraps() {
+        let mut vm = Processor::new();
+        vm.registers[R3 as usize] = 0x1;
+        let decoder = EncoderDecoder::new(Some(0x02012300));
+        vm.form_one_handler(SUB, decoder).unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0xFFFFFFFF);
+    }
+
+    #[test]
+    fn test_form_four_add_overflow_trap() {
+        let mut vm = Processor::with_config(Config {
+            trap_on_overflow: true,
+            ..Config::default()
+        });
+        vm.registers[R2 as usize] = 0x7FFFFFFF;
+        let decoder = EncoderDecoder::new(Some(0x21120001));
+        let result = vm.form_four_handler(ADD, decoder);
+        assert_eq!(result, Err(Fault::ArithmeticOverflow));
+        assert_eq!(vm.registers[R1 as usize], 0x0);
+    }
+
+    #[test]
+    fn test_form_four_ldr_out_of_range() {
+        let mut vm = Processor::new();
+        vm.registers[R2 as usize] = 0xFFFFFFFF;
+        let decoder = EncoderDecoder::new(Some(0x31120001));
+        let result = vm.form_four_handler(LDR, decoder);
+        assert_eq!(result, Err(Fault::MemoryOutOfRange));
+    }
+
+    #[test]
+    fn test_form_one_adds() {
+        let mut vm = Processor::with_config(Config {
+            trap_on_overflow: true,
+            ..Config::default()
+        });
+        vm.registers[R2 as usize] = 0x7FFFFFFF;
+        vm.registers[R3 as usize] = 0x1;
+        let decoder = EncoderDecoder::new(Some(0x11012300));
+        vm.form_one_handler(ADDS, decoder).unwrap();
+        // Overflow is reported through the flags rather than trapping.
+        assert_eq!(vm.registers[R1 as usize], 0x80000000);
+        assert!(vm.flag.get_n() && vm.flag.get_v());
+        assert!(!vm.flag.get_z() && !vm.flag.get_c());
+    }
+
+    #[test]
+    fn test_form_four_subs() {
+        let mut vm = Processor::new();
+        vm.registers[R2 as usize] = 0x3;
+        let decoder = EncoderDecoder::new(Some(0x62120003));
+        vm.form_four_handler(SUBS, decoder).unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0x0);
+        assert!(vm.flag.get_z() && vm.flag.get_c());
+    }
+
+    #[test]
+    fn test_form_four_sub_preserves_flags() {
+        let mut vm = Processor::new();
+        vm.registers[R2 as usize] = 0x3;
+        let decoder = EncoderDecoder::new(Some(0x22120003));
+        vm.form_four_handler(SUB, decoder).unwrap();
+        assert_eq!(vm.flag, Flag::new());
+    }
+
+    #[test]
+    fn test_form_five_movs() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0x1;
+        let decoder = EncoderDecoder::new(Some(0x63100000));
+        vm.form_five_handler(MOVS, decoder).unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0x0);
+        assert!(vm.flag.get_z() && !vm.flag.get_n());
+    }
+
+    #[test]
+    fn test_form_two_mvns() {
+        let mut vm = Processor::new();
+        let decoder = EncoderDecoder::new(Some(0x17012000));
+        vm.form_two_handler(MVNS, decoder).unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0xFFFFFFFF);
+        assert!(vm.flag.get_n() && !vm.flag.get_z());
+    }
+
+    #[test]
+    fn test_form_one_ands() {
+        let mut vm = Processor::new();
+        vm.registers[R2 as usize] = 0x2;
+        vm.registers[R3 as usize] = 0x1;
+        let decoder = EncoderDecoder::new(Some(0x14012300));
+        vm.form_one_handler(ANDS, decoder).unwrap();
+        assert!(vm.flag.get_z());
+    }
+
+    #[test]
+    fn test_form_four_muls() {
+        let mut vm = Processor::new();
+        vm.registers[R2 as usize] = 0xFFFFFFFF;
+        let decoder = EncoderDecoder::new(Some(0x68120003));
+        vm.form_four_handler(MULS, decoder).unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0xFFFFFFFD);
+        assert!(vm.flag.get_n() && !vm.flag.get_v());
+    }
+
+    #[test]
+    fn test_form_five_cmp_carry() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0x3;
+        // As on ARM, the carry flag is set when the subtraction does not borrow.
+        vm.form_five_handler(CMP, EncoderDecoder::new(Some(0x57100002)))
+            .unwrap();
+        assert!(vm.flag.get_c());
+        vm.form_five_handler(CMP, EncoderDecoder::new(Some(0x57100004)))
+            .unwrap();
+        assert!(!vm.flag.get_c() && vm.flag.get_n());
+    }
+
+    #[test]
+    fn test_form_six_undefined_cc() {
+        let mut vm = Processor::new();
+        let decoder = EncoderDecoder::new(Some(0x80F00001));
+        let result = vm.form_six_handler(B, decoder);
+        assert_eq!(result, Err(Fault::UndefinedInstruction));
+    }
+
+    #[test]
+    fn test_form_six_branch_to_zero() {
+        let mut vm = Processor::new();
+        vm.set_pc(0xC);
+        vm.write_to_mm(0xC, 0x80000000);
+        vm.step().unwrap();
+        assert_eq!(vm.get_pc(), 0x0);
+    }
+
+    #[test]
+    fn test_step_undefined_instruction() {
+        let mut vm = Processor::new();
+        vm.load_program(&[0x23100001, 0xFF000000]);
+        vm.step().unwrap();
+        let err = vm.step().unwrap_err();
+        assert_eq!(err.fault, Fault::UndefinedInstruction);
+        assert_eq!(err.pc, 0x4);
+        assert_eq!(err.instr, 0xFF000000);
+    }
+
+    #[test]
+    fn test_step_limit_exceeded() {
+        let mut vm = Processor::with_config(Config {
+            step_limit: Some(2),
+            ..Config::default()
+        });
+        // An infinite loop.
+        vm.load_program(&[0x80000000]);
+        let err = vm.run().unwrap_err();
+        assert_eq!(err.fault, Fault::StepLimitExceeded);
+    }
+
+    #[test]
+    fn test_step_after_halt() {
+        let mut vm = Processor::new();
+        vm.load_program(&[0x23100001, 0x90000000]);
+        vm.run().unwrap();
+        assert_eq!(vm.step().unwrap_err().fault, Fault::Halted);
+    }
+
+    #[test]
+    fn test_form_seven_svc() {
+        let mut vm = Processor::new();
+        vm.registers[R0 as usize] = 0x7;
+        let decoder = EncoderDecoder::new(Some(0x90000000));
+        vm.form_seven_handler(SVC, decoder).unwrap();
+        assert!(vm.is_halted());
+        assert_eq!(vm.get_exit_code(), Some(0x7));
+    }
+
+    #[test]
+    fn test_form_seven_undefined_syscall() {
+        let mut vm = Processor::new();
+        let decoder = EncoderDecoder::new(Some(0x900FFFFF));
+        let result = vm.form_seven_handler(SVC, decoder);
+        assert_eq!(result, Err(Fault::UndefinedSyscall));
+        assert!(!vm.is_halted());
+    }
+
+    #[test]
+    fn test_set_syscall_handler() {
+        let mut handler = DefaultSyscallHandler::with_io(&b""[..], Vec::new());
+        handler.register(
+            0x10,
+            Box::new(|registers: &mut [Payload], memory: &mut Memory| {
+                memory.write(0x1234, Width::Word, registers[R1 as usize]);
+                Ok(Syscall::Continue)
+            }),
+        );
+        let mut vm = Processor::new();
+        vm.set_syscall_handler(handler);
+        // mov r1, #0x2
+        // svc #0x10
+        // svc #0x0
+        vm.load_program(&[0x23100002, 0x90000010, 0x90000000]);
+        vm.run().unwrap();
+        assert_eq!(vm.read_from_mm(0x1234), 0x2);
+        assert_eq!(vm.get_exit_code(), Some(0x0));
+    }
+
+    #[test]
+    fn test_run_zero_word_is_undefined() {
+        let mut vm = Processor::new();
+        // A zero word no longer ends the program.
+        vm.load_program(&[0x23100001]);
+        let err = vm.run().unwrap_err();
+        assert_eq!(err.fault, Fault::UndefinedInstruction);
+        assert_eq!(err.pc, 0x4);
+        assert!(!vm.is_halted());
+    }
+
+    #[test]
+    fn test_run_past_data() {
+        let mut vm = Processor::new();
+        // mov r1, #0x1
+        // svc #0x0
+        // Data placed after the program is never executed.
+        vm.load_program(&[0x23100001, 0x90000000, 0xFF000000]);
+        vm.run().unwrap();
+        assert_eq!(vm.get_pc(), 0x8);
+        assert_eq!(vm.get_exit_code(), Some(0x0));
+    }
+
+    #[test]
+    fn test_run_conditional_execution() {
+        let mut vm = Processor::new();
+        // mov r1, #0x1
+        // cmp r1, #0x1
+        // movne r2, #0x5
+        // moveq r3, #0x6
+        // svcne #0x1
+        // svc #0x0
+        vm.load_program(&[
+            0x23100001, 0x57100001, 0x93220005, 0x93130006, 0x90200001, 0x90000000,
+        ]);
+        vm.run().unwrap();
+        assert_eq!(vm.registers[R2 as usize], INIT_REGISTER_VALUE);
+        assert_eq!(vm.registers[R3 as usize], 0x6);
+        assert_eq!(vm.get_exit_code(), Some(0x0));
+    }
+
+    #[test]
+    fn test_run_subroutine() {
+        let mut vm = Processor::new();
+        // mov r0, #0x5
+        // bl double
+        // mov r2, r0
+        // svc #0x0
+        // double add r0, r0, r0
+        // bx lr
+        vm.load_program(&[
+            0x23000005, 0x81000004, 0x03020000, 0x90000000, 0x01000000, 0x820E0000,
+        ]);
+        vm.run().unwrap();
+        assert_eq!(vm.registers[R2 as usize], 0xA);
+        assert_eq!(vm.registers[LR as usize], 0x8);
+        assert_eq!(vm.get_pc(), 0x10);
+    }
+
+    #[test]
+    fn test_run_return_with_mov_pc() {
+        let mut vm = Processor::new();
+        // bl return
+        // svc #0x0
+        // return mov pc, lr
+        vm.load_program(&[0x81000002, 0x90000000, 0x030FE000]);
+        vm.run().unwrap();
+        assert_eq!(vm.get_pc(), 0x8);
+        assert_eq!(vm.get_n_steps(), 3);
+    }
+
+    #[test]
+    fn test_form_six_bl_not_taken() {
+        let mut vm = Processor::new();
+        // blne 0x4
+        vm.load_program(&[0x81200004]);
+        vm.flag.set_sub(0x1, 0x1);
+        vm.step().unwrap();
+        assert_eq!(vm.registers[LR as usize], INIT_REGISTER_VALUE);
+        assert_eq!(vm.get_pc(), 0x4);
+    }
+
+    #[test]
+    fn test_form_eleven_add_lsl() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0x1;
+        vm.registers[R2 as usize] = 0x3;
+        // add r0, r1, r2, lsl #2
+        let decoder = EncoderDecoder::new(Some(0xB1001202));
+        vm.form_eleven_handler(ADD, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0xD);
+    }
+
+    #[test]
+    fn test_form_eleven_ands_sets_shifter_carry() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0xFFFFFFFF;
+        vm.registers[R2 as usize] = 0x3;
+        vm.registers[R3 as usize] = 0x1;
+        // ands r0, r1, r2, lsr r3
+        let decoder = EncoderDecoder::new(Some(0xC40012A3));
+        vm.form_eleven_handler(ANDS, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0x1);
+        assert!(vm.flag.get_c());
+    }
+
+    #[test]
+    fn test_form_eleven_adds_ignores_shifter_carry() {
+        let mut vm = Processor::new();
+        vm.registers[R2 as usize] = 0x80000000;
+        // adds r0, r1, r2, lsl #1
+        let decoder = EncoderDecoder::new(Some(0xC1001201));
+        vm.form_eleven_handler(ADDS, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0x0);
+        assert!(vm.flag.get_z() && !vm.flag.get_c());
+    }
+
+    #[test]
+    fn test_form_twelve_movs_asr() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0x80000001;
+        // movs r0, r1, asr #1
+        let decoder = EncoderDecoder::new(Some(0xC3001041));
+        vm.form_twelve_handler(MOVS, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0xC0000000);
+        assert!(vm.flag.get_n() && vm.flag.get_c());
+    }
+
+    #[test]
+    fn test_form_twelve_shift_by_zero_keeps_carry() {
+        let mut vm = Processor::new();
+        vm.flag.set_sub(0x1, 0x0);
+        // movs r0, r1, lsl r2
+        let decoder = EncoderDecoder::new(Some(0xC3001082));
+        vm.form_twelve_handler(MOVS, decoder).unwrap();
+        assert!(vm.flag.get_z() && vm.flag.get_c());
+    }
+
+    #[test]
+    fn test_form_thirteen_mla_wraps() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0x80000000;
+        vm.registers[R2 as usize] = 0x3;
+        vm.registers[R3 as usize] = 0x5;
+        // mla r0, r1, r2, r3
+        let decoder = EncoderDecoder::new(Some(0xE0001203));
+        vm.form_thirteen_handler(MLA, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0x80000005);
+    }
+
+    #[test]
+    fn test_form_thirteen_mls() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0x2;
+        vm.registers[R2 as usize] = 0x3;
+        vm.registers[R3 as usize] = 0x5;
+        // mls r0, r1, r2, r3
+        let decoder = EncoderDecoder::new(Some(0xE1001203));
+        vm.form_thirteen_handler(MLS, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0xFFFFFFFF);
+    }
+
+    #[test]
+    fn test_form_thirteen_umull() {
+        let mut vm = Processor::new();
+        vm.registers[R2 as usize] = 0xFFFFFFFF;
+        vm.registers[R3 as usize] = 0xFFFFFFFF;
+        // umull r0, r1, r2, r3
+        let decoder = EncoderDecoder::new(Some(0xE2001203));
+        vm.form_thirteen_handler(UMULL, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0x00000001);
+        assert_eq!(vm.registers[R1 as usize], 0xFFFFFFFE);
+    }
+
+    #[test]
+    fn test_form_thirteen_smull() {
+        let mut vm = Processor::new();
+        vm.registers[R2 as usize] = 0xFFFFFFFE;
+        vm.registers[R3 as usize] = 0x3;
+        // smull r0, r1, r2, r3
+        let decoder = EncoderDecoder::new(Some(0xE3001203));
+        vm.form_thirteen_handler(SMULL, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0xFFFFFFFA);
+        assert_eq!(vm.registers[R1 as usize], 0xFFFFFFFF);
+    }
+
+    #[test]
+    fn test_form_thirteen_umlal_carries_into_high_word() {
+        let mut vm = Processor::new();
+        vm.registers[R0 as usize] = 0xFFFFFFFF;
+        vm.registers[R1 as usize] = 0x1;
+        vm.registers[R2 as usize] = 0x1;
+        vm.registers[R3 as usize] = 0x1;
+        // umlal r0, r1, r2, r3
+        let decoder = EncoderDecoder::new(Some(0xE4001203));
+        vm.form_thirteen_handler(UMLAL, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0x0);
+        assert_eq!(vm.registers[R1 as usize], 0x2);
+    }
+
+    #[test]
+    fn test_form_one_sdiv() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = -7i32 as Payload;
+        vm.registers[R2 as usize] = 0x2;
+        // sdiv r0, r1, r2
+        let decoder = EncoderDecoder::new(Some(0x0D001200));
+        vm.form_one_handler(SDIV, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], -3i32 as Payload);
+        vm.registers[R1 as usize] = 0x80000000;
+        vm.registers[R2 as usize] = 0xFFFFFFFF;
+        let decoder = EncoderDecoder::new(Some(0x0D001200));
+        vm.form_one_handler(SDIV, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0x80000000);
+    }
+
+    #[test]
+    fn test_form_one_udiv_by_zero() {
+        let mut vm = Processor::new();
+        vm.registers[R0 as usize] = 0x5;
+        vm.registers[R1 as usize] = 0x7;
+        // udiv r0, r1, r2
+        let decoder = EncoderDecoder::new(Some(0x0E001200));
+        vm.form_one_handler(UDIV, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0x0);
+    }
+
+    #[test]
+    fn test_form_one_udiv_by_zero_trap() {
+        let mut vm = Processor::with_config(Config {
+            trap_on_divide_by_zero: true,
+            ..Config::default()
+        });
+        vm.registers[R1 as usize] = 0x7;
+        let decoder = EncoderDecoder::new(Some(0x0E001200));
+        let result = vm.form_one_handler(UDIV, decoder);
+        assert_eq!(result, Err(Fault::DivideByZero));
+    }
+
+    #[test]
+    fn test_form_one_lsl_uses_least_significant_byte() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0x1;
+        vm.registers[R2 as usize] = 0x104;
+        // lsl r0, r1, r2
+        let decoder = EncoderDecoder::new(Some(0x09001200));
+        vm.form_one_handler(LSL, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0x10);
+    }
+
+    #[test]
+    fn test_form_four_lsrs() {
+        let mut vm = Processor::new();
+        vm.registers[R1 as usize] = 0x3;
+        // lsrs r0, r1, #1
+        let decoder = EncoderDecoder::new(Some(0x6A010001));
+        vm.form_four_handler(LSRS, decoder).unwrap();
+        assert_eq!(vm.registers[R0 as usize], 0x1);
+        assert!(vm.flag.get_c() && !vm.flag.get_z());
+    }
+
+    #[test]
+    fn test_initial_stack_pointer() {
+        let vm = Processor::new();
+        assert_eq!(vm.get_register(SP), INIT_STACK_POINTER);
+        let vm = Processor::with_config(Config {
+            stack_pointer: 0x8000,
+            ..Config::default()
+        });
+        assert_eq!(vm.get_register(SP), 0x8000);
+    }
+
+    #[test]
+    fn test_run_push_and_pop() {
+        let mut vm = Processor::new();
+        // mov r4, #0x4
+        // mov lr, #0x5
+        // push {r4, lr}
+        // mov r4, #0x0
+        // pop {r1, r4}
+        // svc #0x0
+        vm.load_program(&[
+            0x23400004, 0x23E00005, 0xA5004010, 0x23400000, 0xA4000012, 0x90000000,
+        ]);
+        vm.run().unwrap();
+        assert_eq!(vm.read_from_mm(INIT_STACK_POINTER as Address - 8), 0x4);
+        assert_eq!(vm.read_from_mm(INIT_STACK_POINTER as Address - 4), 0x5);
+        assert_eq!(vm.registers[R1 as usize], 0x4);
+        assert_eq!(vm.registers[R4 as usize], 0x5);
+        assert_eq!(vm.get_register(SP), INIT_STACK_POINTER);
+    }
+
+    #[test]
+    fn test_run_recursion() {
+        let mut vm = Processor::new();
+        // mov r0, #0x3
+        // bl sum
+        // svc #0x0
+        // sum push {r4, lr}
+        // mov r4, r0
+        // subs r0, r0, #0x1
+        // blne sum
+        // add r0, r0, r4
+        // pop {r4, pc}
+        vm.load_program(&[
+            0x23000003, 0x81000003, 0x90000000, 0xA5004010, 0x03040000, 0x62000001, 0x81200003,
+            0x01000400, 0xA4008010,
+        ]);
+        vm.run().unwrap();
+        assert_eq!(vm.get_exit_code(), Some(0x6));
+        assert_eq!(vm.get_register(SP), INIT_STACK_POINTER);
+    }
+
+    #[test]
+    fn test_form_nine_ldmia_writeback() {
+        let mut vm = Processor::new();
+        vm.registers[R0 as usize] = 0x100;
+        vm.write_to_mm(0x100, 0x1);
+        vm.write_to_mm(0x104, 0x2);
+        // ldmia r0!, {r1, r2}
+        let decoder = EncoderDecoder::new(Some(0xA1000006));
+        vm.form_nine_handler(LDMIA, decoder).unwrap();
+        assert_eq!(vm.registers[R1 as usize], 0x1);
+        assert_eq!(vm.registers[R2 as usize], 0x2);
+        assert_eq!(vm.registers[R0 as usize], 0x108);
+    }
+
+    #[test]
+    fn test_form_nine_ldmia_writeback_fault() {
+        let mut vm = Processor::new();
+        vm.registers[R0 as usize] = 0xFFFFFFF8;
+        vm.write_to_mm(0xFFFFFFF8, 0x1);
+        // ldmia r0!, {r1, r2, r3}
+        let decoder = EncoderDecoder::new(Some(0xA100000E));
+        assert_eq!(
+            vm.form_nine_handler(LDMIA, decoder),
+            Err(Fault::MemoryOutOfRange)
+        );
+        assert_eq!(vm.registers[R1 as usize], 0x1);
+        assert_eq!(vm.registers[R0 as usize], 0xFFFFFFF8);
+    }
+
+    #[test]
+    fn test_form_eight_stmdb_without_writeback() {
+        let mut vm = Processor::new();
+        vm.registers[R0 as usize] = 0x100;
+        vm.registers[R1 as usize] = 0x7;
+        // stmdb r0, {r1}
+        let decoder = EncoderDecoder::new(Some(0xA2000002));
+        vm.form_eight_handler(STMDB, decoder).unwrap();
+        assert_eq!(vm.read_from_mm(0xFC), 0x7);
+        assert_eq!(vm.registers[R0 as usize], 0x100);
+    }
+
+    #[test]
+    fn test_run_stack_overflow() {
+        let mut vm = Processor::with_config(Config {
+            stack_pointer: 0x108,
+            stack_limit: Some(0x100),
+            ..Config::default()
+        });
+        // push {r0}
+        // push {r0, r1}
+        vm.load_program(&[0xA5000001, 0xA5000003]);
+        let err = vm.run().unwrap_err();
+        assert_eq!(err.fault, Fault::StackOverflow);
+        assert_eq!(err.pc, 0x4);
+        assert_eq!(vm.get_register(SP), 0x104);
+    }
+
+    #[test]
+    fn test_run_stack_overflow_pre_index() {
+        let mut vm = Processor::with_config(Config {
+            stack_pointer: 0x104,
+            stack_limit: Some(0x100),
+            ..Config::default()
+        });
+        // str r0, [sp, #-4]!
+        // strb r0, [sp, #-1]!
+        vm.load_program(&[0x7100DFFC, 0x7300DFFF]);
+        let err = vm.run().unwrap_err();
+        assert_eq!(err.fault, Fault::StackOverflow);
+        assert_eq!(err.pc, 0x4);
+        assert_eq!(vm.get_register(SP), 0x100);
+    }
+
+    #[test]
+    fn test_run_undefined_condition() {
+        let mut vm = Processor::new();
+        // The condition field of this mov r1, #0x1 holds no condition code.
+        vm.load_program(&[0x93F10001]);
+        let err = vm.run().unwrap_err();
+        assert_eq!(err.fault, Fault::UndefinedInstruction);
+    }
+
+    /// Record the events raised by the processor.
+    struct Recorder(Rc<RefCell<Vec<Event>>>);
+
+    impl Tracer for Recorder {
+        fn trace(&mut self, event: &Event) {
+            self.0.borrow_mut().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn test_attach_tracer() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut vm = Processor::new();
+        vm.attach_tracer(Recorder(events.clone()));
+        vm.registers[R2 as usize] = 0x4;
+        // str r2, [r2, #0x4]
+        vm.load_program(&[0x35220004]);
+        vm.step().unwrap();
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                Event::Fetch {
+                    pc: 0x0,
+                    instr: 0x35220004,
+                },
+                Event::Decode {
+                    form: Form::Four,
+                    opcode: STR,
+                },
+                Event::RegisterRead {
+                    operand: Operand::Dr,
+                    register: R2 as Address,
+                    value: 0x4,
+                },
+                Event::RegisterRead {
+                    operand: Operand::Rx,
+                    register: R2 as Address,
+                    value: 0x4,
+                },
+                Event::Immediate {
+                    n_bits: 16,
+                    value: 0x4,
+                },
+                Event::MemoryWrite {
+                    address: 0x8,
+                    before: 0x0,
+                    value: 0x4,
+                },
+                Event::Retire {
+                    cycle: 1,
+                    flag: Flag::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attach_multiple_tracers() {
+        let first = Rc::new(RefCell::new(Vec::new()));
+        let second = Rc::new(RefCell::new(Vec::new()));
+        let mut vm = counter();
+        vm.attach_tracer(Recorder(first.clone()));
+        vm.attach_tracer(Recorder(second.clone()));
+        vm.run_for(4).unwrap();
+        assert_eq!(*first.borrow(), *second.borrow());
+        let branch = Event::Branch {
+            cc: NE,
+            taken: true,
+            target: 0x4,
+        };
+        let events = first.borrow();
+        assert_eq!(events[events.len() - 2], branch);
+        assert!(events.iter().any(|event| match event {
+            Event::FlagUpdate { before, after } => !before.get_n() && after.get_n(),
+            _ => false,
+        }));
+    }
+
+    /// Load a program counting from 0 to 3 in R1.
+    fn counter() -> Processor {
+        let mut vm = Processor::new();
+        // mov r1, #0x0
+        // add r1, r1, #0x1 ; Loop.
+        // cmp r1, #0x3
+        // bne Loop
+        // svc #0x0
+        vm.load_program(&[0x23100000, 0x21110001, 0x57100003, 0x80200001, 0x90000000]);
+        vm
+    }
+
+    #[test]
+    fn test_step() {
+        let mut vm = counter();
+        let step = vm.step().unwrap();
+        assert_eq!(
+            step,
+            Step {
+                pc: 0x0,
+                instr: 0x23100000,
+                form: Form::Five,
+                opcode: MOV,
+            }
+        );
+        assert_eq!(vm.get_pc(), 0x4);
+        assert_eq!(vm.get_n_steps(), 1);
+    }
+
+    #[test]
+    fn test_run_for() {
+        let mut vm = counter();
+        vm.run_for(3).unwrap();
+        assert_eq!(vm.get_pc(), 0xC);
+        assert_eq!(vm.get_register(R1), 0x1);
+        assert!(!vm.is_halted());
+    }
+
+    #[test]
+    fn test_run_for_stops_at_end_of_program() {
+        let mut vm = counter();
+        vm.run_for(100).unwrap();
+        assert!(vm.is_halted());
+        assert_eq!(vm.get_n_steps(), 1 + 3 * 3 + 1);
+    }
+
+    #[test]
+    fn test_run_until_pc() {
+        let mut vm = counter();
+        vm.run_until(0xC).unwrap();
+        assert_eq!(vm.get_n_steps(), 3);
+        // The condition is only checked once an instruction has executed.
+        vm.run_until(0xC).unwrap();
+        assert_eq!(vm.get_n_steps(), 6);
+    }
+
+    #[test]
+    fn test_run_until_predicate() {
+        let mut vm = counter();
+        vm.run_until(|vm: &Processor| vm.get_register(R1) == 0x2)
+            .unwrap();
+        assert_eq!(vm.get_pc(), 0x8);
+        assert!(!vm.get_flag().get_z());
+    }
+
+    #[test]
+    fn test_get_registers() {
+        let mut vm = counter();
+        vm.run().unwrap();
+        assert_eq!(vm.get_registers().len(), 16);
+        assert_eq!(vm.get_registers()[R1 as usize], 0x3);
+        assert!(vm.get_flag().get_z());
+    }
+
+    #[test]
+    fn test_read_range_from_mm() {
+        let vm = counter();
+        assert_eq!(
+            vm.read_range_from_mm(0x8..0x18),
+            vec![0x57100003, 0x80200001, 0x90000000, 0x0]
+        );
+    }
+
 }
@@ -0,0 +1,74 @@
+use super::Payload;
+use crate::util::Shift;
+
+/// Shift the value by the given amount as the barrel shifter does, returning the result along with
+/// the carry out of the shifter. Shifting by zero produces no carry, leaving the carry flag
+/// untouched, and amounts of 32 or more behave as on ARM.
+pub fn shift(shift: Shift, value: Payload, amount: Payload) -> (Payload, Option<bool>) {
+    // Get a bit of the value being shifted.
+    let bit = |n: Payload| (value >> n) & 1 == 1;
+    if amount == 0 {
+        return (value, None);
+    }
+    match shift {
+        Shift::LSL => match amount {
+            1..=31 => (value << amount, Some(bit(32 - amount))),
+            32 => (0, Some(bit(0))),
+            _ => (0, Some(false)),
+        },
+        Shift::LSR => match amount {
+            1..=31 => (value >> amount, Some(bit(amount - 1))),
+            32 => (0, Some(bit(31))),
+            _ => (0, Some(false)),
+        },
+        Shift::ASR => match amount {
+            1..=31 => (((value as i32) >> amount) as Payload, Some(bit(amount - 1))),
+            // Every bit is shifted out, leaving copies of the sign bit.
+            _ => (((value as i32) >> 31) as Payload, Some(bit(31))),
+        },
+        Shift::ROR => {
+            let result = value.rotate_right(amount % 32);
+            (result, Some(result >> 31 == 1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_shift() {
+        // Reference results of MOVS r0, r1, <shift> r2 on ARM.
+        let cases = [
+            (Shift::LSL, 0x80000001, 0, 0x80000001, None),
+            (Shift::LSL, 0x80000001, 1, 0x00000002, Some(true)),
+            (Shift::LSL, 0x40000001, 1, 0x80000002, Some(false)),
+            (Shift::LSL, 0x00000001, 32, 0x00000000, Some(true)),
+            (Shift::LSL, 0xFFFFFFFF, 33, 0x00000000, Some(false)),
+            (Shift::LSR, 0x00000003, 1, 0x00000001, Some(true)),
+            (Shift::LSR, 0x80000000, 31, 0x00000001, Some(false)),
+            (Shift::LSR, 0x80000000, 32, 0x00000000, Some(true)),
+            (Shift::LSR, 0xFFFFFFFF, 40, 0x00000000, Some(false)),
+            (Shift::ASR, 0x80000000, 4, 0xF8000000, Some(false)),
+            (Shift::ASR, 0x7FFFFFF8, 4, 0x07FFFFFF, Some(true)),
+            (Shift::ASR, 0x80000000, 32, 0xFFFFFFFF, Some(true)),
+            (Shift::ASR, 0x40000000, 100, 0x00000000, Some(false)),
+            (Shift::ROR, 0x00000001, 1, 0x80000000, Some(true)),
+            (Shift::ROR, 0x000000F0, 4, 0x0000000F, Some(false)),
+            (Shift::ROR, 0x80000000, 32, 0x80000000, Some(true)),
+        ];
+        for &(kind, value, amount, result, carry) in cases.iter() {
+            assert_eq!(
+                shift(kind, value, amount),
+                (result, carry),
+                "{:#X} {:?} {}",
+                value,
+                kind,
+                amount
+            );
+        }
+    }
+
+}
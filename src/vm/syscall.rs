@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Stdin, Stdout, Write};
+
+use super::{Address, Fault, Memory, Payload, Width, N_BYTES_IN_MAIN_MEMORY};
+use crate::util::Register;
+
+/// The supervisor call halting the processor with the status held in R0.
+pub const SYS_EXIT: Payload = 0x0;
+/// The supervisor call writing the character held in R0 to the output.
+pub const SYS_PUTCHAR: Payload = 0x1;
+/// The supervisor call writing the zero-terminated string of bytes pointed to by R0 to the output.
+pub const SYS_PUTS: Payload = 0x2;
+/// The supervisor call writing the signed integer held in R0 to the output.
+pub const SYS_PRINT_INT: Payload = 0x3;
+/// The supervisor call reading a character from the input into R0, or -1 at the end of the input.
+pub const SYS_GETCHAR: Payload = 0x4;
+
+/// What the processor does once a supervisor call has been handled.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Syscall {
+    /// Resume execution at the next instruction.
+    Continue,
+    /// Halt the processor with the given exit status.
+    Exit(Payload),
+}
+
+/// A host service invoked by the processor when an SVC instruction executes.
+pub trait SyscallHandler {
+    /// Handle the supervisor call with the given number, the immediate of the SVC instruction.
+    /// Arguments and results are passed through the registers.
+    fn call(
+        &mut self,
+        number: Payload,
+        registers: &mut [Payload],
+        memory: &mut Memory,
+    ) -> Result<Syscall, Fault>;
+}
+
+/// A supervisor call registered by an embedder.
+pub type Service = Box<dyn FnMut(&mut [Payload], &mut Memory) -> Result<Syscall, Fault>>;
+
+/// Provides exit, putchar, puts, print_int and getchar on top of the given input and output.
+/// Services registered by an embedder take precedence over these.
+pub struct DefaultSyscallHandler<R, W> {
+    input: R,
+    output: W,
+    services: HashMap<Payload, Service>,
+}
+
+impl Default for DefaultSyscallHandler<BufReader<Stdin>, Stdout> {
+    fn default() -> DefaultSyscallHandler<BufReader<Stdin>, Stdout> {
+        DefaultSyscallHandler::with_io(BufReader::new(io::stdin()), io::stdout())
+    }
+}
+
+impl DefaultSyscallHandler<BufReader<Stdin>, Stdout> {
+    /// Instantiate a handler reading from the standard input and writing to the standard output.
+    pub fn new() -> DefaultSyscallHandler<BufReader<Stdin>, Stdout> {
+        DefaultSyscallHandler::default()
+    }
+}
+
+impl<R: BufRead, W: Write> DefaultSyscallHandler<R, W> {
+    /// Instantiate a handler reading from and writing to the given streams.
+    pub fn with_io(input: R, output: W) -> DefaultSyscallHandler<R, W> {
+        DefaultSyscallHandler {
+            input,
+            output,
+            services: HashMap::new(),
+        }
+    }
+    /// Register a service under the given supervisor call number, replacing any service
+    /// previously registered under it.
+    pub fn register(&mut self, number: Payload, service: Service) {
+        self.services.insert(number, service);
+    }
+    /// Write bytes to the output, making them visible immediately.
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Fault> {
+        self.output
+            .write_all(bytes)
+            .and_then(|_| self.output.flush())
+            .map_err(|_| Fault::SyscallFailed)
+    }
+    /// Read a single byte from the input, if any is left.
+    fn read(&mut self) -> Result<Option<u8>, Fault> {
+        let byte = match self.input.fill_buf() {
+            Ok(buf) => buf.first().cloned(),
+            Err(_) => return Err(Fault::SyscallFailed),
+        };
+        if byte.is_some() {
+            self.input.consume(1);
+        }
+        Ok(byte)
+    }
+}
+
+impl<R: BufRead, W: Write> SyscallHandler for DefaultSyscallHandler<R, W> {
+    fn call(
+        &mut self,
+        number: Payload,
+        registers: &mut [Payload],
+        memory: &mut Memory,
+    ) -> Result<Syscall, Fault> {
+        if let Some(service) = self.services.get_mut(&number) {
+            return service(registers, memory);
+        }
+        let r0 = registers[Register::R0 as Address];
+        match number {
+            SYS_EXIT => return Ok(Syscall::Exit(r0)),
+            SYS_PUTCHAR => self.write(&[r0 as u8])?,
+            SYS_PUTS => {
+                let mut bytes = Vec::new();
+                let mut address = r0 as Address;
+                loop {
+                    let payload = memory.read(address, Width::Byte);
+                    if payload == 0 {
+                        break;
+                    }
+                    bytes.push(payload as u8);
+                    address = match address.checked_add(1) {
+                        Some(address) if (address as u64) < N_BYTES_IN_MAIN_MEMORY => address,
+                        _ => return Err(Fault::MemoryOutOfRange),
+                    };
+                }
+                self.write(&bytes)?
+            }
+            SYS_PRINT_INT => self.write((r0 as i32).to_string().as_bytes())?,
+            SYS_GETCHAR => {
+                registers[Register::R0 as Address] = match self.read()? {
+                    Some(byte) => byte as Payload,
+                    None => -1i32 as Payload,
+                }
+            }
+            _ => return Err(Fault::UndefinedSyscall),
+        }
+        Ok(Syscall::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::INIT_REGISTER_VALUE;
+    use super::*;
+
+    fn handler(input: &str) -> DefaultSyscallHandler<&[u8], Vec<u8>> {
+        DefaultSyscallHandler::with_io(input.as_bytes(), Vec::new())
+    }
+
+    fn registers(r0: Payload) -> Vec<Payload> {
+        let mut registers = vec![INIT_REGISTER_VALUE; 16];
+        registers[Register::R0 as Address] = r0;
+        registers
+    }
+
+    #[test]
+    fn test_exit() {
+        let mut memory = Memory::new();
+        let result = handler("").call(SYS_EXIT, &mut registers(0x7), &mut memory);
+        assert_eq!(result, Ok(Syscall::Exit(0x7)));
+    }
+
+    #[test]
+    fn test_putchar() {
+        let mut handler = handler("");
+        let result = handler.call(SYS_PUTCHAR, &mut registers(0x61), &mut Memory::new());
+        assert_eq!(result, Ok(Syscall::Continue));
+        assert_eq!(handler.output, b"a");
+    }
+
+    #[test]
+    fn test_puts() {
+        let mut handler = handler("");
+        let mut memory = Memory::new();
+        for (offset, c) in "hi\n".bytes().enumerate() {
+            memory.write(0x100 + offset, Width::Byte, c as Payload);
+        }
+        handler
+            .call(SYS_PUTS, &mut registers(0x100), &mut memory)
+            .unwrap();
+        assert_eq!(handler.output, b"hi\n");
+    }
+
+    #[test]
+    fn test_print_int() {
+        let mut handler = handler("");
+        handler
+            .call(
+                SYS_PRINT_INT,
+                &mut registers(0xFFFFFFFE),
+                &mut Memory::new(),
+            )
+            .unwrap();
+        assert_eq!(handler.output, b"-2");
+    }
+
+    #[test]
+    fn test_getchar() {
+        let mut handler = handler("a");
+        let mut registers = registers(0x0);
+        handler
+            .call(SYS_GETCHAR, &mut registers, &mut Memory::new())
+            .unwrap();
+        assert_eq!(registers[Register::R0 as Address], 0x61);
+        handler
+            .call(SYS_GETCHAR, &mut registers, &mut Memory::new())
+            .unwrap();
+        assert_eq!(registers[Register::R0 as Address], 0xFFFFFFFF);
+    }
+
+    #[test]
+    fn test_undefined_syscall() {
+        let result = handler("").call(0xFF, &mut registers(0x0), &mut Memory::new());
+        assert_eq!(result, Err(Fault::UndefinedSyscall));
+    }
+
+    #[test]
+    fn test_register() {
+        let mut handler = handler("");
+        handler.register(
+            0xFF,
+            Box::new(|registers: &mut [Payload], _: &mut Memory| {
+                registers[Register::R1 as Address] = 0x1234;
+                Ok(Syscall::Continue)
+            }),
+        );
+        let mut registers = registers(0x0);
+        handler
+            .call(0xFF, &mut registers, &mut Memory::new())
+            .unwrap();
+        assert_eq!(registers[Register::R1 as Address], 0x1234);
+    }
+
+    #[test]
+    fn test_register_overrides_default() {
+        let mut handler = handler("");
+        handler.register(
+            SYS_EXIT,
+            Box::new(|_: &mut [Payload], _: &mut Memory| Ok(Syscall::Continue)),
+        );
+        let result = handler.call(SYS_EXIT, &mut registers(0x0), &mut Memory::new());
+        assert_eq!(result, Ok(Syscall::Continue));
+    }
+
+}
@@ -0,0 +1,329 @@
+use std::io::{self, Write};
+
+use super::{Address, Event, Flag, Payload, Tracer};
+use crate::util::{get_name, Form, Instruction, Opcode};
+
+/// The format of the records written by a trace writer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TraceFormat {
+    /// One JSON object per line.
+    JsonLines,
+    /// Comma-separated values preceded by a header.
+    Csv,
+}
+
+/// The header of a trace written as comma-separated values.
+const CSV_HEADER: &str =
+    "cycle,pc,instr,opcode,form,operands,registers,memory,flags_before,flags_after";
+
+/// An operand read by an instruction: either a register or an immediate value.
+struct Operand {
+    name: String,
+    register: Option<Address>,
+    value: Payload,
+}
+
+/// Everything recorded about a single executed instruction.
+struct Record {
+    pc: Address,
+    instr: Instruction,
+    form: Option<Form>,
+    opcode: Option<Opcode>,
+    operands: Vec<Operand>,
+    /// The registers written by the instruction along with their contents before and after.
+    registers: Vec<(Address, Payload, Payload)>,
+    /// The addresses of main memory written by the instruction along with their contents before
+    /// and after.
+    memory: Vec<(Address, Payload, Payload)>,
+    flags_before: Option<Flag>,
+}
+
+impl Record {
+    fn new(pc: Address, instr: Instruction) -> Record {
+        Record {
+            pc,
+            instr,
+            form: None,
+            opcode: None,
+            operands: Vec::new(),
+            registers: Vec::new(),
+            memory: Vec::new(),
+            flags_before: None,
+        }
+    }
+}
+
+/// Format the condition flags as a string of bits in NZCV order.
+fn nzcv(flag: &Flag) -> String {
+    [flag.get_n(), flag.get_z(), flag.get_c(), flag.get_v()]
+        .iter()
+        .map(|&bit| if bit { '1' } else { '0' })
+        .collect()
+}
+
+/// Write a record of every executed instruction to the output, for consumption by other tools.
+/// An instruction raising a fault is not recorded.
+pub struct TraceWriter<W> {
+    output: W,
+    format: TraceFormat,
+    record: Option<Record>,
+}
+
+impl<W: Write> TraceWriter<W> {
+    /// Instantiate a trace writer, writing the header of the format to the output if it has one.
+    pub fn new(mut output: W, format: TraceFormat) -> io::Result<TraceWriter<W>> {
+        if format == TraceFormat::Csv {
+            writeln!(output, "{}", CSV_HEADER)?;
+        }
+        Ok(TraceWriter {
+            output,
+            format,
+            record: None,
+        })
+    }
+    fn write_json(&mut self, cycle: u64, record: &Record, flags_after: &Flag) -> io::Result<()> {
+        let operands: Vec<String> = record
+            .operands
+            .iter()
+            .map(|operand| match operand.register {
+                Some(register) => format!(
+                    "{{\"operand\":\"{}\",\"register\":\"{}\",\"value\":{}}}",
+                    operand.name,
+                    get_name(register),
+                    operand.value
+                ),
+                None => format!(
+                    "{{\"operand\":\"{}\",\"value\":{}}}",
+                    operand.name, operand.value
+                ),
+            })
+            .collect();
+        let registers: Vec<String> = record
+            .registers
+            .iter()
+            .map(|&(register, before, value)| {
+                format!(
+                    "{{\"register\":\"{}\",\"before\":{},\"value\":{}}}",
+                    get_name(register),
+                    before,
+                    value
+                )
+            })
+            .collect();
+        let memory: Vec<String> = record
+            .memory
+            .iter()
+            .map(|&(address, before, value)| {
+                format!(
+                    "{{\"address\":{},\"before\":{},\"value\":{}}}",
+                    address, before, value
+                )
+            })
+            .collect();
+        writeln!(
+            self.output,
+            "{{\"cycle\":{},\"pc\":{},\"instr\":{},\"opcode\":\"{}\",\"form\":\"{}\",\
+             \"operands\":[{}],\"registers\":[{}],\"memory\":[{}],\
+             \"flags_before\":\"{}\",\"flags_after\":\"{}\"}}",
+            cycle,
+            record.pc,
+            record.instr,
+            record
+                .opcode
+                .as_ref()
+                .map(|opcode| format!("{:?}", opcode))
+                .unwrap_or_default(),
+            record
+                .form
+                .map(|form| format!("{:?}", form))
+                .unwrap_or_default(),
+            operands.join(","),
+            registers.join(","),
+            memory.join(","),
+            nzcv(record.flags_before.as_ref().unwrap_or(flags_after)),
+            nzcv(flags_after)
+        )
+    }
+    fn write_csv(&mut self, cycle: u64, record: &Record, flags_after: &Flag) -> io::Result<()> {
+        // Lists are separated by spaces so that they never need to be quoted.
+        let operands: Vec<String> = record
+            .operands
+            .iter()
+            .map(|operand| match operand.register {
+                Some(register) => format!(
+                    "{}:{}={:#010X}",
+                    operand.name,
+                    get_name(register),
+                    operand.value
+                ),
+                None => format!("{}={:#010X}", operand.name, operand.value),
+            })
+            .collect();
+        let registers: Vec<String> = record
+            .registers
+            .iter()
+            .map(|&(register, before, value)| {
+                format!("{}={:#010X}->{:#010X}", get_name(register), before, value)
+            })
+            .collect();
+        let memory: Vec<String> = record
+            .memory
+            .iter()
+            .map(|&(address, before, value)| {
+                format!("[{:#010X}]={:#010X}->{:#010X}", address, before, value)
+            })
+            .collect();
+        writeln!(
+            self.output,
+            "{},{:#010X},{:#010X},{},{},{},{},{},{},{}",
+            cycle,
+            record.pc,
+            record.instr,
+            record
+                .opcode
+                .as_ref()
+                .map(|opcode| format!("{:?}", opcode))
+                .unwrap_or_default(),
+            record
+                .form
+                .map(|form| format!("{:?}", form))
+                .unwrap_or_default(),
+            operands.join(" "),
+            registers.join(" "),
+            memory.join(" "),
+            nzcv(record.flags_before.as_ref().unwrap_or(flags_after)),
+            nzcv(flags_after)
+        )
+    }
+}
+
+impl<W: Write> Tracer for TraceWriter<W> {
+    fn trace(&mut self, event: &Event) {
+        if let Event::Fetch { pc, instr } = *event {
+            self.record = Some(Record::new(pc, instr));
+            return;
+        }
+        if let Event::Retire { cycle, ref flag } = *event {
+            if let Some(record) = self.record.take() {
+                // Tracing is best effort and must not interrupt the execution of the program.
+                let _ = match self.format {
+                    TraceFormat::JsonLines => self.write_json(cycle, &record, flag),
+                    TraceFormat::Csv => self.write_csv(cycle, &record, flag),
+                };
+            }
+            return;
+        }
+        let record = match self.record {
+            Some(ref mut record) => record,
+            None => return,
+        };
+        match *event {
+            Event::Decode { form, ref opcode } => {
+                record.form = Some(form);
+                record.opcode = Some(opcode.clone());
+            }
+            Event::Immediate { n_bits, value } => record.operands.push(Operand {
+                name: format!("Immed{}", n_bits),
+                register: None,
+                value,
+            }),
+            Event::RegisterRead {
+                operand,
+                register,
+                value,
+            } => record.operands.push(Operand {
+                name: format!("{:?}", operand),
+                register: Some(register),
+                value,
+            }),
+            Event::RegisterWrite {
+                register,
+                before,
+                value,
+            } => record.registers.push((register, before, value)),
+            Event::MemoryWrite {
+                address,
+                before,
+                value,
+            } => record.memory.push((address, before, value)),
+            Event::FlagUpdate { before, .. } => record.flags_before = Some(before),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::Processor;
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// A shared buffer, so that the trace can be inspected once the processor owns the writer.
+    #[derive(Clone)]
+    struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Run a program and return its trace.
+    fn trace(program: &[Instruction], format: TraceFormat) -> String {
+        let buffer = Buffer(Rc::new(RefCell::new(Vec::new())));
+        let mut vm = Processor::new();
+        vm.attach_tracer(TraceWriter::new(buffer.clone(), format).unwrap());
+        vm.load_program(program);
+        vm.run().unwrap();
+        let output = buffer.0.borrow();
+        String::from_utf8(output.clone()).unwrap()
+    }
+
+    // mov r1, #0x3
+    // str r1, [r1, #0x1]
+    // cmp r1, #0x4
+    // svc #0x0
+    const PROGRAM: [Instruction; 4] = [0x23100003, 0x35110001, 0x57100004, 0x90000000];
+
+    #[test]
+    fn test_json_lines() {
+        let trace = trace(&PROGRAM, TraceFormat::JsonLines);
+        let lines: Vec<&str> = trace.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            "{\"cycle\":1,\"pc\":0,\"instr\":588251139,\"opcode\":\"MOV\",\"form\":\"Five\",\
+             \"operands\":[{\"operand\":\"Dr\",\"register\":\"R1\",\"value\":0},\
+             {\"operand\":\"Immed20\",\"value\":3}],\
+             \"registers\":[{\"register\":\"R1\",\"before\":0,\"value\":3}],\"memory\":[],\
+             \"flags_before\":\"0000\",\"flags_after\":\"0000\"}"
+        );
+        assert!(lines[1].contains("\"memory\":[{\"address\":4,\"before\":890306561,\"value\":3}]"));
+        assert!(lines[2].ends_with("\"flags_before\":\"0000\",\"flags_after\":\"1000\"}"));
+    }
+
+    #[test]
+    fn test_csv() {
+        let trace = trace(&PROGRAM, TraceFormat::Csv);
+        let lines: Vec<&str> = trace.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[2],
+            "2,0x00000004,0x35110001,STR,Four,\
+             Dr:R1=0x00000003 Rx:R1=0x00000003 Immed16=0x00000001,,\
+             [0x00000004]=0x35110001->0x00000003,0000,0000"
+        );
+        assert_eq!(
+            lines[3],
+            "3,0x00000008,0x57100004,CMP,Five,Dr:R1=0x00000003 Immed20=0x00000004,,,0000,1000"
+        );
+    }
+
+}
@@ -0,0 +1,218 @@
+use std::io::{self, Stdout, Write};
+
+use super::{Address, Flag, Payload};
+use crate::util::{get_name, ConditionCode, Form, Instruction, Opcode};
+
+/// The role of a register read by an instruction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Operand {
+    /// The destination register.
+    Dr,
+    /// The register of the first operand.
+    Rx,
+    /// The register of the second operand.
+    Ry,
+    /// The register of the third operand, or holding the amount the last operand is shifted by.
+    Rz,
+}
+
+/// An event raised by the processor while executing an instruction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// An instruction was fetched from the main memory pointed to by the program counter.
+    Fetch { pc: Address, instr: Instruction },
+    /// The form and opcode of the fetched instruction were decoded.
+    Decode { form: Form, opcode: Opcode },
+    /// An immediate value of the given width was extracted from the instruction.
+    Immediate { n_bits: u8, value: Payload },
+    /// A register was read as an operand.
+    RegisterRead {
+        operand: Operand,
+        register: Address,
+        value: Payload,
+    },
+    /// A register was written with the result of an instruction, replacing the contents it held
+    /// before.
+    RegisterWrite {
+        register: Address,
+        before: Payload,
+        value: Payload,
+    },
+    /// Data was read from main memory.
+    MemoryRead { address: Address, value: Payload },
+    /// Data was written to main memory, replacing the data stored there before.
+    MemoryWrite {
+        address: Address,
+        before: Payload,
+        value: Payload,
+    },
+    /// The condition flags were updated.
+    FlagUpdate { before: Flag, after: Flag },
+    /// A branch was evaluated under its condition code.
+    Branch {
+        cc: ConditionCode,
+        taken: bool,
+        target: Address,
+    },
+    /// The instruction was skipped because its condition did not hold.
+    Skip { cc: ConditionCode },
+    /// An instruction finished executing, leaving the condition flags in the given state.
+    Retire { cycle: u64, flag: Flag },
+}
+
+/// An observer of the execution of the processor. The processor has no tracer attached by default,
+/// so nothing is traced unless asked for.
+pub trait Tracer {
+    fn trace(&mut self, event: &Event);
+}
+
+/// Write every event to the output as column-aligned text.
+pub struct ConsoleTracer<W> {
+    output: W,
+}
+
+impl Default for ConsoleTracer<Stdout> {
+    fn default() -> ConsoleTracer<Stdout> {
+        ConsoleTracer::with_output(io::stdout())
+    }
+}
+
+impl ConsoleTracer<Stdout> {
+    /// Instantiate a tracer writing to the standard output.
+    pub fn new() -> ConsoleTracer<Stdout> {
+        ConsoleTracer::default()
+    }
+}
+
+impl<W: Write> ConsoleTracer<W> {
+    /// Instantiate a tracer writing to the given output.
+    pub fn with_output(output: W) -> ConsoleTracer<W> {
+        ConsoleTracer { output }
+    }
+}
+
+impl<W: Write> Tracer for ConsoleTracer<W> {
+    fn trace(&mut self, event: &Event) {
+        let output = &mut self.output;
+        // Tracing is best effort and must not interrupt the execution of the program.
+        let _ = match *event {
+            Event::Fetch { pc, instr } => {
+                writeln!(output, "----------------------------------------")
+                    .and_then(|_| writeln!(output, "{:30}{:#010X} ", "Pc:", pc))
+                    .and_then(|_| {
+                        writeln!(
+                            output,
+                            "{:17}{:>8} = {:#010X} ",
+                            "Instruction:", "MMem[[PC]]", instr
+                        )
+                    })
+            }
+            Event::Decode { ref opcode, .. } => {
+                writeln!(output, "{:23}{:>4}", "Opcode:", format!("{:?}", opcode))
+            }
+            Event::Immediate { n_bits, value } => {
+                writeln!(output, "{:30}{:#010X}", format!("Immed{}: ", n_bits), value)
+            }
+            Event::RegisterRead {
+                operand,
+                register,
+                value,
+            } => writeln!(
+                output,
+                "{:23}[{}] = {:#010X}",
+                format!("{:?}:", operand),
+                get_name(register),
+                value
+            ),
+            Event::RegisterWrite {
+                register, value, ..
+            } => writeln!(
+                output,
+                "{:23}[{}] = {:#010X}",
+                "Result:",
+                get_name(register),
+                value
+            ),
+            Event::MemoryRead { address, value } => {
+                writeln!(
+                    output,
+                    "{:11}MMem[{:#010X}] = {:#010X}",
+                    "Ptr:", address, value
+                )
+            }
+            Event::MemoryWrite { address, value, .. } => {
+                writeln!(
+                    output,
+                    "{:11}MMem[{:#010X}] = {:#010X}",
+                    "Mem:", address, value
+                )
+            }
+            Event::FlagUpdate { after, .. } => writeln!(
+                output,
+                "{:30}{}{}{}{}",
+                "NZCV:",
+                after.get_n() as u8,
+                after.get_z() as u8,
+                after.get_c() as u8,
+                after.get_v() as u8
+            ),
+            Event::Branch { ref cc, taken, .. } => {
+                writeln!(output, "{:25}{:?} = {}", "Cc:", cc, taken)
+            }
+            Event::Skip { ref cc } => writeln!(output, "{:25}{:?} = false", "Cc:", cc),
+            Event::Retire { .. } => Ok(()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn trace(event: Event) -> String {
+        let mut tracer = ConsoleTracer::with_output(Vec::new());
+        tracer.trace(&event);
+        String::from_utf8(tracer.output).unwrap()
+    }
+
+    #[test]
+    fn test_fetch() {
+        assert_eq!(
+            trace(Event::Fetch {
+                pc: 0x1,
+                instr: 0x23100002
+            }),
+            "----------------------------------------\n\
+             Pc:                           0x00000001 \n\
+             Instruction:     MMem[[PC]] = 0x23100002 \n"
+        );
+    }
+
+    #[test]
+    fn test_decode_aligns_opcodes() {
+        let mov = trace(Event::Decode {
+            form: Form::Five,
+            opcode: Opcode::MOV,
+        });
+        let b = trace(Event::Decode {
+            form: Form::Six,
+            opcode: Opcode::B,
+        });
+        assert_eq!(mov, "Opcode:                 MOV\n");
+        assert_eq!(b.len(), mov.len());
+    }
+
+    #[test]
+    fn test_register_read() {
+        assert_eq!(
+            trace(Event::RegisterRead {
+                operand: Operand::Rx,
+                register: 0x2,
+                value: 0x3
+            }),
+            "Rx:                    [R2] = 0x00000003\n"
+        );
+    }
+
+}